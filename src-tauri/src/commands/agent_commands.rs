@@ -1,22 +1,85 @@
 use crate::services::AgentService;
-use serde::{Deserialize, Serialize};
-use tauri::State;
+use crate::services::agent_service::TestGenerationRequest;
+use crate::services::agent_events::AgentEventEmitter;
+use crate::services::framework_detection::resolve_test_framework;
+use crate::services::llm_provider::{ProviderConfig, ProviderKind};
+use crate::services::secret_store::ApiKeyStore;
+use crate::services::source_chunker::{split_source, DEFAULT_CHUNK_SIZE};
+use crate::services::structured_output::GeneratedTestSuite;
+use tauri::{AppHandle, State};
+use std::path::PathBuf;
 use std::process::Command;
+use uuid::Uuid;
 
-/// Represents a chat message with role and content
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ChatMessage {
-    pub role: String,    // "user" or "assistant"
-    pub content: String, // The message content
-}
-
-/// Initialize the Agent service with an OpenAI API key
+/// Initialize the Agent service with an API key and optional provider settings
+///
+/// The provider, model and base URL are remembered, and used with the stored API key to
+/// initialize the Agent service again on the next start.
+///
+/// # Arguments
+/// * `api_key` - API key for the provider; when empty, the key stored with `set_api_key` is used
+///   (may stay empty for self-hosted endpoints)
+/// * `provider` - `anthropic` (default), `openai`, `openai_compatible` or `mock`
+/// * `model` - Model name; the provider default is used when omitted
+/// * `base_url` - Base URL of the API, required for `openai_compatible`
+/// * `max_concurrent_requests` - How many LLM requests may run at once
+/// * `max_retries` - How many times a rate-limited, overloaded or network-failed call is retried
+/// * `fixtures_dir` - Directory of recorded responses, required for `mock` and for recording
+/// * `record_fixtures` - Save every response of a real provider as a fixture in `fixtures_dir`;
+///   responses served from the response cache are not recorded
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn initialize_agent(
     api_key: String,
+    provider: Option<String>,
+    model: Option<String>,
+    base_url: Option<String>,
+    max_concurrent_requests: Option<usize>,
+    max_retries: Option<u32>,
+    fixtures_dir: Option<String>,
+    record_fixtures: Option<bool>,
+    agent_service: State<'_, AgentService>,
+    api_key_store: State<'_, ApiKeyStore>,
+) -> Result<(), String> {
+    let kind = match provider {
+        Some(name) => ProviderKind::parse(&name)?,
+        None => ProviderKind::Anthropic,
+    };
+
+    let api_key = if api_key.trim().is_empty() {
+        api_key_store.get(kind).ok().flatten().unwrap_or_default()
+    } else {
+        api_key
+    };
+
+    if let Some(limit) = max_concurrent_requests {
+        agent_service.set_max_concurrent_requests(limit).await?;
+    }
+
+    if let Some(max_retries) = max_retries {
+        agent_service.set_max_retries(max_retries).await;
+    }
+
+    let config = ProviderConfig {
+        kind,
+        api_key,
+        model,
+        base_url,
+        fixtures_dir,
+        record_fixtures: record_fixtures.unwrap_or(false),
+    };
+    agent_service.initialize(config.clone()).await?;
+    api_key_store.save_settings(&config);
+    Ok(())
+}
+
+/// Set how many LLM requests the Agent service may run at the same time
+#[tauri::command]
+pub async fn set_agent_concurrency_limit(
+    limit: usize,
     agent_service: State<'_, AgentService>,
 ) -> Result<(), String> {
-    agent_service.initialize(api_key).await
+    agent_service.set_max_concurrent_requests(limit).await
 }
 
 /// Check if the Agent service is initialized
@@ -101,38 +164,229 @@ pub async fn check_package_installation(language: String) -> Result<bool, String
 }
 
 /// Simple invocation of the LLM with a prompt
+///
+/// The request can be cancelled with `cancel_agent_request` using `request_id`.
 #[tauri::command]
 pub async fn agent_simple_invoke(
     prompt: String,
+    request_id: Option<String>,
     agent_service: State<'_, AgentService>,
 ) -> Result<String, String> {
-    let response = agent_service.simple_invoke(prompt).await?;
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let response = agent_service
+        .run_cancellable(&request_id, agent_service.simple_invoke(prompt))
+        .await?;
     Ok(response.content)
 }
 
 /// Create a chain with a system prompt and user input
+///
+/// The request can be cancelled with `cancel_agent_request` using `request_id`.
 #[tauri::command]
 pub async fn agent_chain_invoke(
     system_prompt: String,
     user_input: String,
+    request_id: Option<String>,
     agent_service: State<'_, AgentService>,
 ) -> Result<String, String> {
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
     let response = agent_service
-        .create_chain_response(system_prompt, user_input)
+        .run_cancellable(&request_id, agent_service.create_chain_response(system_prompt, user_input))
         .await?;
     Ok(response.content)
 }
 
 /// Generate tests for provided code
+///
+/// When `stream` is true, the output is also emitted incrementally as `agent:token`
+/// events followed by `agent:done` or `agent:error`, all tagged with `request_id`
+/// (a UUID is generated if none is provided). Either way the request can be
+/// cancelled with `cancel_agent_request`.
+///
+/// Code longer than `max_chunk_chars` is split into chunks of top-level items, or of the
+/// members of a class or impl too large for one chunk, that are generated separately; the
+/// result is then the merged test code rather than the raw output.
+///
+/// Responses are cached by a hash of the provider, model and rendered prompt; pass
+/// `use_cache: false` to skip the lookup and get a fresh response.
+///
+/// Likely secrets in the code are replaced with placeholders first and reported in an
+/// `agent:redaction` event.
+///
+/// # Arguments
+/// * `project_dir` - Root of the project the code is from; when `test_framework` is not given,
+///   the framework and its version are detected from the project's manifests
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn generate_tests(
     code: String,
     language: String,
     test_framework: Option<String>,
+    project_dir: Option<String>,
+    stream: Option<bool>,
+    request_id: Option<String>,
+    max_chunk_chars: Option<usize>,
+    use_cache: Option<bool>,
+    app_handle: AppHandle,
     agent_service: State<'_, AgentService>,
 ) -> Result<String, String> {
-    let response = agent_service
-        .generate_tests(code, language, test_framework)
-        .await?;
-    Ok(response.content)
-} 
\ No newline at end of file
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let stream = stream.unwrap_or(false);
+    let emitter = AgentEventEmitter::new(app_handle, Some(request_id.clone()));
+    let code = redact_code(&code, "code", &emitter, &agent_service);
+    let chunks = split_source(&code, &language, max_chunk_chars.unwrap_or(DEFAULT_CHUNK_SIZE));
+    let project_dir = project_dir.map(PathBuf::from);
+    let (test_framework, framework_version) = match &project_dir {
+        Some(dir) => resolve_test_framework(dir, None, &language, test_framework),
+        None => (test_framework, None),
+    };
+    let request = TestGenerationRequest {
+        code,
+        language,
+        test_framework,
+        framework_version,
+        project_dir,
+        bypass_cache: !use_cache.unwrap_or(true),
+        ..Default::default()
+    };
+
+    if !stream && chunks.len() <= 1 {
+        let response = agent_service
+            .run_cancellable(&request_id, agent_service.generate_tests(request))
+            .await?;
+        return Ok(response.content);
+    }
+
+    let on_token = |token: &str| {
+        if stream {
+            emitter.token(token);
+        }
+    };
+    let result = if chunks.len() > 1 {
+        agent_service
+            .run_cancellable(&request_id, agent_service.generate_tests_chunked(request, chunks, on_token))
+            .await
+    } else {
+        agent_service
+            .run_cancellable(&request_id, agent_service.generate_tests_streaming(request, on_token))
+            .await
+    };
+
+    match result {
+        Ok(response) => {
+            if stream {
+                emitter.done(&response.content, None);
+            }
+            Ok(response.content)
+        }
+        Err(e) => {
+            if stream {
+                emitter.error(&e);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Generate tests as a structured suite: the test file, its suggested path, the functions it
+/// covers, a description of each test case and the dev-dependencies it needs
+///
+/// When `stream` is true, the raw JSON output is also emitted as `agent:token` events followed
+/// by `agent:done` or `agent:error`, all tagged with `request_id`. Either way the request can be
+/// cancelled with `cancel_agent_request`. Likely secrets in the code are redacted first, as in
+/// `generate_tests`.
+///
+/// # Arguments
+/// * `source_path` - Path of the source file relative to the project root, used to suggest where
+///   the test file goes
+/// * `project_dir` - Root of the project; when `test_framework` is not given, the framework and
+///   its version are detected from the manifests nearest to `source_path`
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_test_suite(
+    code: String,
+    language: String,
+    test_framework: Option<String>,
+    source_path: Option<String>,
+    project_dir: Option<String>,
+    stream: Option<bool>,
+    request_id: Option<String>,
+    use_cache: Option<bool>,
+    app_handle: AppHandle,
+    agent_service: State<'_, AgentService>,
+) -> Result<GeneratedTestSuite, String> {
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let stream = stream.unwrap_or(false);
+    let emitter = AgentEventEmitter::new(app_handle, Some(request_id.clone()));
+    let code = redact_code(&code, source_path.as_deref().unwrap_or("code"), &emitter, &agent_service);
+    let project_dir = project_dir.map(PathBuf::from);
+    let (test_framework, framework_version) = match &project_dir {
+        Some(dir) => resolve_test_framework(dir, source_path.as_deref(), &language, test_framework),
+        None => (test_framework, None),
+    };
+    let request = TestGenerationRequest {
+        code,
+        language,
+        test_framework,
+        framework_version,
+        source_path,
+        project_dir,
+        bypass_cache: !use_cache.unwrap_or(true),
+        ..Default::default()
+    };
+
+    let on_token = |token: &str| {
+        if stream {
+            emitter.token(token);
+        }
+    };
+    let result = agent_service
+        .run_cancellable(&request_id, agent_service.generate_test_suite(request, on_token))
+        .await;
+
+    match result {
+        Ok((suite, response)) => {
+            if stream {
+                emitter.done(&response.content, None);
+            }
+            Ok(suite)
+        }
+        Err(e) => {
+            if stream {
+                emitter.error(&e);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Replaces likely secrets in code, reporting any it finds to the frontend
+fn redact_code(code: &str, location: &str, emitter: &AgentEventEmitter, agent_service: &AgentService) -> String {
+    let mut session = agent_service.redactor().session();
+    let code = session.redact(code, location);
+    let report = session.finish();
+    if !report.is_empty() {
+        emitter.redaction(&report);
+    }
+    code
+}
+
+/// Cancel an in-flight agent request
+///
+/// # Returns
+/// * `Result<bool, String>` - false if no request with that ID is running
+#[tauri::command]
+pub fn cancel_agent_request(
+    request_id: String,
+    agent_service: State<'_, AgentService>,
+) -> Result<bool, String> {
+    let cancelled = agent_service.cancel_request(&request_id)?;
+    log::info!("Cancel requested for agent request {}: {}", request_id, cancelled);
+    Ok(cancelled)
+}
+
+/// List the IDs of all in-flight agent requests
+#[tauri::command]
+pub fn list_agent_requests(agent_service: State<'_, AgentService>) -> Vec<String> {
+    agent_service.active_requests()
+}
@@ -0,0 +1,87 @@
+use crate::services::AgentService;
+use crate::services::llm_provider::{ProviderConfig, ProviderKind};
+use crate::services::secret_store::ApiKeyStore;
+use tauri::State;
+
+/// Store the API key for a provider and use it from now on
+///
+/// The key is kept in the OS keychain, or encrypted in the app data directory where there is no
+/// usable keychain, and is used again on the next start. If the Agent service already uses the
+/// provider, it is re-initialized with the new key and its model and base URL are kept; if it
+/// isn't initialized yet, it is initialized with the provider's defaults.
+///
+/// # Arguments
+/// * `api_key` - API key for the provider
+/// * `provider` - `anthropic` (default) or `openai`
+#[tauri::command]
+pub async fn set_api_key(
+    api_key: String,
+    provider: Option<String>,
+    agent_service: State<'_, AgentService>,
+    api_key_store: State<'_, ApiKeyStore>,
+) -> Result<(), String> {
+    let kind = parse_provider(provider)?;
+    api_key_store.set(kind, &api_key)?;
+
+    let api_key = api_key.trim().to_string();
+    let config = match agent_service.provider_config().await {
+        Some(current) if current.kind == kind => ProviderConfig { api_key, ..current },
+        // Another provider stays in use; the key is picked up when the user switches to this one
+        Some(_) => return Ok(()),
+        None => ProviderConfig {
+            kind,
+            api_key,
+            model: None,
+            base_url: None,
+            fixtures_dir: None,
+            record_fixtures: false,
+        },
+    };
+    agent_service.initialize(config.clone()).await?;
+    api_key_store.save_settings(&config);
+    Ok(())
+}
+
+/// Remove the stored API key for a provider
+///
+/// Returns false if no key was stored. An initialized Agent service keeps working until the
+/// app restarts.
+///
+/// # Arguments
+/// * `provider` - `anthropic` (default) or `openai`
+#[tauri::command]
+pub fn clear_api_key(
+    provider: Option<String>,
+    api_key_store: State<'_, ApiKeyStore>,
+) -> Result<bool, String> {
+    api_key_store.clear(parse_provider(provider)?)
+}
+
+/// Remove the stored API keys of every provider
+///
+/// Use this when the key store can no longer be read, e.g. after its key file was deleted:
+/// storing a key fails rather than silently dropping the other providers' keys. An
+/// initialized Agent service keeps working until the app restarts.
+#[tauri::command]
+pub fn reset_api_key_store(api_key_store: State<'_, ApiKeyStore>) -> Result<(), String> {
+    api_key_store.reset()
+}
+
+/// Check whether an API key is stored for a provider, without exposing it
+///
+/// # Arguments
+/// * `provider` - `anthropic` (default) or `openai`
+#[tauri::command]
+pub fn has_api_key(
+    provider: Option<String>,
+    api_key_store: State<'_, ApiKeyStore>,
+) -> Result<bool, String> {
+    Ok(api_key_store.get(parse_provider(provider)?)?.is_some())
+}
+
+fn parse_provider(provider: Option<String>) -> Result<ProviderKind, String> {
+    match provider {
+        Some(name) => ProviderKind::parse(&name),
+        None => Ok(ProviderKind::Anthropic),
+    }
+}
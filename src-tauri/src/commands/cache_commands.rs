@@ -0,0 +1,43 @@
+use crate::services::AgentService;
+use crate::services::response_cache::{CacheSettings, CacheStats};
+use std::time::Duration;
+use tauri::State;
+
+/// Number and total size of the cached test generation responses
+#[tauri::command]
+pub fn get_response_cache_stats(agent_service: State<'_, AgentService>) -> CacheStats {
+    agent_service.response_cache().stats()
+}
+
+/// Remove every cached test generation response
+///
+/// # Returns
+/// * `Result<usize, String>` - How many responses were removed
+#[tauri::command]
+pub fn clear_response_cache(agent_service: State<'_, AgentService>) -> Result<usize, String> {
+    agent_service.response_cache().clear()
+}
+
+/// Change how long cached responses are used and how large the cache may grow
+///
+/// # Arguments
+/// * `ttl_hours` - How long a cached response is used; unchanged when omitted
+/// * `max_megabytes` - Size limit before the oldest responses are evicted; unchanged when omitted
+#[tauri::command]
+pub fn configure_response_cache(
+    ttl_hours: Option<u64>,
+    max_megabytes: Option<u64>,
+    agent_service: State<'_, AgentService>,
+) -> CacheStats {
+    let cache = agent_service.response_cache();
+    let current = cache.stats();
+    cache.configure(CacheSettings {
+        ttl: ttl_hours
+            .map(|hours| Duration::from_secs(hours.saturating_mul(60 * 60)))
+            .unwrap_or(Duration::from_secs(current.ttl_secs)),
+        max_bytes: max_megabytes
+            .map(|megabytes| megabytes.saturating_mul(1024 * 1024))
+            .unwrap_or(current.max_bytes),
+    });
+    cache.stats()
+}
@@ -0,0 +1,160 @@
+use crate::services::AgentService;
+use crate::services::agent_events::AgentEventEmitter;
+use crate::services::chat_sessions::{ChatMessage, ChatSession, ChatSessionStore, ChatSessionSummary};
+use std::path::Path;
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+/// Create a chat session
+///
+/// # Arguments
+/// * `title` - Session title; taken from the first message when omitted
+/// * `project_dir` - Project the session works on, required for attaching files
+/// * `system_prompt` - Replaces the default test assistant system prompt
+#[tauri::command]
+pub fn create_chat_session(
+    title: Option<String>,
+    project_dir: Option<String>,
+    system_prompt: Option<String>,
+    chat_sessions: State<'_, ChatSessionStore>,
+) -> Result<ChatSession, String> {
+    chat_sessions.create(title, project_dir, system_prompt)
+}
+
+/// List all chat sessions, most recently updated first
+#[tauri::command]
+pub fn list_chat_sessions(chat_sessions: State<'_, ChatSessionStore>) -> Result<Vec<ChatSessionSummary>, String> {
+    chat_sessions.list()
+}
+
+/// Load a chat session with its full message history
+#[tauri::command]
+pub fn get_chat_session(session_id: String, chat_sessions: State<'_, ChatSessionStore>) -> Result<ChatSession, String> {
+    chat_sessions.get(&session_id)
+}
+
+/// Delete a chat session
+///
+/// # Returns
+/// * `Result<bool, String>` - false if no session with that ID exists
+#[tauri::command]
+pub fn delete_chat_session(session_id: String, chat_sessions: State<'_, ChatSessionStore>) -> Result<bool, String> {
+    chat_sessions.delete(&session_id)
+}
+
+/// Append a message to a chat session without invoking the agent
+///
+/// # Arguments
+/// * `role` - `user` or `assistant`
+#[tauri::command]
+pub fn append_chat_message(
+    session_id: String,
+    role: String,
+    content: String,
+    chat_sessions: State<'_, ChatSessionStore>,
+) -> Result<ChatSession, String> {
+    chat_sessions.append(&session_id, ChatMessage { role, content })
+}
+
+/// Attach a project file to a chat session; its current content is sent with every request
+///
+/// # Arguments
+/// * `file` - Path of the file, relative to the session's project directory
+#[tauri::command]
+pub fn attach_file_to_chat_session(
+    session_id: String,
+    file: String,
+    chat_sessions: State<'_, ChatSessionStore>,
+) -> Result<ChatSession, String> {
+    chat_sessions.attach(&session_id, &file)
+}
+
+/// Remove an attached file from a chat session
+#[tauri::command]
+pub fn detach_file_from_chat_session(
+    session_id: String,
+    file: String,
+    chat_sessions: State<'_, ChatSessionStore>,
+) -> Result<ChatSession, String> {
+    chat_sessions.detach(&session_id, &file)
+}
+
+/// Invoke the agent with a session's full history and append its reply
+///
+/// The request can be cancelled with `cancel_agent_request` using `request_id`. Secrets
+/// redacted from the request are reported in an `agent:redaction` event with that ID.
+///
+/// # Returns
+/// * `Result<ChatMessage, String>` - The assistant's reply
+#[tauri::command]
+pub async fn invoke_chat_session(
+    session_id: String,
+    request_id: Option<String>,
+    app_handle: AppHandle,
+    chat_sessions: State<'_, ChatSessionStore>,
+    agent_service: State<'_, AgentService>,
+) -> Result<ChatMessage, String> {
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let emitter = AgentEventEmitter::new(app_handle, Some(request_id.clone()));
+    agent_service
+        .run_cancellable(&request_id, reply_in_session(&session_id, &chat_sessions, &emitter, &agent_service))
+        .await
+}
+
+/// Append a user message to a chat session, then invoke the agent and append its reply
+///
+/// The request can be cancelled with `cancel_agent_request` using `request_id`; the user
+/// message is kept either way.
+///
+/// # Returns
+/// * `Result<ChatMessage, String>` - The assistant's reply
+#[tauri::command]
+pub async fn send_chat_message(
+    session_id: String,
+    content: String,
+    request_id: Option<String>,
+    app_handle: AppHandle,
+    chat_sessions: State<'_, ChatSessionStore>,
+    agent_service: State<'_, AgentService>,
+) -> Result<ChatMessage, String> {
+    chat_sessions.append(
+        &session_id,
+        ChatMessage {
+            role: "user".to_string(),
+            content,
+        },
+    )?;
+    invoke_chat_session(session_id, request_id, app_handle, chat_sessions, agent_service).await
+}
+
+/// Sends a session's redacted history, with its attached files in the system prompt, and stores the reply
+async fn reply_in_session(
+    session_id: &str,
+    chat_sessions: &ChatSessionStore,
+    emitter: &AgentEventEmitter,
+    agent_service: &AgentService,
+) -> Result<ChatMessage, String> {
+    let session = chat_sessions.get(session_id)?;
+    if session.messages.last().map_or(true, |message| message.role != "user") {
+        return Err("The chat session has no user message to reply to".to_string());
+    }
+
+    let mut redaction = agent_service.redactor().session();
+    let (system_prompt, history) = chat_sessions.request(&session, &mut redaction);
+
+    let report = redaction.finish();
+    if !report.is_empty() {
+        log::info!("Redacted {} likely secret(s) from chat session {}", report.redactions.len(), session_id);
+        emitter.redaction(&report);
+    }
+
+    let project_dir = session.project_dir.as_deref().map(Path::new);
+    let response = agent_service.chat(system_prompt, &history, project_dir).await?;
+
+    let reply = ChatMessage {
+        role: "assistant".to_string(),
+        content: response.content,
+    };
+    chat_sessions.append(session_id, reply.clone())?;
+    Ok(reply)
+}
@@ -1,13 +1,34 @@
 use crate::services::file_service::FileService;
 use crate::services::AgentService;
-use std::path::PathBuf;
+use crate::services::agent_service::TestGenerationRequest;
+use crate::services::context_collector::{ContextCollector, DEFAULT_CONTEXT_BUDGET};
+use crate::services::coverage::{format_coverage_gaps, load_project_coverage, CoverageFormat, CoverageReport, FileCoverage};
+use crate::services::agent_events::AgentEventEmitter;
+use crate::services::agent_tools::{ProjectTools, DEFAULT_TOOL_STEPS, MAX_TOOL_STEPS};
+use crate::services::framework_detection::{detect_test_framework, resolve_test_framework, DetectedFramework};
+use crate::services::batch_generation::{
+    language_for_file, select_batch_files, BatchFileResult, BatchFileStatus, BatchSummary, DEFAULT_BATCH_CONCURRENCY,
+    MAX_BATCH_CONCURRENCY,
+};
+use crate::services::source_chunker::{split_source, DEFAULT_CHUNK_SIZE};
+use crate::services::symbol_locator::{file_context, locate_symbol};
+use crate::services::syntax_validation::{check_syntax, format_syntax_errors, is_supported, SyntaxError};
+use crate::services::test_extraction::extract_test_code;
+use crate::services::test_merge::merge_tests;
+use crate::services::redaction::RedactionSession;
+use crate::services::test_review::TestReview;
+use crate::services::test_runner::{run_test_file, TestRunOutcome};
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use tauri::State;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::{AppHandle, State};
 use std::fs;
 use log::info;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use futures::stream::{self, StreamExt};
+use uuid::Uuid;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct FileAnalysisResult {
     pub source_files: HashMap<String, Option<String>>,
     pub file_count: usize,
@@ -47,14 +68,524 @@ pub async fn find_test_files(
     }
 }
 
+/// Parse test code with the grammar for its language and report syntax errors
+///
+/// # Returns
+/// * `Result<Vec<SyntaxError>, String>` - Errors with 1-based line and column; empty if the code
+///   parses or no grammar is available for the language
+#[tauri::command]
+pub fn check_test_syntax(code: String, language: String) -> Result<Vec<SyntaxError>, String> {
+    check_syntax(&code, &language)
+}
+
+/// Detect the test framework a project uses for a language from its manifests
+///
+/// # Arguments
+/// * `source_file` - Path relative to `directory`; manifests closer to it win, e.g. in a monorepo
+///
+/// # Returns
+/// * `Result<Option<DetectedFramework>, String>` - The framework, its version and the manifest it
+///   was found in, or None if the project doesn't name one
+#[tauri::command]
+pub fn detect_project_test_framework(
+    directory: String,
+    language: String,
+    source_file: Option<String>,
+) -> Result<Option<DetectedFramework>, String> {
+    let dir_path = PathBuf::from(&directory);
+    if !dir_path.is_dir() {
+        return Err(format!("Directory does not exist: {}", directory));
+    }
+    Ok(detect_test_framework(&dir_path, source_file.as_deref(), &language))
+}
+
+/// Coverage of a project's source files, read from one coverage report
+#[derive(Serialize, Debug)]
+pub struct ProjectCoverage {
+    pub format: CoverageFormat,
+    pub report_path: String,
+    /// Coverage of each source file of the analysis the report covers, keyed like `source_files`
+    pub files: HashMap<String, FileCoverage>,
+}
+
+/// Read a coverage report and map it onto the source files found by `find_test_files`
+///
+/// Reads lcov, Cobertura XML and coverage.py JSON reports.
+///
+/// # Arguments
+/// * `report_path` - Report to read, relative to `directory`; the newest report found in the
+///   project is used when omitted
+///
+/// # Returns
+/// * `Result<Option<ProjectCoverage>, String>` - None if no report is given and none is found
+#[tauri::command]
+pub fn load_coverage(
+    directory: String,
+    analysis: FileAnalysisResult,
+    report_path: Option<String>,
+) -> Result<Option<ProjectCoverage>, String> {
+    let Some(report) = load_project_coverage(Path::new(&directory), report_path.as_deref())? else {
+        return Ok(None);
+    };
+
+    let files = report.map_onto(analysis.source_files.keys());
+    info!(
+        "Coverage report {} covers {} of {} source files",
+        report.report_path,
+        files.len(),
+        analysis.source_files.len()
+    );
+    Ok(Some(ProjectCoverage {
+        format: report.format,
+        report_path: report.report_path,
+        files,
+    }))
+}
+
+/// Upper bound on repair iterations, whatever the caller asks for
+const MAX_REPAIR_ATTEMPTS: u32 = 5;
+
+/// How a test should be generated and written
+struct TestGenerationOptions {
+    /// Emit `agent:token` / `agent:done` events while generating
+    stream: bool,
+    /// Number of run-and-repair iterations after writing the test file (0 disables the loop)
+    repair_attempts: u32,
+    /// Include signatures from the file's local imports in the prompt
+    include_dependencies: bool,
+    /// Add missing cases to an existing test file instead of replacing it
+    augment: bool,
+    /// Source files longer than this many characters are generated in chunks
+    max_chunk_chars: usize,
+    /// Reuse a cached response to an identical generation request
+    use_cache: bool,
+    /// Ask for a structured result and write the test where the agent suggests
+    structured: bool,
+    /// Only generate tests for this function, method or class of the source file
+    symbol: Option<String>,
+    /// Coverage of the existing tests, used to point the agent at what they miss
+    coverage: Option<CoverageReport>,
+    /// Number of project tool calls the agent may make before answering (0 disables tools)
+    tool_steps: usize,
+}
+
+/// Loads the project's newest coverage report when coverage-guided generation is asked for
+///
+/// A project without a report is generated for as usual.
+fn load_coverage_if(use_coverage: Option<bool>, directory: &str) -> Result<Option<CoverageReport>, String> {
+    if !use_coverage.unwrap_or(false) {
+        return Ok(None);
+    }
+    let coverage = load_project_coverage(Path::new(directory), None)?;
+    if coverage.is_none() {
+        log::warn!("No coverage report found in {}; generating without coverage", directory);
+    }
+    Ok(coverage)
+}
+
 // In Tauri v2, we need to use normal function parameters - the renaming is handled by Tauri itself
+//
+// When `stream` is true, generation progress is emitted as `agent:token` events and the
+// outcome as `agent:done` (with the written test path) or `agent:error`, tagged with `request_id`.
+// The request can be cancelled with `cancel_agent_request`; nothing is written once cancelled.
+//
+// When `repair_attempts` is set, the written test file is run with the project's test framework
+// and any failures are fed back to the agent for up to that many repair iterations. Each run is
+// reported as an `agent:test-run` event. If the tests can't be run at all (e.g. the runner isn't
+// installed), that is reported as a failed run and the written file is returned unrepaired.
+//
+// Signatures from the source file's local imports are added to the prompt unless
+// `include_dependencies` is false.
+//
+// When `augment` is true and the source file already has a test file, the existing tests are
+// sent to the agent, which is asked only for missing cases; those are merged into the existing
+// file so hand-written tests are kept.
+//
+// Source files longer than `max_chunk_chars` are split into chunks of top-level items (or of
+// the members of a class or impl too large for one chunk), each generated with the file's
+// header, and the resulting tests are merged into one test file.
+//
+// When `test_framework` is not given, the framework is detected from the project's manifests
+// (package.json and runner configs, pytest configs, Cargo.toml, go.mod, pom.xml or build.gradle)
+// and its version is passed to the agent along with it.
+//
+// When `use_coverage` is true, the newest lcov, Cobertura or coverage.py JSON report in the
+// project is read and the source file's uncovered lines and branches are sent to the agent, so
+// the new tests raise coverage instead of repeating what is already tested. Since the new tests
+// only fill the gaps, `use_coverage` implies `augment`: they are merged into the existing test file.
+//
+// Identical generation requests are answered from the response cache unless `use_cache` is false.
+//
+// Likely secrets in the source file, its dependency context and existing tests (API keys, private
+// keys, high-entropy strings, `.env`-style assignments and user patterns) are replaced with
+// placeholders before the prompt is built, and reported in an `agent:redaction` event.
+//
+// When `structured` is true, the agent is asked for a JSON test suite that also names the test
+// file's location, which is used for new test files if it is a test path inside the project.
+// Files generated in chunks always use plain output.
+//
+// When `use_tools` is true, the agent can read files, list directories, search the project and
+// find existing tests before answering, up to `max_tool_steps` calls (8 by default). The tools
+// can't reach outside `directory`, their output is redacted like the source file, and each call
+// is reported as an `agent:tool-call` event. Tool mode sends the whole file in one request and
+// ignores `structured` and the response cache.
+//
+// Generated code is parsed before it is written. If it has syntax errors and `repair_attempts`
+// is set, the errors are sent to the agent for repair (using the same attempt budget);
+// otherwise the request fails with the line and column of each error and nothing is written.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn generate_and_write_test(
     directory: String,
     source_file: String,
     language: String,
     test_framework: Option<String>,
+    stream: Option<bool>,
+    request_id: Option<String>,
+    repair_attempts: Option<u32>,
+    include_dependencies: Option<bool>,
+    augment: Option<bool>,
+    max_chunk_chars: Option<usize>,
+    use_cache: Option<bool>,
+    structured: Option<bool>,
+    use_coverage: Option<bool>,
+    use_tools: Option<bool>,
+    max_tool_steps: Option<usize>,
+    app_handle: AppHandle,
     agent_service: State<'_, AgentService>,
+) -> Result<String, String> {
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let emitter = AgentEventEmitter::new(app_handle, Some(request_id.clone()));
+    let options = TestGenerationOptions {
+        stream: stream.unwrap_or(false),
+        repair_attempts: repair_attempts.unwrap_or(0).min(MAX_REPAIR_ATTEMPTS),
+        include_dependencies: include_dependencies.unwrap_or(true),
+        // Tests that only cover the gaps would replace the tests that cover the rest
+        augment: augment.unwrap_or(false) || use_coverage.unwrap_or(false),
+        max_chunk_chars: max_chunk_chars.unwrap_or(DEFAULT_CHUNK_SIZE),
+        use_cache: use_cache.unwrap_or(true),
+        structured: structured.unwrap_or(false),
+        symbol: None,
+        coverage: load_coverage_if(use_coverage, &directory)?,
+        tool_steps: match use_tools.unwrap_or(false) {
+            true => max_tool_steps.unwrap_or(DEFAULT_TOOL_STEPS).min(MAX_TOOL_STEPS),
+            false => 0,
+        },
+    };
+
+    let result = agent_service
+        .run_cancellable(
+            &request_id,
+            write_generated_test(directory, source_file, language, test_framework, &options, &emitter, &agent_service),
+        )
+        .await;
+
+    if options.stream {
+        if let Err(e) = &result {
+            emitter.error(e);
+        }
+    }
+    result
+}
+
+// Generates and writes tests for many files of a project, `concurrency` files at a time.
+//
+// `analysis` is the result of `find_test_files`; every source file without a test is generated
+// unless `files` selects a subset. Selected files that already have tests get the missing cases
+// merged in, as with `augment`, so hand-written tests are kept. Each file's language is detected
+// from its extension unless `language` is given.
+//
+// Every file runs as its own agent request, which can be cancelled with `cancel_agent_request`
+// without stopping the rest of the batch; cancelling `batch_id` stops the whole batch. Each file
+// is reported as an `agent:batch-progress` event when it starts and when it finishes, and the
+// summary of successes and failures as `agent:batch-done`, all tagged with `batch_id`.
+//
+// With `use_coverage`, the project's coverage report is read once and each file's uncovered
+// lines and branches are targeted, as in `generate_and_write_test`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_tests_for_project(
+    directory: String,
+    analysis: FileAnalysisResult,
+    files: Option<Vec<String>>,
+    language: Option<String>,
+    test_framework: Option<String>,
+    concurrency: Option<usize>,
+    batch_id: Option<String>,
+    repair_attempts: Option<u32>,
+    include_dependencies: Option<bool>,
+    use_cache: Option<bool>,
+    use_coverage: Option<bool>,
+    app_handle: AppHandle,
+    agent_service: State<'_, AgentService>,
+) -> Result<BatchSummary, String> {
+    let batch_id = batch_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let files = select_batch_files(&analysis.source_files, files);
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_BATCH_CONCURRENCY)
+        .clamp(1, MAX_BATCH_CONCURRENCY);
+    info!("Generating tests for {} files in {} ({} at a time)", files.len(), directory, concurrency);
+    // The report is read once and shared by every file of the batch
+    let coverage = load_coverage_if(use_coverage, &directory)?;
+
+    let job = BatchJob {
+        directory,
+        language,
+        test_framework,
+        options: TestGenerationOptions {
+            stream: false,
+            repair_attempts: repair_attempts.unwrap_or(0).min(MAX_REPAIR_ATTEMPTS),
+            include_dependencies: include_dependencies.unwrap_or(true),
+            augment: true,
+            max_chunk_chars: DEFAULT_CHUNK_SIZE,
+            use_cache: use_cache.unwrap_or(true),
+            structured: false,
+            symbol: None,
+            coverage,
+            tool_steps: 0,
+        },
+        emitter: AgentEventEmitter::new(app_handle.clone(), Some(batch_id.clone())),
+        app_handle,
+        agent_service: &agent_service,
+        completed: AtomicUsize::new(0),
+        total: files.len(),
+    };
+
+    let batch = async {
+        let mut pending = stream::iter(files)
+            .map(|source_file| job.generate(source_file))
+            .buffer_unordered(concurrency);
+
+        let mut results = Vec::with_capacity(job.total);
+        while let Some(result) = pending.next().await {
+            let completed = job.completed.fetch_add(1, Ordering::Relaxed) + 1;
+            job.emitter.batch_progress(&result, completed, job.total);
+            results.push(result);
+        }
+        Ok(BatchSummary::new(batch_id.clone(), results))
+    };
+
+    let result = agent_service.run_cancellable(&batch_id, batch).await;
+    match &result {
+        Ok(summary) => {
+            info!(
+                "Batch {} finished: {} succeeded, {} failed",
+                batch_id, summary.succeeded, summary.failed
+            );
+            job.emitter.batch_done(summary);
+        }
+        Err(e) => job.emitter.error(e),
+    }
+    result
+}
+
+/// Settings and progress shared by the files of a batch job
+struct BatchJob<'a> {
+    directory: String,
+    /// Language of every file; detected per file when not set
+    language: Option<String>,
+    test_framework: Option<String>,
+    options: TestGenerationOptions,
+    /// Emits the batch's progress events
+    emitter: AgentEventEmitter,
+    app_handle: AppHandle,
+    agent_service: &'a AgentService,
+    /// Files that have finished so far
+    completed: AtomicUsize,
+    total: usize,
+}
+
+impl BatchJob<'_> {
+    /// Generates the test for one file of the batch as its own cancellable request
+    async fn generate(&self, source_file: String) -> BatchFileResult {
+        let mut file = BatchFileResult {
+            source_file,
+            request_id: Uuid::new_v4().to_string(),
+            status: BatchFileStatus::Started,
+            test_file_path: None,
+            error: None,
+        };
+        self.emitter
+            .batch_progress(&file, self.completed.load(Ordering::Relaxed), self.total);
+
+        let language = self
+            .language
+            .clone()
+            .or_else(|| language_for_file(&file.source_file).map(str::to_string));
+        let result = match language {
+            Some(language) => {
+                let file_emitter = AgentEventEmitter::new(self.app_handle.clone(), Some(file.request_id.clone()));
+                self.agent_service
+                    .run_cancellable(
+                        &file.request_id,
+                        write_generated_test(
+                            self.directory.clone(),
+                            file.source_file.clone(),
+                            language,
+                            self.test_framework.clone(),
+                            &self.options,
+                            &file_emitter,
+                            self.agent_service,
+                        ),
+                    )
+                    .await
+            }
+            None => Err(format!("Could not detect the language of {}", file.source_file)),
+        };
+
+        match result {
+            Ok(test_file_path) => {
+                file.status = BatchFileStatus::Succeeded;
+                file.test_file_path = Some(test_file_path);
+            }
+            Err(e) => {
+                log::warn!("Failed to generate tests for {}: {}", file.source_file, e);
+                file.status = BatchFileStatus::Failed;
+                file.error = Some(e);
+            }
+        }
+        file
+    }
+}
+
+// Reviews a source file's existing tests and returns what could be better: branches of the
+// source no test takes, weak assertions, brittle mocks and suggested new cases, each with the
+// line it refers to (a source line for branches and cases, a test line for assertions and mocks).
+//
+// `test_file` is the match `find_test_files` found for `source_file`; when omitted, the
+// conventional test locations for `source_file` are checked. The language is detected from the source file's extension and the test
+// framework from the project's manifests unless given. The review can be cancelled with
+// `cancel_agent_request` using `request_id`. Likely secrets in both files are redacted first, as
+// in `generate_and_write_test`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn review_test_file(
+    directory: String,
+    source_file: String,
+    test_file: Option<String>,
+    language: Option<String>,
+    test_framework: Option<String>,
+    request_id: Option<String>,
+    app_handle: AppHandle,
+    agent_service: State<'_, AgentService>,
+) -> Result<TestReview, String> {
+    let file_service = FileService::new();
+    let dir_path = PathBuf::from(&directory);
+    if !file_service.path_exists(&dir_path) {
+        return Err(format!("Directory does not exist: {}", dir_path.display()));
+    }
+
+    let language = match language.or_else(|| language_for_file(&source_file).map(str::to_string)) {
+        Some(language) => language,
+        None => return Err(format!("Could not detect the language of {}", source_file)),
+    };
+    let test_file = match test_file.or_else(|| file_service.find_existing_test_file(&dir_path, &source_file)) {
+        Some(test_file) => test_file,
+        None => return Err(format!("{} has no test file to review", source_file)),
+    };
+    let (test_framework, _) = resolve_test_framework(&dir_path, Some(&source_file), &language, test_framework);
+
+    let source_code = fs::read_to_string(dir_path.join(&source_file))
+        .map_err(|e| format!("Failed to read source file: {}", e))?;
+    let test_code = fs::read_to_string(dir_path.join(&test_file))
+        .map_err(|e| format!("Failed to read test file: {}", e))?;
+    info!("Reviewing {} against {}", test_file, source_file);
+
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let mut redaction = agent_service.redactor().session();
+    let source_code = redaction.redact(&source_code, &source_file);
+    let test_code = redaction.redact(&test_code, &test_file);
+    let redaction = redaction.finish();
+    if !redaction.is_empty() {
+        AgentEventEmitter::new(app_handle, Some(request_id.clone())).redaction(&redaction);
+    }
+
+    let (review, _) = agent_service
+        .run_cancellable(
+            &request_id,
+            agent_service.review_tests(source_code, test_code, language, test_framework, Some(&dir_path)),
+        )
+        .await?;
+
+    info!(
+        "Review of {}: {} untested branches, {} weak assertions, {} brittle mocks, {} suggested cases",
+        test_file,
+        review.untested_branches.len(),
+        review.weak_assertions.len(),
+        review.brittle_mocks.len(),
+        review.suggested_cases.len()
+    );
+    Ok(review)
+}
+
+// Generates tests for one function, method or class of a source file and adds them to the
+// file's test file, creating it if needed; existing tests are kept.
+//
+// `symbol_name` may be qualified with its class, impl or receiver type (`Parser.parse`) when the
+// name alone is ambiguous. The rest of the source file is sent as context. The language is
+// detected from the file's extension unless `language` is given. Streaming, cancellation,
+// syntax checking, repair and `use_coverage` work as in `generate_and_write_test`; coverage
+// gaps are limited to the symbol's lines.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_tests_for_symbol(
+    directory: String,
+    source_file: String,
+    symbol_name: String,
+    language: Option<String>,
+    test_framework: Option<String>,
+    stream: Option<bool>,
+    request_id: Option<String>,
+    repair_attempts: Option<u32>,
+    include_dependencies: Option<bool>,
+    use_cache: Option<bool>,
+    use_coverage: Option<bool>,
+    app_handle: AppHandle,
+    agent_service: State<'_, AgentService>,
+) -> Result<String, String> {
+    let language = match language.or_else(|| language_for_file(&source_file).map(str::to_string)) {
+        Some(language) => language,
+        None => return Err(format!("Could not detect the language of {}", source_file)),
+    };
+    let request_id = request_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let emitter = AgentEventEmitter::new(app_handle, Some(request_id.clone()));
+    let options = TestGenerationOptions {
+        stream: stream.unwrap_or(false),
+        repair_attempts: repair_attempts.unwrap_or(0).min(MAX_REPAIR_ATTEMPTS),
+        include_dependencies: include_dependencies.unwrap_or(true),
+        augment: true,
+        max_chunk_chars: DEFAULT_CHUNK_SIZE,
+        use_cache: use_cache.unwrap_or(true),
+        structured: false,
+        symbol: Some(symbol_name),
+        coverage: load_coverage_if(use_coverage, &directory)?,
+        tool_steps: 0,
+    };
+
+    let result = agent_service
+        .run_cancellable(
+            &request_id,
+            write_generated_test(directory, source_file, language, test_framework, &options, &emitter, &agent_service),
+        )
+        .await;
+
+    if options.stream {
+        if let Err(e) = &result {
+            emitter.error(e);
+        }
+    }
+    result
+}
+
+/// Generates a test for the source file and writes it next to the project's other tests
+async fn write_generated_test(
+    directory: String,
+    source_file: String,
+    language: String,
+    test_framework: Option<String>,
+    options: &TestGenerationOptions,
+    emitter: &AgentEventEmitter,
+    agent_service: &AgentService,
 ) -> Result<String, String> {
     let file_service = FileService::new();
     info!("Generating test for {} in {} with language {}", source_file, directory, language);
@@ -72,16 +603,336 @@ pub async fn generate_and_write_test(
         return Err(format!("Directory does not exist: {}", dir_path.display()));
     }
     
+    // Use the framework the project is set up for unless the caller picked one
+    let (test_framework, framework_version) =
+        resolve_test_framework(&dir_path, Some(&source_file), &language, test_framework);
+    
     // Read the source file content
     let full_source_path = dir_path.join(&source_file);
     let source_code = fs::read_to_string(&full_source_path)
         .map_err(|e| format!("Failed to read source file: {}", e))?;
     
-    // Generate test code using the agent
-    let test_response = agent_service.generate_tests(source_code, language, test_framework).await?;
+    // Replace likely secrets before any of the file is put in a prompt; everything below,
+    // including symbol lookup, chunking and repair, works on the redacted text
+    let mut redaction = agent_service.redactor().session();
+    let source_code = redaction.redact(&source_code, &source_file);
     
-    // Write the test file
-    let test_file_path = file_service.write_test_file(&dir_path, &source_file, &test_response.content)?;
+    // Gather signatures of the local code this file imports so tests can build real values
+    let dependency_context = if options.include_dependencies {
+        ContextCollector::new(&dir_path)
+            .collect(&source_file, &source_code, DEFAULT_CONTEXT_BUDGET)
+            .map(|context| redaction.redact(&context, "dependency context"))
+    } else {
+        None
+    };
+
+    // In augment mode, find the tests that already exist so only missing cases are generated
+    let existing_test = if options.augment {
+        file_service
+            .find_existing_test_file(&dir_path, &source_file)
+            .map(|path| file_service.read_file(dir_path.join(&path)).map(|content| (path, content)))
+            .transpose()?
+    } else {
+        None
+    };
+
+    // When targeting one symbol, only its code is tested and the whole file is sent as context
+    let (code, file, lines) = match &options.symbol {
+        Some(symbol) => {
+            let located = locate_symbol(&source_code, &language, symbol)?;
+            info!(
+                "Generating tests for {} ({}) at lines {}-{} of {}",
+                symbol, located.kind, located.start_line, located.end_line, source_file
+            );
+            (
+                located.code,
+                Some(file_context(&source_code, options.max_chunk_chars)),
+                Some((located.start_line, located.end_line)),
+            )
+        }
+        None => (source_code.clone(), None, None),
+    };
+
+    // Point the agent at the lines and branches the existing tests miss
+    let coverage_gaps = options.coverage.as_ref().and_then(|report| {
+        let Some(coverage) = report.for_source(&source_file) else {
+            info!("{} is not in the coverage report {}", source_file, report.report_path);
+            return None;
+        };
+        match lines {
+            Some((start, end)) => format_coverage_gaps(&coverage.within(start, end)),
+            None => format_coverage_gaps(coverage),
+        }
+    });
+
+    let generation_request = TestGenerationRequest {
+        code,
+        language: language.clone(),
+        test_framework: test_framework.clone(),
+        framework_version,
+        dependency_context,
+        // The file on disk keeps its secrets; only the copy in the prompt is redacted
+        existing_tests: existing_test.as_ref().map(|(path, content)| redaction.redact(content, path)),
+        project_dir: Some(dir_path.clone()),
+        part: None,
+        bypass_cache: !options.use_cache,
+        structured: false,
+        source_path: Some(source_file.clone()),
+        symbol: options.symbol.clone(),
+        file_context: file,
+        coverage_gaps,
+    };
+    
+    // The session stays open for tool output, so a secret seen again keeps its placeholder
+    let report = redaction.take_report();
+    if !report.is_empty() {
+        info!("Redacted {} likely secret(s) before generating tests for {}", report.redactions.len(), source_file);
+        emitter.redaction(&report);
+    }
+    
+    // Generate test code using the agent, one chunk at a time for files too large to send whole
+    let chunks = split_source(&generation_request.code, &language, options.max_chunk_chars);
+    let mut suggested_path = None;
+    let generated = if options.tool_steps > 0 {
+        info!("Generating tests for {} with up to {} tool calls", source_file, options.tool_steps);
+        let mut tools = ProjectTools::new(&dir_path, redaction)?;
+        let result = agent_service
+            .generate_tests_with_tools(generation_request, &mut tools, options.tool_steps, |step| emitter.tool_call(step))
+            .await;
+        let report = tools.take_redactions();
+        if !report.is_empty() {
+            info!("Redacted {} likely secret(s) from tool output for {}", report.redactions.len(), source_file);
+            emitter.redaction(&report);
+        }
+        redaction = tools.into_redaction();
+        let response = result?;
+        if options.stream {
+            emitter.token(&response.content);
+        }
+        extract_test_code(&response.content, &language)?
+    } else if chunks.len() > 1 {
+        info!("Generating tests for {} in {} chunks", source_file, chunks.len());
+        agent_service
+            .generate_tests_chunked(generation_request, chunks, |token| {
+                if options.stream {
+                    emitter.token(token);
+                }
+            })
+            .await?
+            .content
+    } else if options.structured {
+        let (suite, _) = agent_service
+            .generate_test_suite(generation_request, |token| {
+                if options.stream {
+                    emitter.token(token);
+                }
+            })
+            .await?;
+        info!(
+            "Generated {} test case(s) for {} covering: {}",
+            suite.test_cases.len(),
+            source_file,
+            suite.covered_functions.join(", ")
+        );
+        if !suite.dev_dependencies.is_empty() {
+            info!("Generated tests for {} need: {}", source_file, suite.dev_dependencies.join(", "));
+        }
+        suggested_path = suite.test_path;
+        suite.content
+    } else {
+        let test_response = if options.stream {
+            agent_service
+                .generate_tests_streaming(generation_request, |token| emitter.token(token))
+                .await?
+        } else {
+            agent_service.generate_tests(generation_request).await?
+        };
+
+        // Pull the test code out of the response, rejecting it if there are no tests to write
+        extract_test_code(&test_response.content, &language)?
+    };
+
+    // Make sure the generated code parses before anything is written to the project
+    let generated = repair_syntax(
+        generated,
+        &source_code,
+        &language,
+        test_framework.as_deref(),
+        options.repair_attempts,
+        &dir_path,
+        &mut redaction,
+        emitter,
+        agent_service,
+    )
+    .await?;
+    
+    // Write the test file, merging into the existing one in augment mode. Repairs only ever see
+    // and replace `additions`, which are merged into the original file again, so hand-written
+    // tests are never rewritten by the agent.
+    let mut additions = generated;
+    let (test_file_path, mut test_code, existing_content) = match existing_test {
+        Some((existing_path, existing_content)) => {
+            let merged = require_valid_syntax(merge_tests(&existing_content, &additions, &language), &language)
+                .map_err(|e| format!("Merging the generated cases into {} failed: {}", existing_path, e))?;
+            file_service.write_file(&dir_path, &existing_path, &merged)?;
+            info!("Added generated cases to existing test file {}", existing_path);
+            (existing_path, merged, Some(existing_content))
+        }
+        None => {
+            let path = file_service.write_test_file_at(&dir_path, &source_file, suggested_path.as_deref(), &additions)?;
+            (path, additions.clone(), None)
+        }
+    };
+
+    // Run the tests and feed failures back to the agent until they pass or we run out of attempts
+    if options.repair_attempts > 0 {
+        for attempt in 0..=options.repair_attempts {
+            // The file is already written, so a runner that can't start ends the loop rather than the request
+            let outcome = match run_test_file(&dir_path, &test_file_path, &language, test_framework.as_deref()).await {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    log::warn!("Could not run {}, keeping it without repair: {}", test_file_path, e);
+                    emitter.test_run(attempt, &TestRunOutcome { passed: false, command: String::new(), output: e });
+                    break;
+                }
+            };
+            emitter.test_run(attempt, &outcome);
+
+            if outcome.passed {
+                info!("Generated tests in {} pass after {} repair(s)", test_file_path, attempt);
+                break;
+            }
+            if attempt == options.repair_attempts {
+                info!("Generated tests in {} still fail after {} repair(s)", test_file_path, attempt);
+                break;
+            }
+
+            // Test output can echo values from the environment, so it is redacted like the code
+            let failed_tests = redaction.redact(&additions, &test_file_path);
+            let output = redaction.redact(&outcome.output, "test output");
+            report_redactions(&mut redaction, emitter);
+            let failure_output = match existing_content {
+                Some(_) => format!(
+                    "These tests were added to an existing test file and only the added tests are shown. \
+                     Fix only them; failures of the file's other tests are not yours to fix.\n\n{}",
+                    output
+                ),
+                None => output,
+            };
+            let repair_response = agent_service
+                .repair_tests(
+                    source_code.clone(),
+                    failed_tests,
+                    failure_output,
+                    language.clone(),
+                    test_framework.clone(),
+                    Some(&dir_path),
+                )
+                .await?;
+
+            // Keep the previous version if the repair doesn't contain usable, parseable tests
+            let repaired = extract_test_code(&repair_response.content, &language).and_then(|repaired| {
+                let file = match &existing_content {
+                    Some(existing) => merge_tests(existing, &repaired, &language),
+                    None => repaired.clone(),
+                };
+                require_valid_syntax(file, &language).map(|file| (repaired, file))
+            });
+            match repaired {
+                Ok((repaired, file)) => {
+                    additions = repaired;
+                    test_code = file;
+                    file_service.write_file(&dir_path, &test_file_path, &test_code)?;
+                }
+                Err(e) => {
+                    info!("Discarding repair attempt {} for {}: {}", attempt + 1, test_file_path, e);
+                }
+            }
+        }
+    }
+
+    if options.stream {
+        emitter.done(&test_code, Some(test_file_path.clone()));
+    }
     
     Ok(test_file_path)
-} 
\ No newline at end of file
+}
+
+/// Checks that generated test code parses, asking the agent to fix any syntax errors
+///
+/// Fails with the position of each error once `repair_attempts` repairs have been tried,
+/// so a file that doesn't parse is never written. The test code is redacted with `redaction`
+/// before it is sent back.
+#[allow(clippy::too_many_arguments)]
+async fn repair_syntax(
+    mut test_code: String,
+    source_code: &str,
+    language: &str,
+    test_framework: Option<&str>,
+    repair_attempts: u32,
+    project_dir: &Path,
+    redaction: &mut RedactionSession,
+    emitter: &AgentEventEmitter,
+    agent_service: &AgentService,
+) -> Result<String, String> {
+    if !is_supported(language) {
+        info!("No {} grammar available, skipping the syntax check of the generated tests", language);
+        return Ok(test_code);
+    }
+
+    let mut attempt = 0;
+    loop {
+        let errors = check_syntax(&test_code, language)?;
+        if errors.is_empty() {
+            return Ok(test_code);
+        }
+        let report = format_syntax_errors(&errors);
+        if attempt == repair_attempts {
+            return Err(format!("The generated tests have syntax errors and were not written:\n{}", report));
+        }
+        attempt += 1;
+
+        info!(
+            "Generated tests have {} syntax error(s), requesting repair {}/{}",
+            errors.len(),
+            attempt,
+            repair_attempts
+        );
+        let broken_tests = redaction.redact(&test_code, "generated tests");
+        report_redactions(redaction, emitter);
+        let repair_response = agent_service
+            .repair_tests(
+                source_code.to_string(),
+                broken_tests,
+                format!("The test file does not parse:\n{}", report),
+                language.to_string(),
+                test_framework.map(str::to_string),
+                Some(project_dir),
+            )
+            .await?;
+
+        match extract_test_code(&repair_response.content, language) {
+            Ok(repaired) => test_code = repaired,
+            Err(e) => info!("Discarding syntax repair attempt {}: {}", attempt, e),
+        }
+    }
+}
+
+/// Emits the redactions made since the last report, if there are any
+fn report_redactions(redaction: &mut RedactionSession, emitter: &AgentEventEmitter) {
+    let report = redaction.take_report();
+    if !report.is_empty() {
+        info!("Redacted {} likely secret(s) before requesting a repair", report.redactions.len());
+        emitter.redaction(&report);
+    }
+}
+
+/// Passes test code through if it parses, otherwise fails with its syntax errors
+fn require_valid_syntax(test_code: String, language: &str) -> Result<String, String> {
+    let errors = check_syntax(&test_code, language)?;
+    if errors.is_empty() {
+        Ok(test_code)
+    } else {
+        Err(format!("syntax errors:\n{}", format_syntax_errors(&errors)))
+    }
+}
@@ -3,3 +3,9 @@ pub mod dialog_commands;
 pub mod file_watcher_commands;
 pub mod agent_commands;
 pub mod file_analysis_commands;
+pub mod prompt_commands;
+pub mod usage_commands;
+pub mod cache_commands;
+pub mod chat_commands;
+pub mod api_key_commands;
+pub mod redaction_commands;
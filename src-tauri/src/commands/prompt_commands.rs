@@ -0,0 +1,52 @@
+use crate::services::AgentService;
+use crate::services::prompt_registry::{PromptSource, PromptTemplate};
+use std::path::PathBuf;
+use tauri::State;
+
+/// List the effective prompt templates, including project overrides when a project is given
+///
+/// # Arguments
+/// * `project_dir` - Root of the project whose `.vibede/prompts` folder should be included
+#[tauri::command]
+pub fn list_prompt_templates(
+    project_dir: Option<String>,
+    agent_service: State<'_, AgentService>,
+) -> Result<Vec<PromptTemplate>, String> {
+    let project_dir = project_dir.map(PathBuf::from);
+    Ok(agent_service.prompts().list(project_dir.as_deref()))
+}
+
+/// Read the effective version of a prompt template
+///
+/// # Arguments
+/// * `key` - Template key, e.g. `test_system` or `test_guidance.python.pytest`
+/// * `project_dir` - Root of the project whose `.vibede/prompts` folder should be included
+#[tauri::command]
+pub fn read_prompt_template(
+    key: String,
+    project_dir: Option<String>,
+    agent_service: State<'_, AgentService>,
+) -> Result<PromptTemplate, String> {
+    let project_dir = project_dir.map(PathBuf::from);
+    agent_service.prompts().read(&key, project_dir.as_deref())
+}
+
+/// Save a prompt template, overriding the built-in version
+///
+/// # Arguments
+/// * `key` - Template key, e.g. `test_system` or `test_guidance.python.pytest`
+/// * `content` - Template text; `{{variable}}` placeholders are filled in when prompts are built
+/// * `scope` - `app` to save for every project, or `project` to save in the project's `.vibede/prompts`
+/// * `project_dir` - Root of the project, required for the `project` scope
+#[tauri::command]
+pub fn save_prompt_template(
+    key: String,
+    content: String,
+    scope: String,
+    project_dir: Option<String>,
+    agent_service: State<'_, AgentService>,
+) -> Result<PromptTemplate, String> {
+    let scope = PromptSource::parse_scope(&scope)?;
+    let project_dir = project_dir.map(PathBuf::from);
+    agent_service.prompts().save(&key, &content, scope, project_dir.as_deref())
+}
@@ -0,0 +1,58 @@
+use crate::services::AgentService;
+use crate::services::redaction::{RedactionPattern, RedactionReport, RedactionSettings};
+use serde::Serialize;
+use tauri::State;
+
+/// Code with its likely secrets replaced, and what was replaced
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactionPreview {
+    pub code: String,
+    pub report: RedactionReport,
+}
+
+/// Whether secrets are redacted from prompts, and the user patterns redacted besides the built-in ones
+#[tauri::command]
+pub fn get_redaction_settings(agent_service: State<'_, AgentService>) -> RedactionSettings {
+    agent_service.redactor().settings()
+}
+
+/// Change what is redacted from code before it is sent to the LLM
+///
+/// The settings are saved in the app data directory.
+///
+/// # Arguments
+/// * `enabled` - Redact anything at all; unchanged when omitted
+/// * `detect_high_entropy` - Also redact quoted strings that look like random keys; unchanged when omitted
+/// * `patterns` - Regular expressions to redact besides the built-in ones, replacing the current list;
+///   only the first capture group is replaced when a pattern has one. Unchanged when omitted
+#[tauri::command]
+pub fn configure_redaction(
+    enabled: Option<bool>,
+    detect_high_entropy: Option<bool>,
+    patterns: Option<Vec<RedactionPattern>>,
+    agent_service: State<'_, AgentService>,
+) -> Result<RedactionSettings, String> {
+    let redactor = agent_service.redactor();
+    let current = redactor.settings();
+    redactor.configure(RedactionSettings {
+        enabled: enabled.unwrap_or(current.enabled),
+        detect_high_entropy: detect_high_entropy.unwrap_or(current.detect_high_entropy),
+        patterns: patterns.unwrap_or(current.patterns),
+    })?;
+    Ok(redactor.settings())
+}
+
+/// Redact code with the current settings without sending it anywhere, to check what would be replaced
+///
+/// # Arguments
+/// * `location` - File name the report refers to; `code` when omitted
+#[tauri::command]
+pub fn preview_redaction(
+    code: String,
+    location: Option<String>,
+    agent_service: State<'_, AgentService>,
+) -> RedactionPreview {
+    let mut session = agent_service.redactor().session();
+    let code = session.redact(&code, location.as_deref().unwrap_or("code"));
+    RedactionPreview { code, report: session.finish() }
+}
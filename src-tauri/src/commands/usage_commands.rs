@@ -0,0 +1,48 @@
+use crate::services::AgentService;
+use crate::services::usage_ledger::{UsageFilter, UsageGrouping, UsageRecord, UsageTotal};
+use tauri::State;
+
+/// Sum the token usage and cost of agent calls
+///
+/// # Arguments
+/// * `group_by` - `day`, `project`, `operation` or `model`
+/// * `since` - First day to include (`YYYY-MM-DD`)
+/// * `until` - Last day to include (`YYYY-MM-DD`)
+/// * `project_dir` - Only include calls made for this project
+#[tauri::command]
+pub fn get_usage_totals(
+    group_by: String,
+    since: Option<String>,
+    until: Option<String>,
+    project_dir: Option<String>,
+    agent_service: State<'_, AgentService>,
+) -> Result<Vec<UsageTotal>, String> {
+    let grouping = UsageGrouping::parse(&group_by)?;
+    let filter = UsageFilter {
+        since,
+        until,
+        project: project_dir,
+    };
+    agent_service.usage_ledger().totals(grouping, &filter)
+}
+
+/// List the recorded agent calls, oldest first
+///
+/// # Arguments
+/// * `since` - First day to include (`YYYY-MM-DD`)
+/// * `until` - Last day to include (`YYYY-MM-DD`)
+/// * `project_dir` - Only include calls made for this project
+#[tauri::command]
+pub fn list_usage_records(
+    since: Option<String>,
+    until: Option<String>,
+    project_dir: Option<String>,
+    agent_service: State<'_, AgentService>,
+) -> Result<Vec<UsageRecord>, String> {
+    let filter = UsageFilter {
+        since,
+        until,
+        project: project_dir,
+    };
+    agent_service.usage_ledger().records(&filter)
+}
@@ -5,11 +5,21 @@ pub mod utils;
 use commands::dialog_commands::{select_directory_dialog, list_directory_files, list_directories};
 use commands::file_watcher_commands::{start_watching_directory, stop_watching_directory, list_active_watchers, trigger_test_event, FileWatcherState};
 use commands::auth_commands::{initialize_auth0, login, logout, get_auth_state, is_authenticated, handle_auth_callback, register_uri_scheme_handler, manual_authenticate, set_test_pkce_params, get_test_pkce_params};
-use commands::agent_commands::{initialize_agent, is_agent_initialized, agent_simple_invoke, agent_chain_invoke, generate_tests, check_package_installation};
-use commands::file_analysis_commands::{find_test_files, generate_and_write_test};
+use commands::agent_commands::{initialize_agent, is_agent_initialized, agent_simple_invoke, agent_chain_invoke, generate_tests, generate_test_suite, check_package_installation, cancel_agent_request, list_agent_requests, set_agent_concurrency_limit};
+use commands::file_analysis_commands::{check_test_syntax, detect_project_test_framework, find_test_files, generate_and_write_test, generate_tests_for_project, generate_tests_for_symbol, load_coverage, review_test_file};
+use commands::prompt_commands::{list_prompt_templates, read_prompt_template, save_prompt_template};
+use commands::usage_commands::{get_usage_totals, list_usage_records};
+use commands::cache_commands::{get_response_cache_stats, clear_response_cache, configure_response_cache};
+use commands::api_key_commands::{set_api_key, clear_api_key, reset_api_key_store, has_api_key};
+use commands::redaction_commands::{get_redaction_settings, configure_redaction, preview_redaction};
+use commands::chat_commands::{create_chat_session, list_chat_sessions, get_chat_session, delete_chat_session, append_chat_message, attach_file_to_chat_session, detach_file_from_chat_session, invoke_chat_session, send_chat_message};
 use services::file_service::FileService;
 use services::auth_service::{AuthService, AuthStateStore};
 use services::AgentService;
+use services::agent_events::AgentEventEmitter;
+use services::response_cache::RESPONSE_CACHE_DIR;
+use services::chat_sessions::{ChatSessionStore, CHAT_SESSION_DIR};
+use services::secret_store::{open_backend, ApiKeyStore};
 use utils::panic_handler::setup_panic_handler;
 use std::fs::File;
 use std::io::Write;
@@ -74,6 +84,14 @@ pub fn run() {
     log_to_file("Initializing Agent service");
     let builder = builder.manage(AgentService::new());
     
+    // Initialize the chat session store
+    log_to_file("Initializing chat session store");
+    let builder = builder.manage(ChatSessionStore::new());
+    
+    // Initialize the API key store
+    log_to_file("Initializing API key store");
+    let builder = builder.manage(ApiKeyStore::new());
+    
     log_to_file("Setting up invoke handler");
     let builder = builder.invoke_handler(tauri::generate_handler![
         select_directory_dialog,
@@ -101,11 +119,57 @@ pub fn run() {
         agent_simple_invoke,
         agent_chain_invoke,
         generate_tests,
+        generate_test_suite,
         check_package_installation,
+        cancel_agent_request,
+        list_agent_requests,
+        set_agent_concurrency_limit,
+        
+        // API key commands
+        set_api_key,
+        clear_api_key,
+        reset_api_key_store,
+        has_api_key,
+        
+        // Redaction commands
+        get_redaction_settings,
+        configure_redaction,
+        preview_redaction,
         
         // File analysis commands
         find_test_files,
         generate_and_write_test,
+        generate_tests_for_project,
+        generate_tests_for_symbol,
+        check_test_syntax,
+        detect_project_test_framework,
+        load_coverage,
+        review_test_file,
+        
+        // Prompt template commands
+        list_prompt_templates,
+        read_prompt_template,
+        save_prompt_template,
+        
+        // Usage accounting commands
+        get_usage_totals,
+        list_usage_records,
+        
+        // Response cache commands
+        get_response_cache_stats,
+        clear_response_cache,
+        configure_response_cache,
+        
+        // Chat session commands
+        create_chat_session,
+        list_chat_sessions,
+        get_chat_session,
+        delete_chat_session,
+        append_chat_message,
+        attach_file_to_chat_session,
+        detach_file_from_chat_session,
+        invoke_chat_session,
+        send_chat_message,
     ]);
     
     log_to_file("Setting up app");
@@ -124,6 +188,42 @@ pub fn run() {
         app.manage(FileWatcherState::new(app_handle.clone()));
         log_to_file("File watcher state initialized");
         
+        // Keep prompt templates, the usage ledger, the response cache, redaction settings, chat sessions and API keys in the app data directory
+        match app.path().app_data_dir() {
+            Ok(data_dir) => {
+                let agent_service = app.state::<AgentService>();
+                agent_service.prompts().set_app_dir(data_dir.join("prompts"));
+                agent_service.response_cache().set_dir(data_dir.join(RESPONSE_CACHE_DIR));
+                agent_service.usage_ledger().set_dir(data_dir.clone());
+                agent_service.redactor().set_dir(data_dir.clone());
+                app.state::<ChatSessionStore>().set_dir(data_dir.join(CHAT_SESSION_DIR));
+                app.state::<ApiKeyStore>().set_dir(data_dir.clone());
+                app.state::<ApiKeyStore>().set_backend(open_backend(&app.config().identifier, data_dir));
+            }
+            Err(e) => log_to_file(&format!("Failed to resolve app data directory: {}", e)),
+        }
+        
+        // Report retries of failed LLM calls to the frontend
+        let retry_app_handle = app.handle().clone();
+        app.state::<AgentService>().set_retry_listener(move |request_id, retry| {
+            AgentEventEmitter::new(retry_app_handle.clone(), request_id.map(|id| id.to_string())).retry(retry);
+        });
+        
+        // Initialize the Agent service with the stored API key and provider settings, so the frontend doesn't have to
+        let startup_app_handle = app.handle().clone();
+        tauri::async_runtime::spawn(async move {
+            let Some(config) = startup_app_handle.state::<ApiKeyStore>().startup_config() else {
+                log_to_file("No stored API key; the Agent service will be initialized from the frontend");
+                return;
+            };
+            
+            let kind = config.kind;
+            match startup_app_handle.state::<AgentService>().initialize(config).await {
+                Ok(()) => log_to_file(&format!("Agent service initialized with the stored {:?} API key", kind)),
+                Err(e) => log_to_file(&format!("Failed to initialize Agent service with the stored API key: {}", e)),
+            }
+        });
+        
         // Register URI scheme handler for Auth0 callbacks
         log_to_file("Registering URI scheme handler for Auth0");
         register_uri_scheme_handler(&app.handle());
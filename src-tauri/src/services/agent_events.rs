@@ -0,0 +1,250 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use crate::services::agent_tools::ToolStep;
+use crate::services::batch_generation::{BatchFileResult, BatchSummary};
+use crate::services::llm_retry::{LlmErrorKind, RetryNotice};
+use crate::services::redaction::RedactionReport;
+use crate::services::test_runner::TestRunOutcome;
+
+/// Event emitted for every chunk of streamed LLM output
+pub const AGENT_TOKEN_EVENT: &str = "agent:token";
+/// Event emitted once a streamed request has finished successfully
+pub const AGENT_DONE_EVENT: &str = "agent:done";
+/// Event emitted when a streamed request fails
+pub const AGENT_ERROR_EVENT: &str = "agent:error";
+/// Event emitted each time a generated test file is run during the repair loop
+pub const AGENT_TEST_RUN_EVENT: &str = "agent:test-run";
+/// Event emitted when a failed LLM call is about to be retried
+pub const AGENT_RETRY_EVENT: &str = "agent:retry";
+/// Event emitted when a file of a batch job starts or finishes
+pub const AGENT_BATCH_PROGRESS_EVENT: &str = "agent:batch-progress";
+/// Event emitted once every file of a batch job has been processed
+pub const AGENT_BATCH_DONE_EVENT: &str = "agent:batch-done";
+/// Event emitted when likely secrets were replaced before a prompt was sent
+pub const AGENT_REDACTION_EVENT: &str = "agent:redaction";
+/// Event emitted each time the agent calls a project tool
+pub const AGENT_TOOL_CALL_EVENT: &str = "agent:tool-call";
+
+/// Payload of an `agent:token` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentTokenEvent {
+    /// ID of the request the token belongs to
+    pub request_id: String,
+    /// The newly generated text
+    pub token: String,
+}
+
+/// Payload of an `agent:done` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentDoneEvent {
+    /// ID of the request that finished
+    pub request_id: String,
+    /// The full generated content
+    pub content: String,
+    /// Path of the written test file, for requests that write one
+    pub test_file_path: Option<String>,
+}
+
+/// Payload of an `agent:error` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentErrorEvent {
+    /// ID of the request that failed
+    pub request_id: String,
+    /// Error message
+    pub error: String,
+}
+
+/// Payload of an `agent:test-run` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentTestRunEvent {
+    /// ID of the request the run belongs to
+    pub request_id: String,
+    /// Which run this is; 0 is the freshly generated file, 1.. are repair iterations
+    pub attempt: u32,
+    /// Outcome of the run
+    pub outcome: TestRunOutcome,
+}
+
+/// Payload of an `agent:retry` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentRetryEvent {
+    /// ID of the request whose LLM call is retried
+    pub request_id: String,
+    /// Which retry this is, starting at 1
+    pub attempt: u32,
+    /// How many retries are allowed in total
+    pub max_retries: u32,
+    /// Why the previous attempt failed
+    pub kind: LlmErrorKind,
+    /// How long until the retry, in milliseconds
+    pub delay_ms: u64,
+    /// Error message of the previous attempt
+    pub error: String,
+}
+
+/// Payload of an `agent:batch-progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentBatchProgressEvent {
+    /// ID of the batch job
+    pub request_id: String,
+    /// The file that started or finished, with its outcome once finished
+    pub file: BatchFileResult,
+    /// Files of the batch that have finished so far
+    pub completed: usize,
+    /// Files in the batch
+    pub total: usize,
+}
+
+/// Payload of an `agent:batch-done` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentBatchDoneEvent {
+    /// ID of the batch job
+    pub request_id: String,
+    pub summary: BatchSummary,
+}
+
+/// Payload of an `agent:redaction` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentRedactionEvent {
+    /// ID of the request whose prompt was redacted
+    pub request_id: String,
+    pub report: RedactionReport,
+}
+
+/// Payload of an `agent:tool-call` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentToolCallEvent {
+    /// ID of the request the agent is working on
+    pub request_id: String,
+    pub call: ToolStep,
+}
+
+/// Emits agent events to the frontend for a single request
+pub struct AgentEventEmitter {
+    app_handle: AppHandle,
+    request_id: String,
+}
+
+impl AgentEventEmitter {
+    /// Creates an emitter for the given request ID, generating one if not provided
+    pub fn new(app_handle: AppHandle, request_id: Option<String>) -> Self {
+        Self {
+            app_handle,
+            request_id: request_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+        }
+    }
+
+    /// The request ID attached to every event
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// Emits an `agent:token` event
+    pub fn token(&self, token: &str) {
+        let event = AgentTokenEvent {
+            request_id: self.request_id.clone(),
+            token: token.to_string(),
+        };
+        if let Err(e) = self.app_handle.emit(AGENT_TOKEN_EVENT, event) {
+            log::error!("Failed to emit {} event: {}", AGENT_TOKEN_EVENT, e);
+        }
+    }
+
+    /// Emits an `agent:done` event
+    pub fn done(&self, content: &str, test_file_path: Option<String>) {
+        let event = AgentDoneEvent {
+            request_id: self.request_id.clone(),
+            content: content.to_string(),
+            test_file_path,
+        };
+        if let Err(e) = self.app_handle.emit(AGENT_DONE_EVENT, event) {
+            log::error!("Failed to emit {} event: {}", AGENT_DONE_EVENT, e);
+        }
+    }
+
+    /// Emits an `agent:error` event
+    pub fn error(&self, error: &str) {
+        let event = AgentErrorEvent {
+            request_id: self.request_id.clone(),
+            error: error.to_string(),
+        };
+        if let Err(e) = self.app_handle.emit(AGENT_ERROR_EVENT, event) {
+            log::error!("Failed to emit {} event: {}", AGENT_ERROR_EVENT, e);
+        }
+    }
+
+    /// Emits an `agent:test-run` event
+    pub fn test_run(&self, attempt: u32, outcome: &TestRunOutcome) {
+        let event = AgentTestRunEvent {
+            request_id: self.request_id.clone(),
+            attempt,
+            outcome: outcome.clone(),
+        };
+        if let Err(e) = self.app_handle.emit(AGENT_TEST_RUN_EVENT, event) {
+            log::error!("Failed to emit {} event: {}", AGENT_TEST_RUN_EVENT, e);
+        }
+    }
+
+    /// Emits an `agent:retry` event
+    pub fn retry(&self, retry: &RetryNotice) {
+        let event = AgentRetryEvent {
+            request_id: self.request_id.clone(),
+            attempt: retry.attempt,
+            max_retries: retry.max_retries,
+            kind: retry.kind,
+            delay_ms: retry.delay_ms,
+            error: retry.error.clone(),
+        };
+        if let Err(e) = self.app_handle.emit(AGENT_RETRY_EVENT, event) {
+            log::error!("Failed to emit {} event: {}", AGENT_RETRY_EVENT, e);
+        }
+    }
+
+    /// Emits an `agent:batch-progress` event
+    pub fn batch_progress(&self, file: &BatchFileResult, completed: usize, total: usize) {
+        let event = AgentBatchProgressEvent {
+            request_id: self.request_id.clone(),
+            file: file.clone(),
+            completed,
+            total,
+        };
+        if let Err(e) = self.app_handle.emit(AGENT_BATCH_PROGRESS_EVENT, event) {
+            log::error!("Failed to emit {} event: {}", AGENT_BATCH_PROGRESS_EVENT, e);
+        }
+    }
+
+    /// Emits an `agent:batch-done` event
+    pub fn batch_done(&self, summary: &BatchSummary) {
+        let event = AgentBatchDoneEvent {
+            request_id: self.request_id.clone(),
+            summary: summary.clone(),
+        };
+        if let Err(e) = self.app_handle.emit(AGENT_BATCH_DONE_EVENT, event) {
+            log::error!("Failed to emit {} event: {}", AGENT_BATCH_DONE_EVENT, e);
+        }
+    }
+
+    /// Emits an `agent:redaction` event
+    pub fn redaction(&self, report: &RedactionReport) {
+        let event = AgentRedactionEvent {
+            request_id: self.request_id.clone(),
+            report: report.clone(),
+        };
+        if let Err(e) = self.app_handle.emit(AGENT_REDACTION_EVENT, event) {
+            log::error!("Failed to emit {} event: {}", AGENT_REDACTION_EVENT, e);
+        }
+    }
+
+    /// Emits an `agent:tool-call` event
+    pub fn tool_call(&self, call: &ToolStep) {
+        let event = AgentToolCallEvent {
+            request_id: self.request_id.clone(),
+            call: call.clone(),
+        };
+        if let Err(e) = self.app_handle.emit(AGENT_TOOL_CALL_EVENT, event) {
+            log::error!("Failed to emit {} event: {}", AGENT_TOOL_CALL_EVENT, e);
+        }
+    }
+}
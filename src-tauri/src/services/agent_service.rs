@@ -1,168 +1,921 @@
 use langchain_rust::{
-    chain::{Chain, LLMChainBuilder},
+    chain::{Chain, LLMChain, LLMChainBuilder},
     fmt_message, fmt_template,
-    language_models::llm::LLM,
-    llm::Claude,
+    language_models::{GenerateResult, TokenUsage},
     message_formatter,
-    prompt::HumanMessagePromptTemplate,
+    prompt::{HumanMessagePromptTemplate, PromptArgs},
     prompt_args,
     schemas::messages::Message,
     template_fstring,
 };
+use futures::future::{abortable, AbortHandle, Aborted};
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
+
+use crate::services::agent_tools::{describe_tools, parse_tool_call, ProjectTools, ToolStep};
+use crate::services::chat_sessions::ChatMessage;
+use crate::services::llm_provider::{build_provider, LlmProvider, ProviderConfig};
+use crate::services::llm_retry::{with_retry, LlmError, RetryNotice, RetryPolicy};
+use crate::services::redaction::Redactor;
+use crate::services::response_cache::ResponseCache;
+use crate::services::prompt_registry::{
+    render_template, PromptRegistry, AGENT_TOOLS_TEMPLATE, TEST_GUIDANCE_TEMPLATE, TEST_JSON_OUTPUT_TEMPLATE,
+    TEST_REVIEW_TEMPLATE, TEST_SYSTEM_TEMPLATE, TEST_USER_TEMPLATE,
+};
+use crate::services::structured_output::{parse_test_suite, GeneratedTestSuite};
+use crate::services::syntax_validation::{check_syntax, is_supported};
+use crate::services::test_extraction::extract_test_code;
+use crate::services::test_merge::merge_tests;
+use crate::services::test_review::{number_lines, parse_test_review, TestReview};
+use crate::services::usage_ledger::{estimate_tokens, CallUsage, UsageLedger, UsageOperation, UsageRecord};
+
+/// Number of LLM requests allowed to run at the same time unless configured otherwise
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Called before each retry of a failed LLM call, with the ID of the request it belongs to
+pub type RetryListener = Arc<dyn Fn(Option<&str>, &RetryNotice) + Send + Sync>;
+
+tokio::task_local! {
+    /// ID of the request being run by `run_cancellable`, used to tag retry notices
+    static REQUEST_ID: String;
+}
 
 /// Represents the response from an LLM model
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentResponse {
     pub content: String,
+    /// Token usage and latency of the call(s) that produced the content
+    pub usage: Option<CallUsage>,
+    /// True if the content came from the response cache instead of the provider
+    #[serde(default)]
+    pub cached: bool,
+}
+
+/// Everything needed to generate tests for one piece of code
+#[derive(Debug, Clone, Default)]
+pub struct TestGenerationRequest {
+    /// The source code to test
+    pub code: String,
+    /// Language of the source code
+    pub language: String,
+    /// Testing framework to use, if known
+    pub test_framework: Option<String>,
+    /// Version of the testing framework the project uses, if known
+    pub framework_version: Option<String>,
+    /// Signatures of the local types and functions the code depends on
+    pub dependency_context: Option<String>,
+    /// Contents of the existing test file; when set, only missing cases are requested
+    pub existing_tests: Option<String>,
+    /// Root of the project, used to find project-local prompt templates
+    pub project_dir: Option<PathBuf>,
+    /// Position of `code` within a larger source file, e.g. `2 of 5`; set for chunked generation
+    pub part: Option<String>,
+    /// Skip the response cache lookup; the fresh response still replaces the cached one
+    pub bypass_cache: bool,
+    /// Ask for a JSON `GeneratedTestSuite` instead of plain test code
+    pub structured: bool,
+    /// Path of the source file relative to the project root, used to suggest a test path
+    pub source_path: Option<String>,
+    /// Name of the function, method or class `code` holds, when only that symbol is tested
+    pub symbol: Option<String>,
+    /// The whole source file, sent as context when only one symbol is tested
+    pub file_context: Option<String>,
+    /// The lines and branches existing tests leave uncovered, from a coverage report
+    pub coverage_gaps: Option<String>,
+}
+
+/// A test generation chain and what is needed to account for and cache its response
+struct TestGenerationChain {
+    chain: LLMChain,
+    /// Length of the rendered prompt, for estimating usage
+    prompt_chars: usize,
+    /// Hash of the provider, model and rendered prompt
+    cache_key: String,
+}
+
+/// Removes a request started by `run_cancellable` from the in-flight requests when dropped
+struct RequestRegistration<'a> {
+    requests: &'a std::sync::Mutex<HashMap<String, AbortHandle>>,
+    request_id: &'a str,
+}
+
+impl Drop for RequestRegistration<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut requests) = self.requests.lock() {
+            requests.remove(self.request_id);
+        }
+    }
 }
 
 /// Service for handling AI Agents using LangChain
 pub struct AgentService {
-    claude: Arc<Mutex<Option<Claude>>>,
+    /// The configured provider; only held long enough to clone the `Arc`
+    provider: Arc<RwLock<Option<Arc<dyn LlmProvider>>>>,
+    /// The settings the provider was built from
+    provider_config: Arc<RwLock<Option<ProviderConfig>>>,
+    /// Limits how many LLM requests run at once
+    limiter: Arc<RwLock<Arc<Semaphore>>>,
+    /// Abort handles of in-flight requests, keyed by request ID
+    requests: Arc<std::sync::Mutex<HashMap<String, AbortHandle>>>,
+    /// Prompt templates used to build test generation prompts
+    prompts: PromptRegistry,
+    /// How transient provider errors are retried
+    retry_policy: Arc<RwLock<RetryPolicy>>,
+    /// Told about every retry so progress can be shown in the UI
+    retry_listener: Arc<std::sync::RwLock<Option<RetryListener>>>,
+    /// Records the token usage of every call
+    usage: UsageLedger,
+    /// Responses to earlier identical test generation requests
+    cache: ResponseCache,
+    /// Removes likely secrets from code before it is put in a prompt
+    redactor: Redactor,
 }
 
 impl AgentService {
     /// Create a new AgentService instance
     pub fn new() -> Self {
         Self {
-            claude: Arc::new(Mutex::new(None)),
+            provider: Arc::new(RwLock::new(None)),
+            provider_config: Arc::new(RwLock::new(None)),
+            limiter: Arc::new(RwLock::new(Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_REQUESTS)))),
+            requests: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            prompts: PromptRegistry::new(),
+            retry_policy: Arc::new(RwLock::new(RetryPolicy::default())),
+            retry_listener: Arc::new(std::sync::RwLock::new(None)),
+            usage: UsageLedger::new(),
+            cache: ResponseCache::new(),
+            redactor: Redactor::new(),
         }
     }
 
-    /// Initialize the Claude model with the provided API key
-    pub async fn initialize(&self, api_key: String) -> Result<(), String> {
-        let claude = Claude::default()
-            .with_api_key(api_key)
-            .with_model("claude-3-7-sonnet-20250219");
+    /// Initialize the service with the provider, model, base URL and key to use
+    pub async fn initialize(&self, config: ProviderConfig) -> Result<(), String> {
+        let provider = build_provider(config.clone())?;
+        log::info!(
+            "Agent initialized with provider {:?} and model {}",
+            provider.kind(),
+            provider.model()
+        );
 
-        let mut lock = self.claude.lock().await;
-        *lock = Some(claude);
+        let mut lock = self.provider.write().await;
+        *lock = Some(provider);
+        *self.provider_config.write().await = Some(config);
         
         Ok(())
     }
 
+    /// The settings the service was last initialized with
+    pub async fn provider_config(&self) -> Option<ProviderConfig> {
+        self.provider_config.read().await.clone()
+    }
+
     /// Check if the service has been initialized with an API key
     pub async fn is_initialized(&self) -> bool {
-        let lock = self.claude.lock().await;
+        let lock = self.provider.read().await;
         lock.is_some()
     }
 
+    /// Set how many LLM requests may run at the same time
+    /// Requests already running keep their slot under the previous limit
+    pub async fn set_max_concurrent_requests(&self, limit: usize) -> Result<(), String> {
+        if limit == 0 {
+            return Err("The concurrency limit must be at least 1".to_string());
+        }
+
+        let mut limiter = self.limiter.write().await;
+        *limiter = Arc::new(Semaphore::new(limit));
+        log::info!("Agent concurrency limit set to {}", limit);
+
+        Ok(())
+    }
+
+    /// Set how many times a rate-limited, overloaded or network-failed LLM call is retried
+    pub async fn set_max_retries(&self, max_retries: u32) {
+        let mut policy = self.retry_policy.write().await;
+        policy.max_retries = max_retries;
+        log::info!("Agent retry limit set to {}", max_retries);
+    }
+
+    /// Set the function told about every retry of a failed LLM call
+    pub fn set_retry_listener<F>(&self, listener: F)
+    where
+        F: Fn(Option<&str>, &RetryNotice) + Send + Sync + 'static,
+    {
+        if let Ok(mut retry_listener) = self.retry_listener.write() {
+            *retry_listener = Some(Arc::new(listener));
+        }
+    }
+
+    /// Run an agent operation under the given request ID so it can be cancelled
+    /// with `cancel_request` while it is in flight
+    pub async fn run_cancellable<T, F>(&self, request_id: &str, operation: F) -> Result<T, String>
+    where
+        F: Future<Output = Result<T, String>>,
+    {
+        let (operation, abort_handle) = abortable(REQUEST_ID.scope(request_id.to_string(), operation));
+
+        {
+            let mut requests = self.requests.lock().map_err(|e| e.to_string())?;
+            if requests.contains_key(request_id) {
+                return Err(format!("A request with ID {} is already running", request_id));
+            }
+            requests.insert(request_id.to_string(), abort_handle);
+        }
+        // Unregisters the request however it ends, including when this future is dropped
+        let _registration = RequestRegistration {
+            requests: &self.requests,
+            request_id,
+        };
+
+        match operation.await {
+            Ok(result) => result,
+            Err(Aborted) => {
+                log::info!("Agent request {} was cancelled", request_id);
+                Err(format!("Request {} was cancelled", request_id))
+            }
+        }
+    }
+
+    /// Cancel an in-flight request
+    /// Returns false if no request with that ID is running
+    pub fn cancel_request(&self, request_id: &str) -> Result<bool, String> {
+        let requests = self.requests.lock().map_err(|e| e.to_string())?;
+        match requests.get(request_id) {
+            Some(abort_handle) => {
+                abort_handle.abort();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// List the IDs of all in-flight requests
+    pub fn active_requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .map(|requests| requests.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The prompt template registry used for test generation
+    pub fn prompts(&self) -> &PromptRegistry {
+        &self.prompts
+    }
+
+    /// The ledger every call's token usage is recorded in
+    pub fn usage_ledger(&self) -> &UsageLedger {
+        &self.usage
+    }
+
+    /// The cache of test generation responses
+    pub fn response_cache(&self) -> &ResponseCache {
+        &self.cache
+    }
+
+    /// The redactor source code is passed through before it is sent to the provider
+    pub fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
+
     /// Simple invocation of the LLM with a prompt
     pub async fn simple_invoke(&self, prompt: String) -> Result<AgentResponse, String> {
-        let lock = self.claude.lock().await;
+        let provider = self.current_provider().await?;
+
+        let started = Instant::now();
+        let result = self
+            .retrying(|| {
+                let llm = provider.llm();
+                let messages = [Message::new_human_message(&prompt)];
+                async move { llm.generate(&messages).await.map_err(|e| LlmError::classify(e.to_string())) }
+            })
+            .await?;
+
+        let usage = Self::call_usage(provider.as_ref(), result.tokens, prompt.len(), &result.generation, started);
+        self.record_usage(UsageOperation::SimpleInvoke, None, &usage);
         
-        if let Some(claude) = &*lock {
-            let response = claude.invoke(&prompt).await
-                .map_err(|e| format!("Error invoking LLM: {e}"))?;
-            
-            Ok(AgentResponse { content: response })
-        } else {
-            Err("Agent service has not been initialized with an API key".to_string())
-        }
+        Ok(AgentResponse { content: result.generation, usage: Some(usage), cached: false })
     }
 
     /// Create a chain with a system prompt and user input
     pub async fn create_chain_response(&self, system_prompt: String, user_input: String) -> Result<AgentResponse, String> {
-        let lock = self.claude.lock().await;
-        
-        if let Some(claude) = &*lock {
-            let prompt = message_formatter![
-                fmt_message!(Message::new_system_message(&system_prompt)),
-                fmt_template!(HumanMessagePromptTemplate::new(template_fstring!(
-                    "{input}", "input"
-                )))
-            ];
-
-            let chain = LLMChainBuilder::new()
-                .prompt(prompt)
-                .llm(claude.clone())
-                .build()
-                .map_err(|e| format!("Error building chain: {e}"))?;
-
-            let result = chain
-                .invoke(prompt_args! {
-                    "input" => user_input,
-                })
-                .await
-                .map_err(|e| format!("Error invoking chain: {e}"))?;
+        let provider = self.current_provider().await?;
 
-            let content = result.to_string();
-            Ok(AgentResponse { content })
-        } else {
-            Err("Agent service has not been initialized with an API key".to_string())
-        }
+        let prompt = message_formatter![
+            fmt_message!(Message::new_system_message(&system_prompt)),
+            fmt_template!(HumanMessagePromptTemplate::new(template_fstring!(
+                "{input}", "input"
+            )))
+        ];
+
+        let chain = LLMChainBuilder::new()
+            .prompt(prompt)
+            .llm(provider.llm())
+            .build()
+            .map_err(|e| format!("Error building chain: {e}"))?;
+
+        let prompt_chars = system_prompt.len() + user_input.len();
+        let args = prompt_args! {
+            "input" => user_input,
+        };
+        let started = Instant::now();
+        let result = self.invoke_chain(&chain, args).await?;
+
+        let usage = Self::call_usage(provider.as_ref(), result.tokens, prompt_chars, &result.generation, started);
+        self.record_usage(UsageOperation::Chain, None, &usage);
+
+        Ok(AgentResponse { content: result.generation, usage: Some(usage), cached: false })
     }
     
     /// Generate tests for provided code
-    pub async fn generate_tests(&self, code: String, language: String, test_framework: Option<String>) -> Result<AgentResponse, String> {
-        let lock = self.claude.lock().await;
-        
-        if let Some(claude) = &*lock {
-            // Language-specific guidance for test generation
-            let language_guidance = match language.to_lowercase().as_str() {
-                "javascript" | "typescript" => {
-                    "Use Jest for testing. Include proper imports and describe/it blocks."
-                },
-                "python" => {
-                    "Use pytest for testing. Include proper imports and test_ prefixed functions."
-                },
-                "rust" => {
-                    "Use Rust's built-in testing framework with #[test] annotations. Include proper modules and use statements."
-                },
-                _ => {
-                    "Follow best practices for the language's standard testing framework."
+    pub async fn generate_tests(&self, request: TestGenerationRequest) -> Result<AgentResponse, String> {
+        let provider = self.current_provider().await?;
+        let prepared = Self::build_test_generation_chain(provider.as_ref(), &self.prompts, &request)?;
+
+        if let Some(content) = self.cached_response(&request, &prepared.cache_key) {
+            return Ok(AgentResponse { content, usage: None, cached: true });
+        }
+
+        let started = Instant::now();
+        let result = self.invoke_chain(&prepared.chain, PromptArgs::new()).await?;
+
+        let usage = Self::call_usage(provider.as_ref(), result.tokens, prepared.prompt_chars, &result.generation, started);
+        self.record_usage(UsageOperation::TestGeneration, request.project_dir.as_deref(), &usage);
+        self.cache_if_usable(&request, &prepared.cache_key, provider.model(), &result.generation);
+
+        Ok(AgentResponse { content: result.generation, usage: Some(usage), cached: false })
+    }
+
+    /// Generate tests for provided code, passing each chunk of output to `on_token` as it arrives
+    pub async fn generate_tests_streaming<F>(
+        &self,
+        request: TestGenerationRequest,
+        mut on_token: F,
+    ) -> Result<AgentResponse, String>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let provider = self.current_provider().await?;
+        let prepared = Self::build_test_generation_chain(provider.as_ref(), &self.prompts, &request)?;
+
+        if let Some(content) = self.cached_response(&request, &prepared.cache_key) {
+            on_token(&content);
+            return Ok(AgentResponse { content, usage: None, cached: true });
+        }
+
+        let started = Instant::now();
+        let policy = *self.retry_policy.read().await;
+        let mut attempt = 0;
+        loop {
+            // Taken for each attempt and given back before the backoff, as in `retrying`
+            let permit = self.acquire_permit().await?;
+            let mut content = String::new();
+            // Providers that report usage while streaming send it with one of the last chunks
+            let mut tokens: Option<TokenUsage> = None;
+            let result: Result<(), LlmError> = async {
+                let mut stream = prepared
+                    .chain
+                    .stream(PromptArgs::new())
+                    .await
+                    .map_err(|e| LlmError::classify(e.to_string()))?;
+
+                while let Some(chunk) = stream.next().await {
+                    let chunk = chunk.map_err(|e| LlmError::classify(e.to_string()))?;
+                    if chunk.tokens.is_some() {
+                        tokens = chunk.tokens;
+                    }
+                    if chunk.content.is_empty() {
+                        continue;
+                    }
+                    on_token(&chunk.content);
+                    content.push_str(&chunk.content);
                 }
+                Ok(())
+            }
+            .await;
+
+            let error = match result {
+                Ok(()) => {
+                    let usage = Self::call_usage(provider.as_ref(), tokens, prepared.prompt_chars, &content, started);
+                    self.record_usage(UsageOperation::TestGeneration, request.project_dir.as_deref(), &usage);
+                    self.cache_if_usable(&request, &prepared.cache_key, provider.model(), &content);
+                    return Ok(AgentResponse { content, usage: Some(usage), cached: false });
+                }
+                Err(error) => error,
             };
-            
-            // Create a specialized system prompt for test generation
-            let system_prompt = format!(
-                r#"You are a specialized test generation agent. Your task is to analyze the code provided and generate comprehensive test cases.
 
-Follow these guidelines:
-1. Create thorough test cases covering all functionality in the code
-2. Include tests for edge cases and error handling
-3. Ensure the tests are well-organized and commented
-4. Use {}{}
-5. {}
-
-Respond ONLY with the generated test code, without explanations or commentary outside the code."#,
-                language,
-                if let Some(framework) = test_framework {
-                    format!(" and the {} testing framework", framework)
-                } else {
-                    " best practices for testing".to_string()
-                },
-                language_guidance
-            );
-
-            let prompt = message_formatter![
-                fmt_message!(Message::new_system_message(&system_prompt)),
-                fmt_template!(HumanMessagePromptTemplate::new(template_fstring!(
-                    "Here is the code to generate tests for:\n\n```\n{code}\n```\n\nGenerate comprehensive tests for this code.", 
-                    "code"
-                )))
-            ];
-
-            let chain = LLMChainBuilder::new()
-                .prompt(prompt)
-                .llm(claude.clone())
-                .build()
-                .map_err(|e| format!("Error building test generation chain: {e}"))?;
-
-            let result = chain
-                .invoke(prompt_args! {
-                    "code" => code,
+            // Tokens already passed to `on_token` can't be taken back, so only retry before the first one
+            attempt += 1;
+            let retry = if content.is_empty() { policy.next_retry(attempt, &error) } else { None };
+            let Some(retry) = retry else {
+                return Err(error.into());
+            };
+            self.notify_retry(&retry);
+            drop(permit);
+            tokio::time::sleep(Duration::from_millis(retry.delay_ms)).await;
+        }
+    }
+
+    /// Generate tests for a source file that was split into chunks with `split_source`
+    ///
+    /// Each chunk is sent as its own request, in order, and the test code extracted from
+    /// the responses is merged into one test file. Unlike the other generation methods,
+    /// the returned content is the merged test code rather than the raw LLM output.
+    /// Raw output is still passed to `on_token` as it arrives.
+    pub async fn generate_tests_chunked<F>(
+        &self,
+        request: TestGenerationRequest,
+        chunks: Vec<String>,
+        mut on_token: F,
+    ) -> Result<AgentResponse, String>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let total = chunks.len();
+        let mut merged: Option<String> = None;
+        let mut usage = CallUsage::default();
+        let mut all_cached = true;
+
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let part = format!("{} of {}", index + 1, total);
+            log::info!("Generating tests for part {}", part);
+
+            let chunk_request = TestGenerationRequest {
+                code: chunk,
+                part: Some(part.clone()),
+                ..request.clone()
+            };
+            let response = self.generate_tests_streaming(chunk_request, &mut on_token).await?;
+            if let Some(chunk_usage) = &response.usage {
+                usage.add(chunk_usage);
+            }
+            all_cached &= response.cached;
+
+            // A part without usable tests (e.g. only constants) shouldn't sink the whole file
+            match extract_test_code(&response.content, &request.language) {
+                Ok(tests) => {
+                    merged = Some(match merged {
+                        Some(existing) => merge_tests(&existing, &tests, &request.language),
+                        None => tests,
+                    });
+                }
+                Err(e) => log::warn!("Discarding generated tests for part {}: {}", part, e),
+            }
+        }
+
+        merged
+            .map(|content| AgentResponse {
+                content,
+                usage: (!all_cached).then_some(usage),
+                cached: all_cached,
+            })
+            .ok_or_else(|| format!("None of the {} parts of the source file produced usable tests", total))
+    }
+
+    /// Generate tests as a `GeneratedTestSuite`, passing each chunk of raw output to `on_token`
+    pub async fn generate_test_suite<F>(
+        &self,
+        request: TestGenerationRequest,
+        mut on_token: F,
+    ) -> Result<(GeneratedTestSuite, AgentResponse), String>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let request = TestGenerationRequest {
+            structured: true,
+            ..request
+        };
+        let response = self.generate_tests_streaming(request.clone(), &mut on_token).await?;
+        let suite = parse_test_suite(&response.content, &request.language)?;
+        Ok((suite, response))
+    }
+
+    /// Generate tests for provided code, letting the model call project tools first
+    ///
+    /// The usual test generation prompt gets the `agent_tools` instructions appended. Each
+    /// response that is a tool call is answered with the tool's output, up to `max_steps` calls,
+    /// and passed to `on_step`; the first response that isn't one is the answer. Responses are
+    /// not cached, since what the tools return can change between requests.
+    pub async fn generate_tests_with_tools<F>(
+        &self,
+        request: TestGenerationRequest,
+        tools: &mut ProjectTools,
+        max_steps: usize,
+        mut on_step: F,
+    ) -> Result<AgentResponse, String>
+    where
+        F: FnMut(&ToolStep) + Send,
+    {
+        let provider = self.current_provider().await?;
+        let (mut system_prompt, user_prompt) = Self::render_test_generation_prompts(&self.prompts, &request)?;
+
+        let tools_template = self.prompts.resolve(
+            AGENT_TOOLS_TEMPLATE,
+            &request.language,
+            request.test_framework.as_deref(),
+            request.project_dir.as_deref(),
+        )?;
+        let variables = HashMap::from([("tools", describe_tools()), ("max_steps", max_steps.to_string())]);
+        system_prompt.push_str("\n\n");
+        system_prompt.push_str(&render_template(&tools_template.content, &variables));
+
+        let mut messages = vec![
+            Message::new_system_message(&system_prompt),
+            Message::new_human_message(&user_prompt),
+        ];
+        let mut prompt_chars = system_prompt.len() + user_prompt.len();
+        let mut usage = CallUsage::default();
+        let mut step = 0;
+
+        loop {
+            let started = Instant::now();
+            let result = self
+                .retrying(|| {
+                    let llm = provider.llm();
+                    let messages = messages.clone();
+                    async move { llm.generate(&messages).await.map_err(|e| LlmError::classify(e.to_string())) }
                 })
-                .await
-                .map_err(|e| format!("Error generating tests: {e}"))?;
+                .await?;
+
+            let call_usage = Self::call_usage(provider.as_ref(), result.tokens, prompt_chars, &result.generation, started);
+            self.record_usage(UsageOperation::TestGeneration, request.project_dir.as_deref(), &call_usage);
+            usage.add(&call_usage);
+
+            let Some(call) = parse_tool_call(&result.generation) else {
+                log::info!("Agent answered after {} tool call(s)", step);
+                return Ok(AgentResponse { content: result.generation, usage: Some(usage), cached: false });
+            };
+            if step >= max_steps {
+                return Err(format!("The agent kept calling tools after its {} tool calls were used up", max_steps));
+            }
+            step += 1;
+
+            let output = tools.run(&call).await;
+            on_step(&ToolStep {
+                step,
+                tool: call.tool.clone(),
+                input: call.input.clone(),
+                output_chars: output.as_ref().map(String::len).unwrap_or_default(),
+                error: output.as_ref().err().cloned(),
+            });
+
+            let mut reply = match output {
+                Ok(output) => format!("Result of {}:\n\n{}", call.tool, output),
+                Err(e) => format!("{} failed: {}", call.tool, e),
+            };
+            reply.push_str(&match max_steps - step {
+                0 => "\n\nYou have no tool calls left. Respond with your final answer now.".to_string(),
+                left => format!("\n\nYou have {} tool call(s) left.", left),
+            });
+
+            prompt_chars += result.generation.len() + reply.len();
+            messages.push(Message::new_ai_message(&result.generation));
+            messages.push(Message::new_human_message(&reply));
+        }
+    }
+
+    /// Ask the agent to fix a generated test file, given the output of running it
+    pub async fn repair_tests(
+        &self,
+        source_code: String,
+        test_code: String,
+        failure_output: String,
+        language: String,
+        test_framework: Option<String>,
+        project_dir: Option<&Path>,
+    ) -> Result<AgentResponse, String> {
+        let provider = self.current_provider().await?;
+
+        let system_prompt = format!(
+            r#"You are a specialized test repair agent. A test file was generated for the source code provided, but running it with {}{} failed.
+
+Follow these guidelines:
+1. Fix compile errors, wrong imports and incorrect assumptions about the source code
+2. If a test fails because the expectation is wrong, correct the expectation to match the source code's actual behavior
+3. Keep every test that already passes and do not remove test cases unless they cannot be fixed
+4. Do not modify the source code
+
+Respond ONLY with the complete corrected test file, without explanations or commentary outside the code."#,
+            language,
+            test_framework
+                .map(|framework| format!(" and the {} testing framework", framework))
+                .unwrap_or_default(),
+        );
+
+        let prompt = message_formatter![
+            fmt_message!(Message::new_system_message(&system_prompt)),
+            fmt_template!(HumanMessagePromptTemplate::new(template_fstring!(
+                "Source code:\n\n```\n{source}\n```\n\nGenerated test file:\n\n```\n{tests}\n```\n\nOutput from running the tests:\n\n```\n{output}\n```\n\nReturn the corrected test file.",
+                "source", "tests", "output"
+            )))
+        ];
+
+        let chain = LLMChainBuilder::new()
+            .prompt(prompt)
+            .llm(provider.llm())
+            .build()
+            .map_err(|e| format!("Error building test repair chain: {e}"))?;
+
+        let prompt_chars = system_prompt.len() + source_code.len() + test_code.len() + failure_output.len();
+        let args = prompt_args! {
+            "source" => source_code,
+            "tests" => test_code,
+            "output" => failure_output,
+        };
+        let started = Instant::now();
+        let result = self.invoke_chain(&chain, args).await?;
+
+        let usage = Self::call_usage(provider.as_ref(), result.tokens, prompt_chars, &result.generation, started);
+        self.record_usage(UsageOperation::TestRepair, project_dir, &usage);
+
+        Ok(AgentResponse { content: result.generation, usage: Some(usage), cached: false })
+    }
+
+    /// Review an existing test file against its source code
+    ///
+    /// The review prompt is the `test_review` template, so it can be overridden per language,
+    /// framework or project like the test generation prompts.
+    pub async fn review_tests(
+        &self,
+        source_code: String,
+        test_code: String,
+        language: String,
+        test_framework: Option<String>,
+        project_dir: Option<&Path>,
+    ) -> Result<(TestReview, AgentResponse), String> {
+        let provider = self.current_provider().await?;
+
+        let template = self
+            .prompts
+            .resolve(TEST_REVIEW_TEMPLATE, &language, test_framework.as_deref(), project_dir)?;
+        let variables = HashMap::from([
+            ("language", language),
+            ("framework", test_framework.unwrap_or_default()),
+        ]);
+        let system_prompt = render_template(&template.content, &variables);
+        let user_prompt = format!(
+            "Source code:\n\n```\n{}\n```\n\nTest file:\n\n```\n{}\n```\n\nReview the test file.",
+            number_lines(&source_code),
+            number_lines(&test_code)
+        );
+        let messages = vec![
+            Message::new_system_message(&system_prompt),
+            Message::new_human_message(&user_prompt),
+        ];
+        let prompt_chars = system_prompt.len() + user_prompt.len();
+
+        let started = Instant::now();
+        let result = self
+            .retrying(|| {
+                let llm = provider.llm();
+                let messages = messages.clone();
+                async move { llm.generate(&messages).await.map_err(|e| LlmError::classify(e.to_string())) }
+            })
+            .await?;
+
+        let usage = Self::call_usage(provider.as_ref(), result.tokens, prompt_chars, &result.generation, started);
+        self.record_usage(UsageOperation::TestReview, project_dir, &usage);
+
+        let review = parse_test_review(&result.generation, source_code.lines().count(), test_code.lines().count())?;
+        Ok((review, AgentResponse { content: result.generation, usage: Some(usage), cached: false }))
+    }
 
-            let content = result.to_string();
-            Ok(AgentResponse { content })
+    /// Continue a conversation, sending the full message history
+    pub async fn chat(
+        &self,
+        system_prompt: String,
+        history: &[ChatMessage],
+        project_dir: Option<&Path>,
+    ) -> Result<AgentResponse, String> {
+        let provider = self.current_provider().await?;
+
+        let mut messages = vec![Message::new_system_message(&system_prompt)];
+        for message in history {
+            messages.push(match message.role.as_str() {
+                "assistant" => Message::new_ai_message(&message.content),
+                _ => Message::new_human_message(&message.content),
+            });
+        }
+        let prompt_chars = system_prompt.len() + history.iter().map(|m| m.content.len()).sum::<usize>();
+
+        let started = Instant::now();
+        let result = self
+            .retrying(|| {
+                let llm = provider.llm();
+                let messages = messages.clone();
+                async move { llm.generate(&messages).await.map_err(|e| LlmError::classify(e.to_string())) }
+            })
+            .await?;
+
+        let usage = Self::call_usage(provider.as_ref(), result.tokens, prompt_chars, &result.generation, started);
+        self.record_usage(UsageOperation::Chat, project_dir, &usage);
+
+        Ok(AgentResponse { content: result.generation, usage: Some(usage), cached: false })
+    }
+
+    /// Get the configured provider without holding the lock for the duration of a request
+    async fn current_provider(&self) -> Result<Arc<dyn LlmProvider>, String> {
+        let lock = self.provider.read().await;
+        lock.clone()
+            .ok_or_else(|| "Agent service has not been initialized with an API key".to_string())
+    }
+
+    /// Wait for a free request slot under the configured concurrency limit
+    /// The slot is released when the returned permit is dropped
+    async fn acquire_permit(&self) -> Result<OwnedSemaphorePermit, String> {
+        let limiter = self.limiter.read().await.clone();
+        limiter
+            .acquire_owned()
+            .await
+            .map_err(|e| format!("Failed to acquire request slot: {e}"))
+    }
+
+    /// Run a provider call, retrying transient failures under the configured policy
+    ///
+    /// Each attempt waits for a request slot of its own and gives it back before the backoff,
+    /// so a request waiting out a rate limit doesn't hold up unrelated requests.
+    async fn retrying<T, F, Fut>(&self, mut operation: F) -> Result<T, LlmError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, LlmError>>,
+    {
+        let policy = *self.retry_policy.read().await;
+        with_retry(
+            policy,
+            || {
+                let attempt = operation();
+                async move {
+                    let _permit = self.acquire_permit().await.map_err(LlmError::classify)?;
+                    attempt.await
+                }
+            },
+            |retry| self.notify_retry(retry),
+        )
+        .await
+    }
+
+    /// Invoke a chain, retrying transient failures
+    async fn invoke_chain(&self, chain: &LLMChain, args: PromptArgs) -> Result<GenerateResult, LlmError> {
+        self.retrying(|| {
+            let args = args.clone();
+            async move { chain.call(args).await.map_err(|e| LlmError::classify(e.to_string())) }
+        })
+        .await
+    }
+
+    /// Cache a test generation response, unless callers would reject it
+    ///
+    /// Only responses with tests that parse are cached, so a response that fails the callers'
+    /// checks is regenerated next time instead of being served from the cache again.
+    fn cache_if_usable(&self, request: &TestGenerationRequest, cache_key: &str, model: &str, content: &str) {
+        let code = if request.structured {
+            parse_test_suite(content, &request.language).map(|suite| suite.content)
         } else {
-            Err("Agent service has not been initialized with an API key".to_string())
+            extract_test_code(content, &request.language)
+        };
+        let usable = code.and_then(|code| {
+            if !is_supported(&request.language) {
+                return Ok(());
+            }
+            match check_syntax(&code, &request.language)?.len() {
+                0 => Ok(()),
+                errors => Err(format!("{} syntax error(s)", errors)),
+            }
+        });
+        match usable {
+            Ok(()) => self.cache.put(cache_key, model, content),
+            Err(e) => log::info!("Not caching unusable test generation response: {}", e),
         }
     }
-} 
\ No newline at end of file
+
+    /// Look up a cached test generation response unless the request bypasses the cache
+    fn cached_response(&self, request: &TestGenerationRequest, cache_key: &str) -> Option<String> {
+        if request.bypass_cache {
+            return None;
+        }
+        self.cache.get(cache_key)
+    }
+
+    /// Usage of a finished call, estimated from the prompt and output length if the provider didn't report it
+    fn call_usage(
+        provider: &dyn LlmProvider,
+        tokens: Option<TokenUsage>,
+        prompt_chars: usize,
+        output: &str,
+        started: Instant,
+    ) -> CallUsage {
+        let (input_tokens, output_tokens, estimated) = match tokens {
+            Some(tokens) => (tokens.prompt_tokens as u64, tokens.completion_tokens as u64, false),
+            None => (estimate_tokens(prompt_chars), estimate_tokens(output.len()), true),
+        };
+
+        CallUsage {
+            model: provider.model().to_string(),
+            input_tokens,
+            output_tokens,
+            estimated,
+            latency_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// Add a finished call to the usage ledger, tagged with the current request ID
+    fn record_usage(&self, operation: UsageOperation, project_dir: Option<&Path>, usage: &CallUsage) {
+        let project = project_dir.map(|dir| dir.to_string_lossy().to_string());
+        let request_id = REQUEST_ID.try_with(|id| id.clone()).ok();
+        self.usage.record(&UsageRecord::new(operation, project, request_id, usage.clone()));
+    }
+
+    /// Tell the retry listener about a retry of the current request
+    fn notify_retry(&self, retry: &RetryNotice) {
+        let listener = self.retry_listener.read().ok().and_then(|listener| listener.clone());
+        if let Some(listener) = listener {
+            let request_id = REQUEST_ID.try_with(|id| id.clone()).ok();
+            listener(request_id.as_deref(), retry);
+        }
+    }
+
+    /// Render the system and user prompts for a test generation request
+    fn render_test_generation_prompts(
+        prompts: &PromptRegistry,
+        request: &TestGenerationRequest,
+    ) -> Result<(String, String), String> {
+        let framework = request.test_framework.as_deref();
+        let project_dir = request.project_dir.as_deref();
+        let resolve = |name: &str| prompts.resolve(name, &request.language, framework, project_dir);
+
+        let mut variables = HashMap::from([
+            ("language", request.language.clone()),
+            ("framework", request.test_framework.clone().unwrap_or_default()),
+            ("framework_version", request.framework_version.clone().unwrap_or_default()),
+            ("code", request.code.clone()),
+            ("context", request.dependency_context.clone().unwrap_or_default()),
+            ("existing_tests", request.existing_tests.clone().unwrap_or_default()),
+            ("part", request.part.clone().unwrap_or_default()),
+            ("source_path", request.source_path.clone().unwrap_or_default()),
+            ("symbol", request.symbol.clone().unwrap_or_default()),
+            ("file", request.file_context.clone().unwrap_or_default()),
+            ("coverage", request.coverage_gaps.clone().unwrap_or_default()),
+        ]);
+        // Language-specific guidance is itself a template so it can use the other variables
+        let guidance = render_template(&resolve(TEST_GUIDANCE_TEMPLATE)?.content, &variables);
+        variables.insert("guidance", guidance);
+
+        let system_template = resolve(TEST_SYSTEM_TEMPLATE)?;
+        let user_template = resolve(TEST_USER_TEMPLATE)?;
+        log::debug!(
+            "Generating tests with prompt templates {} ({:?}) and {} ({:?})",
+            system_template.key,
+            system_template.source,
+            user_template.key,
+            user_template.source
+        );
+
+        let mut system_prompt = render_template(&system_template.content, &variables);
+        if request.structured {
+            let format = render_template(&resolve(TEST_JSON_OUTPUT_TEMPLATE)?.content, &variables);
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(&format);
+        }
+        let user_prompt = render_template(&user_template.content, &variables);
+        Ok((system_prompt, user_prompt))
+    }
+
+    /// Build the chain used for test generation from the system and user prompt templates
+    fn build_test_generation_chain(
+        provider: &dyn LlmProvider,
+        prompts: &PromptRegistry,
+        request: &TestGenerationRequest,
+    ) -> Result<TestGenerationChain, String> {
+        // The templates are rendered up front, so the chain itself takes no arguments
+        let (system_prompt, user_prompt) = Self::render_test_generation_prompts(prompts, request)?;
+        let prompt_chars = system_prompt.len() + user_prompt.len();
+        // The rendered prompt covers the code, context, templates, language and framework
+        let cache_key = ResponseCache::key(&[
+            &format!("{:?}", provider.kind()),
+            provider.model(),
+            &system_prompt,
+            &user_prompt,
+        ]);
+        let prompt = message_formatter![
+            fmt_message!(Message::new_system_message(system_prompt)),
+            fmt_message!(Message::new_human_message(user_prompt))
+        ];
+
+        let chain = LLMChainBuilder::new()
+            .prompt(prompt)
+            .llm(provider.llm())
+            .build()
+            .map_err(|e| format!("Error building test generation chain: {e}"))?;
+
+        Ok(TestGenerationChain {
+            chain,
+            prompt_chars,
+            cache_key,
+        })
+    }
+}
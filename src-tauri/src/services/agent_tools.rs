@@ -0,0 +1,317 @@
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::services::file_service::FileService;
+use crate::services::redaction::{RedactionReport, RedactionSession};
+
+/// Tool calls allowed per request unless configured otherwise
+pub const DEFAULT_TOOL_STEPS: usize = 8;
+
+/// Upper bound on tool calls per request, whatever the caller asks for
+pub const MAX_TOOL_STEPS: usize = 20;
+
+/// Tool output longer than this many characters is cut off
+const MAX_TOOL_OUTPUT_CHARS: usize = 40_000;
+
+/// Files larger than this many bytes can't be read with `read_file`
+const MAX_READ_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Entries `list_dir` returns at most
+const MAX_LISTED_ENTRIES: usize = 500;
+
+/// Matching lines `grep` returns at most
+const MAX_GREP_MATCHES: usize = 100;
+
+/// The tools the model can call, with what they take, as described in the prompt
+const TOOLS: &[(&str, &str)] = &[
+    (
+        "read_file",
+        r#"Reads a text file. Input: {"path": "file relative to the project root", "start_line": optional first line to read, "end_line": optional last line to read}"#,
+    ),
+    (
+        "list_dir",
+        r#"Lists the files and directories in a directory; directories end with a slash. Input: {"path": "directory relative to the project root, \".\" for the root"}"#,
+    ),
+    (
+        "grep",
+        r#"Finds lines matching a regular expression in the project's files. Input: {"pattern": "regular expression", "path": optional directory to search in, relative to the project root}"#,
+    ),
+    (
+        "find_test_for",
+        r#"Finds the existing test file of a source file. Input: {"path": "source file relative to the project root"}"#,
+    ),
+];
+
+/// A tool call parsed from a model response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolCall {
+    pub tool: String,
+    #[serde(default)]
+    pub input: Value,
+}
+
+/// A tool call the agent made and how it went; the output itself stays between the agent and the model
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolStep {
+    /// Which call this is, starting at 1
+    pub step: usize,
+    pub tool: String,
+    pub input: Value,
+    /// Length of the output sent back to the model
+    pub output_chars: usize,
+    /// Why the call failed, if it did; failures are reported to the model too
+    pub error: Option<String>,
+}
+
+/// The tools' descriptions, one per line, for the prompt
+pub fn describe_tools() -> String {
+    TOOLS
+        .iter()
+        .map(|(name, description)| format!("- {}: {}", name, description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses a response that is a tool call, i.e. only a JSON object with a `tool` name and
+/// an optional `input`
+///
+/// Anything else, including code that happens to contain JSON or a JSON answer with other
+/// fields, is the model's answer.
+pub fn parse_tool_call(response: &str) -> Option<ToolCall> {
+    let mut json = response.trim();
+    // Models sometimes fence the call despite being asked not to; code in another language is an answer
+    if let Some(fenced) = json.strip_prefix("```") {
+        let (tag, body) = fenced.split_once('\n')?;
+        if !matches!(tag.trim().to_lowercase().as_str(), "" | "json") {
+            return None;
+        }
+        json = body.trim_end().strip_suffix("```")?.trim();
+    }
+    if !json.starts_with('{') {
+        return None;
+    }
+    serde_json::from_str(json).ok()
+}
+
+/// Read-only file tools confined to one project
+///
+/// Every path is resolved against the project root and refused if it leads outside it, and
+/// all output is redacted like the rest of the prompt before it is sent back to the model.
+pub struct ProjectTools {
+    /// Canonical project root
+    root: PathBuf,
+    redaction: RedactionSession,
+}
+
+impl ProjectTools {
+    /// Tools for the project at `root`, redacting their output with `redaction`
+    pub fn new(root: &Path, redaction: RedactionSession) -> Result<Self, String> {
+        let root = root
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve project root {}: {}", root.display(), e))?;
+        Ok(Self { root, redaction })
+    }
+
+    /// Run a tool call, returning its redacted output
+    ///
+    /// The tools walk and read the file system, so they run on the blocking thread pool.
+    pub async fn run(&mut self, call: &ToolCall) -> Result<String, String> {
+        log::info!("Agent tool call {} {}", call.tool, call.input);
+        let root = self.root.clone();
+        let blocking_call = call.clone();
+        let output = tokio::task::spawn_blocking(move || run_tool(&root, &blocking_call))
+            .await
+            .map_err(|e| format!("{} failed: {}", call.tool, e))??;
+
+        let output = match output.char_indices().nth(MAX_TOOL_OUTPUT_CHARS) {
+            Some((end, _)) => format!("{}\n... (output cut off after {} characters)", &output[..end], MAX_TOOL_OUTPUT_CHARS),
+            None => output,
+        };
+        let location = string_input(&call.input, "path").unwrap_or_else(|| call.tool.clone());
+        Ok(self.redaction.redact(&output, &location))
+    }
+
+    /// The redactions made in tool output since the last call
+    pub fn take_redactions(&mut self) -> RedactionReport {
+        self.redaction.take_report()
+    }
+
+    /// Hand the redaction session back, so later prompts keep the same placeholders
+    pub fn into_redaction(self) -> RedactionSession {
+        self.redaction
+    }
+}
+
+/// Runs a tool call against the project at `root`, unredacted
+fn run_tool(root: &Path, call: &ToolCall) -> Result<String, String> {
+    let file_service = FileService::new();
+    match call.tool.as_str() {
+        "read_file" => read_file(&file_service, root, &call.input),
+        "list_dir" => list_dir(&file_service, root, &call.input),
+        "grep" => grep(&file_service, root, &call.input),
+        "find_test_for" => find_test_for(&file_service, root, &call.input),
+        other => {
+            let names: Vec<&str> = TOOLS.iter().map(|(name, _)| *name).collect();
+            Err(format!("Unknown tool {}; the tools are {}", other, names.join(", ")))
+        }
+    }
+}
+
+fn read_file(file_service: &FileService, root: &Path, input: &Value) -> Result<String, String> {
+    let relative = string_input(input, "path").ok_or("read_file needs a path")?;
+    let path = file_service.resolve_in_project(root, &relative)?;
+    if !path.is_file() {
+        return Err(format!("{} is not a file", relative));
+    }
+    // Checked before reading, so a huge file is never loaded just to be cut off
+    let size = path
+        .metadata()
+        .map_err(|e| format!("Failed to read {}: {}", relative, e))?
+        .len();
+    if size > MAX_READ_FILE_BYTES {
+        return Err(format!("{} is {} bytes; files over {} bytes can't be read", relative, size, MAX_READ_FILE_BYTES));
+    }
+    let content = file_service.read_file(&path)?;
+
+    let start = usize_input(input, "start_line").unwrap_or(1).max(1);
+    let end = usize_input(input, "end_line").unwrap_or(usize::MAX);
+    if start == 1 && end == usize::MAX {
+        return Ok(content);
+    }
+    let lines: Vec<&str> = content.lines().skip(start - 1).take(end.saturating_sub(start) + 1).collect();
+    if lines.is_empty() {
+        return Err(format!("{} has only {} lines", relative, content.lines().count()));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn list_dir(file_service: &FileService, root: &Path, input: &Value) -> Result<String, String> {
+    let relative = string_input(input, "path").unwrap_or_else(|| ".".to_string());
+    let path = file_service.resolve_in_project(root, &relative)?;
+    if !path.is_dir() {
+        return Err(format!("{} is not a directory", relative));
+    }
+
+    let entries = file_service.list_dir(&path)?;
+    if entries.is_empty() {
+        return Ok(format!("{} is empty", relative));
+    }
+    let total = entries.len();
+    let mut listing = entries.into_iter().take(MAX_LISTED_ENTRIES).collect::<Vec<_>>().join("\n");
+    if total > MAX_LISTED_ENTRIES {
+        listing.push_str(&format!("\n... and {} more", total - MAX_LISTED_ENTRIES));
+    }
+    Ok(listing)
+}
+
+fn grep(file_service: &FileService, root: &Path, input: &Value) -> Result<String, String> {
+    let pattern = string_input(input, "pattern").ok_or("grep needs a pattern")?;
+    let regex = RegexBuilder::new(&pattern)
+        .size_limit(1024 * 1024)
+        .build()
+        .map_err(|e| format!("Invalid pattern {}: {}", pattern, e))?;
+    let relative = string_input(input, "path").unwrap_or_else(|| ".".to_string());
+    let dir = file_service.resolve_in_project(root, &relative)?;
+
+    let matches = file_service.grep(root, &dir, &regex, MAX_GREP_MATCHES);
+    if matches.is_empty() {
+        return Ok(format!("No lines match {}", pattern));
+    }
+    let mut output = matches
+        .iter()
+        .map(|(path, line, text)| format!("{}:{}: {}", path, line, text))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if matches.len() >= MAX_GREP_MATCHES {
+        output.push_str(&format!("\n... stopped after {} matches; narrow the pattern or path", MAX_GREP_MATCHES));
+    }
+    Ok(output)
+}
+
+fn find_test_for(file_service: &FileService, root: &Path, input: &Value) -> Result<String, String> {
+    let relative = string_input(input, "path").ok_or("find_test_for needs a path")?;
+    // Checked only to keep the lookup inside the project
+    file_service.resolve_in_project(root, &relative)?;
+
+    Ok(match file_service.find_existing_test_file(root, relative.trim()) {
+        Some(test_file) => test_file,
+        None => format!("{} has no test file", relative),
+    })
+}
+
+fn string_input(input: &Value, key: &str) -> Option<String> {
+    input
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn usize_input(input: &Value, key: &str) -> Option<usize> {
+    input.get(key).and_then(Value::as_u64).map(|value| value as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_a_bare_tool_call() {
+        let call = parse_tool_call(r#"  {"tool": "read_file", "input": {"path": "src/utils.ts"}}  "#).unwrap();
+        assert_eq!(call.tool, "read_file");
+        assert_eq!(call.input, json!({"path": "src/utils.ts"}));
+
+        // The input is optional
+        let call = parse_tool_call(r#"{"tool": "list_dir"}"#).unwrap();
+        assert_eq!((call.tool.as_str(), call.input), ("list_dir", Value::Null));
+    }
+
+    #[test]
+    fn parses_a_fenced_tool_call() {
+        for response in [
+            "```json\n{\"tool\": \"grep\", \"input\": {\"pattern\": \"fn main\"}}\n```",
+            "```\n{\"tool\": \"grep\", \"input\": {\"pattern\": \"fn main\"}}\n```\n",
+        ] {
+            let call = parse_tool_call(response).unwrap();
+            assert_eq!(call.tool, "grep");
+            assert_eq!(call.input["pattern"], "fn main");
+        }
+
+        // An unterminated fence isn't a complete call
+        assert!(parse_tool_call("```json\n{\"tool\": \"grep\"}").is_none());
+    }
+
+    #[test]
+    fn treats_prose_with_braces_as_an_answer() {
+        assert!(parse_tool_call("I'll call {\"tool\": \"read_file\"} to look at the helpers.").is_none());
+        assert!(parse_tool_call("{tool} and {input} are placeholders in the template").is_none());
+        assert!(parse_tool_call("{\"tool\": \"read_file\"} first, then the tests").is_none());
+    }
+
+    #[test]
+    fn treats_test_code_containing_a_tool_object_as_an_answer() {
+        let fenced = "```ts\n{\"tool\": \"read_file\", \"input\": {\"path\": \"a.ts\"}}\n```";
+        assert!(parse_tool_call(fenced).is_none());
+
+        let test_code = r#"import { parseToolCall } from './agent';
+
+test('parses a tool call', () => {
+  expect(parseToolCall('{"tool": "read_file", "input": {}}')).toEqual({ tool: 'read_file', input: {} });
+});"#;
+        assert!(parse_tool_call(test_code).is_none());
+
+        // A structured answer whose test file holds a tool call, or that has a tool field among others
+        let structured = json!({
+            "test_path": "tests/agent.test.ts",
+            "content": "const call = {\"tool\": \"grep\"};",
+            "covered_functions": [],
+        });
+        assert!(parse_tool_call(&structured.to_string()).is_none());
+        assert!(parse_tool_call(r#"{"tool": "grep", "input": {}, "expected": "error"}"#).is_none());
+    }
+}
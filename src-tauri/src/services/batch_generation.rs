@@ -0,0 +1,114 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Number of files generated at once unless the caller asks for a different number
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+
+/// Upper bound on files generated at once, whatever the caller asks for
+pub const MAX_BATCH_CONCURRENCY: usize = 16;
+
+/// Where a file of a batch is in its generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchFileStatus {
+    Started,
+    Succeeded,
+    Failed,
+}
+
+/// Progress or outcome of generating the test for one file of a batch
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchFileResult {
+    /// Source file, relative to the project directory
+    pub source_file: String,
+    /// Request ID the file was generated under, for cancelling it or matching its events
+    pub request_id: String,
+    pub status: BatchFileStatus,
+    /// Path of the written test file, if generation succeeded
+    pub test_file_path: Option<String>,
+    /// Why generation failed, if it did
+    pub error: Option<String>,
+}
+
+/// Result of a batch test generation job
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchSummary {
+    pub batch_id: String,
+    /// Number of files in the batch
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// One result per file, in the order they finished
+    pub results: Vec<BatchFileResult>,
+}
+
+impl BatchSummary {
+    /// Summarizes the results of a finished batch
+    pub fn new(batch_id: String, results: Vec<BatchFileResult>) -> Self {
+        let succeeded = results
+            .iter()
+            .filter(|result| result.status == BatchFileStatus::Succeeded)
+            .count();
+        Self {
+            batch_id,
+            total: results.len(),
+            succeeded,
+            failed: results.len() - succeeded,
+            results,
+        }
+    }
+}
+
+/// Chooses the files of a batch from a `find_test_files` result
+///
+/// With `selected`, those files are used as long as the analysis found them; otherwise every
+/// source file without a test is. Files are sorted so batches run in a predictable order.
+pub fn select_batch_files(
+    source_files: &HashMap<String, Option<String>>,
+    selected: Option<Vec<String>>,
+) -> Vec<String> {
+    let mut files: Vec<String> = match selected {
+        Some(selected) => selected
+            .into_iter()
+            .filter(|file| {
+                let known = source_files.contains_key(file);
+                if !known {
+                    log::warn!("Skipping {}: not a source file found in the project", file);
+                }
+                known
+            })
+            .collect(),
+        None => source_files
+            .iter()
+            .filter(|(_, test_file)| test_file.is_none())
+            .map(|(source_file, _)| source_file.clone())
+            .collect(),
+    };
+
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// The language name the agent expects for a source file, from its extension
+pub fn language_for_file(source_file: &str) -> Option<&'static str> {
+    let extension = Path::new(source_file).extension()?.to_str()?.to_lowercase();
+    let language = match extension.as_str() {
+        "ts" => "typescript",
+        "tsx" | "jsx" => "react",
+        "js" | "mjs" | "cjs" => "javascript",
+        "py" => "python",
+        "rs" => "rust",
+        "go" => "go",
+        "java" => "java",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cpp" | "hpp" => "cpp",
+        _ => return None,
+    };
+    Some(language)
+}
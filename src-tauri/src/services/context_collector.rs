@@ -0,0 +1,855 @@
+use log::info;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use crate::services::file_service::FileService;
+
+/// Default number of characters of dependency context to include in a prompt
+pub const DEFAULT_CONTEXT_BUDGET: usize = 12_000;
+
+/// Signatures extracted from one local dependency
+#[derive(Debug, Clone)]
+pub struct DependencySnippet {
+    /// Path of the dependency relative to the project root
+    pub path: String,
+    /// Type and function signatures found in the file
+    pub signatures: String,
+}
+
+/// Collects type and function signatures from the local files a source file imports
+pub struct ContextCollector {
+    /// Canonical project root; no file outside it is read
+    project_root: PathBuf,
+    file_service: FileService,
+}
+
+impl ContextCollector {
+    /// Creates a collector for the given project root
+    pub fn new<P: AsRef<Path>>(project_root: P) -> Self {
+        let project_root = project_root.as_ref();
+        Self {
+            project_root: project_root.canonicalize().unwrap_or_else(|_| project_root.to_path_buf()),
+            file_service: FileService::new(),
+        }
+    }
+
+    /// Builds the dependency context for a source file, limited to `budget` characters
+    /// Returns None if the file has no resolvable local imports
+    pub fn collect(&self, source_file: &str, source_code: &str, budget: usize) -> Option<String> {
+        let snippets = self.collect_snippets(source_file, source_code);
+        if snippets.is_empty() {
+            return None;
+        }
+
+        let mut context = String::new();
+        for snippet in snippets {
+            let section = format!("// {}\n{}\n\n", snippet.path, snippet.signatures.trim_end());
+            let remaining = budget.saturating_sub(context.len());
+            if remaining == 0 {
+                break;
+            }
+
+            if section.len() <= remaining {
+                context.push_str(&section);
+            } else {
+                // Cut the last section on a line boundary so we never send half a signature
+                let cut: String = section
+                    .lines()
+                    .scan(0, |used, line| {
+                        *used += line.len() + 1;
+                        (*used <= remaining).then_some(line)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if !cut.is_empty() {
+                    context.push_str(&cut);
+                    context.push('\n');
+                }
+                break;
+            }
+        }
+
+        info!("Collected {} characters of dependency context for {}", context.len(), source_file);
+        Some(context.trim_end().to_string()).filter(|c| !c.is_empty())
+    }
+
+    /// Resolves the source file's local imports and extracts their signatures
+    /// Only files inside the project are read, whatever the imports point at
+    pub fn collect_snippets(&self, source_file: &str, source_code: &str) -> Vec<DependencySnippet> {
+        let source_path = self.project_root.join(source_file);
+        let canonical_source = source_path.canonicalize().unwrap_or_else(|_| source_path.clone());
+        let extension = Path::new(source_file)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+
+        let dependencies = match extension {
+            "rs" => self.resolve_rust_imports(&source_path, source_code),
+            "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" => self.resolve_js_imports(&source_path, source_code),
+            "py" => self.resolve_python_imports(&source_path, source_code),
+            _ => Vec::new(),
+        };
+
+        let mut seen = HashSet::new();
+        dependencies
+            .into_iter()
+            .filter(|path| *path != canonical_source && seen.insert(path.clone()))
+            .filter_map(|path| {
+                // Resolved paths are canonical paths inside the project; anything else is skipped
+                let relative = path.strip_prefix(&self.project_root).ok()?.to_string_lossy().into_owned();
+                let content = self.file_service.read_file(&path).ok()?;
+                let signatures = extract_signatures(&content, extension);
+                if signatures.trim().is_empty() {
+                    return None;
+                }
+                Some(DependencySnippet { path: relative, signatures })
+            })
+            .collect()
+    }
+
+    /// Resolves `use crate::…`, `use super::…` and `use self::…` imports to files
+    fn resolve_rust_imports(&self, source_path: &Path, source_code: &str) -> Vec<PathBuf> {
+        let Some(src_dir) = self.find_rust_src_dir(source_path) else {
+            return Vec::new();
+        };
+
+        // Directories holding the current module's children and its parent's children
+        let stem = source_path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let file_dir = source_path.parent().map(Path::to_path_buf);
+        let (module_dir, parent_dir) = if matches!(stem, "mod" | "lib" | "main") {
+            (file_dir.clone(), file_dir.and_then(|d| d.parent().map(Path::to_path_buf)))
+        } else {
+            (file_dir.as_ref().map(|d| d.join(stem)), file_dir)
+        };
+
+        let mut resolved = Vec::new();
+        for statement in rust_use_statements(source_code) {
+            let mut segments: Vec<&str> = statement.split("::").map(str::trim).collect();
+            let base = match segments.first().copied() {
+                Some("crate") => Some(src_dir.clone()),
+                Some("self") => module_dir.clone(),
+                Some("super") => {
+                    let mut dir = parent_dir.clone();
+                    let mut supers = 0;
+                    while segments.get(supers + 1) == Some(&"super") {
+                        dir = dir.and_then(|d| d.parent().map(Path::to_path_buf));
+                        supers += 1;
+                    }
+                    segments.drain(..supers);
+                    dir
+                }
+                _ => None,
+            };
+            let Some(base) = base else { continue };
+
+            // Drop the leading keyword and any `{...}` group or glob at the end
+            let path_segments: Vec<&str> = segments[1..]
+                .iter()
+                .copied()
+                .filter(|s| !s.is_empty() && !s.starts_with('{') && *s != "*")
+                .collect();
+
+            // Try the longest module path first: `a::b::Item` may live in a/b.rs or a.rs
+            for len in (1..=path_segments.len()).rev() {
+                let module = path_segments[..len].join("/");
+                let candidates = [base.join(format!("{}.rs", module)), base.join(&module).join("mod.rs")];
+                if let Some(found) = candidates.iter().find_map(|c| self.project_file(c)) {
+                    resolved.push(found);
+                    break;
+                }
+            }
+        }
+
+        resolved
+    }
+
+    /// Finds the `src` directory of the crate containing the given file
+    fn find_rust_src_dir(&self, source_path: &Path) -> Option<PathBuf> {
+        let mut dir = source_path.parent();
+        while let Some(current) = dir {
+            if current.join("Cargo.toml").exists() {
+                return Some(current.join("src"));
+            }
+            if current == self.project_root {
+                break;
+            }
+            dir = current.parent();
+        }
+        None
+    }
+
+    /// Resolves relative `import … from './x'`, `export … from './x'` and `require('./x')`
+    fn resolve_js_imports(&self, source_path: &Path, source_code: &str) -> Vec<PathBuf> {
+        let Some(dir) = source_path.parent() else {
+            return Vec::new();
+        };
+
+        js_import_specifiers(source_code)
+            .into_iter()
+            .filter(|spec| spec.starts_with("./") || spec.starts_with("../"))
+            .filter_map(|spec| {
+                let base = normalize(&dir.join(&spec));
+                let extensions = ["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+                // The specifier itself only counts if it names a script, e.g. `./util.js`
+                let mut candidates = Vec::new();
+                if base.extension().and_then(|e| e.to_str()).is_some_and(|e| extensions.contains(&e)) {
+                    candidates.push(base.clone());
+                }
+                candidates.extend(extensions.iter().map(|ext| PathBuf::from(format!("{}.{}", base.display(), ext))));
+                candidates.extend(extensions.iter().map(|ext| base.join(format!("index.{}", ext))));
+
+                candidates.iter().find_map(|c| self.project_file(c))
+            })
+            .collect()
+    }
+
+    /// Resolves `from .x import y`, `from pkg.mod import y` and `import pkg.mod` to project files
+    fn resolve_python_imports(&self, source_path: &Path, source_code: &str) -> Vec<PathBuf> {
+        let Some(dir) = source_path.parent() else {
+            return Vec::new();
+        };
+        let roots = [self.project_root.clone(), self.project_root.join("src")];
+
+        let mut resolved = Vec::new();
+        for (module, names) in python_imports(source_code) {
+            let dots = module.chars().take_while(|c| *c == '.').count();
+            let dotted = &module[dots..];
+
+            let bases: Vec<PathBuf> = if dots > 0 {
+                let mut base = dir.to_path_buf();
+                for _ in 1..dots {
+                    base = base.parent().map(Path::to_path_buf).unwrap_or(base);
+                }
+                vec![base]
+            } else {
+                roots.to_vec()
+            };
+
+            // `from pkg import mod` may refer to pkg/mod.py as well as a name in pkg/__init__.py
+            let mut modules: Vec<String> = names
+                .iter()
+                .map(|name| if dotted.is_empty() { name.clone() } else { format!("{}.{}", dotted, name) })
+                .collect();
+            if !dotted.is_empty() {
+                modules.push(dotted.to_string());
+            }
+
+            for module in modules {
+                let relative = module.replace('.', "/");
+                for base in &bases {
+                    let candidates = [base.join(format!("{}.py", relative)), base.join(&relative).join("__init__.py")];
+                    if let Some(found) = candidates.iter().find_map(|c| self.project_file(c)) {
+                        resolved.push(found);
+                        break;
+                    }
+                }
+            }
+        }
+
+        resolved
+    }
+
+    /// The canonical path of an import candidate, if it is a file inside the project
+    ///
+    /// Specifiers can climb out with `..` and symlinks can point anywhere, so candidates are
+    /// only trusted after both are resolved.
+    fn project_file(&self, candidate: &Path) -> Option<PathBuf> {
+        let path = candidate.canonicalize().ok()?;
+        (path.starts_with(&self.project_root) && path.is_file()).then_some(path)
+    }
+}
+
+/// Returns the paths of all `use` statements, with multi-line statements joined
+fn rust_use_statements(source_code: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current: Option<String> = None;
+
+    for line in source_code.lines() {
+        let trimmed = line.trim();
+        if let Some(statement) = current.as_mut() {
+            statement.push_str(trimmed);
+        } else if let Some(rest) = trimmed.strip_prefix("use ").or_else(|| trimmed.strip_prefix("pub use ")) {
+            current = Some(rest.to_string());
+        } else if let Some(rest) = trimmed.strip_prefix("pub(crate) use ") {
+            current = Some(rest.to_string());
+        }
+
+        if current.as_ref().is_some_and(|s| s.contains(';')) {
+            let statement = current.take().unwrap_or_default();
+            statements.extend(expand_rust_use(statement.trim_end_matches(';')));
+        }
+    }
+
+    statements
+}
+
+/// Expands `a::{b, c::D}` into `a::b` and `a::c::D` (one level of nesting)
+fn expand_rust_use(statement: &str) -> Vec<String> {
+    match (statement.find('{'), statement.rfind('}')) {
+        (Some(open), Some(close)) if open < close => {
+            let prefix = &statement[..open];
+            split_top_level(&statement[open + 1..close])
+                .into_iter()
+                .map(|item| format!("{}{}", prefix, item.trim()))
+                .collect()
+        }
+        _ => vec![statement.to_string()],
+    }
+}
+
+/// Splits on commas that are not inside braces
+fn split_top_level(list: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in list.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    items.push(current);
+    items.into_iter().filter(|i| !i.trim().is_empty()).collect()
+}
+
+/// Returns the module specifiers of all imports, re-exports and requires
+fn js_import_specifiers(source_code: &str) -> Vec<String> {
+    let mut specifiers = Vec::new();
+
+    for line in source_code.lines() {
+        let trimmed = line.trim();
+        let is_import = trimmed.starts_with("import ") || trimmed.starts_with("export ") || trimmed.starts_with('}');
+        if is_import {
+            if let Some(index) = trimmed.find(" from ") {
+                if let Some(spec) = quoted(&trimmed[index + 6..]) {
+                    specifiers.push(spec);
+                }
+            } else if let Some(rest) = trimmed.strip_prefix("import ") {
+                // Side-effect import: import './setup'
+                if let Some(spec) = quoted(rest) {
+                    specifiers.push(spec);
+                }
+            }
+        }
+
+        let mut rest = trimmed;
+        while let Some(index) = rest.find("require(") {
+            rest = &rest[index + 8..];
+            if let Some(spec) = quoted(rest) {
+                specifiers.push(spec);
+            }
+        }
+    }
+
+    specifiers
+}
+
+/// Returns the contents of the first quoted string at the start of `text`
+fn quoted(text: &str) -> Option<String> {
+    let text = text.trim_start();
+    let quote = text.chars().next().filter(|c| matches!(c, '\'' | '"' | '`'))?;
+    let rest = &text[1..];
+    rest.find(quote).map(|end| rest[..end].to_string())
+}
+
+/// Returns (module, imported names) for every import statement
+fn python_imports(source_code: &str) -> Vec<(String, Vec<String>)> {
+    let mut imports = Vec::new();
+
+    for line in source_code.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("from ") {
+            if let Some((module, names)) = rest.split_once(" import ") {
+                let names = names
+                    .trim_matches(|c| c == '(' || c == ')' || c == ' ')
+                    .split(',')
+                    .map(|n| n.split(" as ").next().unwrap_or("").trim().to_string())
+                    .filter(|n| !n.is_empty() && n != "*")
+                    .collect();
+                imports.push((module.trim().to_string(), names));
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("import ") {
+            for module in rest.split(',') {
+                let module = module.split(" as ").next().unwrap_or("").trim();
+                if !module.is_empty() {
+                    imports.push((module.to_string(), Vec::new()));
+                }
+            }
+        }
+    }
+
+    imports
+}
+
+/// Collapses `.` and `..` components without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
+
+/// Extracts the public surface of a file: type definitions in full, functions as signatures
+pub fn extract_signatures(content: &str, extension: &str) -> String {
+    match extension {
+        "rs" => extract_brace_signatures(content, &RUST_RULES),
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" => extract_brace_signatures(content, &JS_RULES),
+        "py" => extract_python_signatures(content),
+        _ => String::new(),
+    }
+}
+
+/// Declaration keywords for a brace-delimited language
+struct BraceRules {
+    /// Declarations whose whole body is kept (data types)
+    full_body: &'static [&'static str],
+    /// Declarations whose body is dropped, keeping only the first line of the signature
+    signature_only: &'static [&'static str],
+    /// Declarations whose member signatures are kept (classes, impls, traits)
+    members: &'static [&'static str],
+    /// Visibility prefixes that may precede a declaration keyword
+    modifiers: &'static [&'static str],
+    /// Declarations other than `members` blocks must carry a modifier to be importable
+    exported_only: bool,
+}
+
+const RUST_RULES: BraceRules = BraceRules {
+    full_body: &["struct ", "enum ", "type ", "const ", "static "],
+    signature_only: &["fn ", "async fn ", "const fn ", "unsafe fn "],
+    members: &["impl ", "impl<", "trait "],
+    modifiers: &["pub(crate) ", "pub(super) ", "pub "],
+    exported_only: true,
+};
+
+const JS_RULES: BraceRules = BraceRules {
+    full_body: &["interface ", "type ", "enum "],
+    signature_only: &["function ", "async function ", "const ", "let "],
+    members: &["class ", "abstract class "],
+    modifiers: &["export default ", "export ", "declare "],
+    exported_only: true,
+};
+
+/// Extracts declarations from a brace-delimited language using the given rules
+fn extract_brace_signatures(content: &str, rules: &BraceRules) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut output: Vec<&str> = Vec::new();
+    let mut attributes: Vec<&str> = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        let trimmed = line.trim_start();
+
+        // Only top-level declarations are considered
+        if line.len() != trimmed.len() || trimmed.is_empty() {
+            index += 1;
+            continue;
+        }
+
+        // Attributes and decorators belong to the declaration that follows them
+        if trimmed.starts_with("#[") || trimmed.starts_with('@') {
+            attributes.push(line);
+            index += 1;
+            continue;
+        }
+
+        let mut declaration = trimmed;
+        for modifier in rules.modifiers {
+            if let Some(rest) = declaration.strip_prefix(modifier) {
+                declaration = rest;
+            }
+        }
+        let exported = declaration.len() != trimmed.len() || !rules.exported_only;
+        let starts_with_any = |keywords: &[&str]| keywords.iter().any(|k| declaration.starts_with(k));
+
+        if starts_with_any(rules.members) {
+            let end = block_end(&lines, index);
+            output.append(&mut attributes);
+            output.extend(member_signatures(&lines[index..=end]));
+            output.push("");
+            index = end + 1;
+        } else if exported && starts_with_any(rules.signature_only) {
+            let end = block_end(&lines, index);
+            output.append(&mut attributes);
+            output.push(line.trim_end_matches(['{', ' ']));
+            output.push("");
+            index = end + 1;
+        } else if exported && starts_with_any(rules.full_body) {
+            let end = block_end(&lines, index);
+            output.append(&mut attributes);
+            output.extend_from_slice(&lines[index..=end]);
+            output.push("");
+            index = end + 1;
+        } else {
+            index += 1;
+        }
+
+        attributes.clear();
+    }
+
+    output.join("\n")
+}
+
+/// Index of the line that closes the block starting at `start`
+/// Declarations without a block end at the first line ending with `;`
+fn block_end(lines: &[&str], start: usize) -> usize {
+    let mut depth: i32 = 0;
+    let mut opened = false;
+
+    for (offset, line) in lines[start..].iter().enumerate() {
+        for c in line.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if (opened && depth <= 0) || (!opened && line.trim_end().ends_with(';')) {
+            return start + offset;
+        }
+    }
+
+    lines.len() - 1
+}
+
+/// Keeps the header of a class/impl block and the first line of each direct member
+fn member_signatures<'a>(block: &[&'a str]) -> Vec<&'a str> {
+    let mut output = vec![block[0]];
+    let mut depth: i32 = 0;
+
+    for (index, line) in block.iter().enumerate() {
+        let depth_before = depth;
+        for c in line.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+
+        if index > 0 && depth_before == 1 {
+            let trimmed = line.trim();
+            let is_member = !trimmed.is_empty()
+                && !trimmed.starts_with("//")
+                && !trimmed.starts_with('*')
+                && !trimmed.starts_with("/*")
+                && trimmed != "}";
+            if is_member {
+                output.push(line.trim_end_matches(['{', ' ']));
+            }
+        }
+    }
+
+    if block.len() > 1 {
+        output.push("}");
+    }
+    output
+}
+
+/// Extracts class headers, annotated class fields and function signatures from Python
+fn extract_python_signatures(content: &str) -> String {
+    let mut output = Vec::new();
+    let mut in_class = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if indent == 0 && !trimmed.is_empty() {
+            in_class = trimmed.starts_with("class ");
+        }
+
+        let is_signature = trimmed.starts_with("def ")
+            || trimmed.starts_with("async def ")
+            || trimmed.starts_with("class ")
+            || trimmed.starts_with('@');
+        let is_class_field = in_class
+            && indent > 0
+            && indent <= 4
+            && trimmed.contains(':')
+            && !trimmed.ends_with(':')
+            && trimmed.split(':').next().is_some_and(|name| name.trim().chars().all(|c| c.is_alphanumeric() || c == '_'));
+
+        if (is_signature && indent <= 4) || is_class_field {
+            output.push(line);
+        }
+    }
+
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A fresh directory holding `project/` and a file outside it, with the given project files
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("context-collector-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(&base).unwrap();
+        for outside in ["outside.ts", "leak.py"] {
+            fs::write(base.join(outside), "export function secret(): string {}\ndef secret():\n").unwrap();
+        }
+
+        let root = base.join("project");
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    fn snippet_paths(root: &Path, source_file: &str) -> Vec<String> {
+        let source = fs::read_to_string(root.join(source_file)).unwrap();
+        let mut paths: Vec<String> = ContextCollector::new(root)
+            .collect_snippets(source_file, &source)
+            .into_iter()
+            .map(|snippet| snippet.path.replace('\\', "/"))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn resolves_rust_crate_super_and_nested_imports() {
+        let root = project(
+            "rust",
+            &[
+                ("Cargo.toml", "[package]\nname = \"shop\"\n"),
+                ("src/lib.rs", "pub mod cart;\n"),
+                (
+                    "src/cart.rs",
+                    "use crate::model::{Item, store::Store};\nuse super::util::round;\nuse self::line::Line;\nuse std::fmt;\n\npub fn total() {}\n",
+                ),
+                ("src/model.rs", "pub struct Item;\n"),
+                ("src/model/store/mod.rs", "pub struct Store;\n"),
+                ("src/util.rs", "pub fn round(value: f64) -> f64 {\n    value.round()\n}\n"),
+                ("src/cart/line.rs", "pub struct Line;\n"),
+            ],
+        );
+
+        assert_eq!(
+            snippet_paths(&root, "src/cart.rs"),
+            ["src/cart/line.rs", "src/model.rs", "src/model/store/mod.rs", "src/util.rs"]
+        );
+    }
+
+    #[test]
+    fn resolves_relative_js_imports_and_skips_packages_and_assets() {
+        let root = project(
+            "js",
+            &[
+                (
+                    "src/cart.ts",
+                    "import { Item } from './item';\nimport { format } from '../shared/money';\nimport { Button } from './components';\nimport './styles.css';\nimport _ from 'lodash';\nconst legacy = require('./legacy.js');\n",
+                ),
+                ("src/item.ts", "export interface Item {\n  price: number;\n}\n"),
+                ("shared/money.js", "export function format(value) {\n  return `${value}`;\n}\n"),
+                ("src/components/index.tsx", "export function Button() {\n  return null;\n}\n"),
+                ("src/styles.css", "export function notScript() {}\n"),
+                ("src/legacy.js", "export const legacy = () => 1;\n"),
+            ],
+        );
+
+        assert_eq!(
+            snippet_paths(&root, "src/cart.ts"),
+            ["shared/money.js", "src/components/index.tsx", "src/item.ts", "src/legacy.js"]
+        );
+    }
+
+    #[test]
+    fn resolves_relative_and_absolute_python_imports() {
+        let root = project(
+            "python",
+            &[
+                (
+                    "app/cart.py",
+                    "from .item import Item\nfrom app.models import Model\nimport utils\nfrom . import pricing\nimport os\n",
+                ),
+                ("app/__init__.py", ""),
+                ("app/item.py", "class Item:\n    price: int\n"),
+                ("app/models/__init__.py", "class Model:\n    pass\n"),
+                ("app/pricing.py", "def price(item):\n    return item.price\n"),
+                ("src/utils.py", "def round_price(value):\n    return round(value, 2)\n"),
+            ],
+        );
+
+        assert_eq!(
+            snippet_paths(&root, "app/cart.py"),
+            ["app/item.py", "app/models/__init__.py", "app/pricing.py", "src/utils.py"]
+        );
+    }
+
+    #[test]
+    fn never_reads_files_outside_the_project() {
+        let root = project(
+            "escape",
+            &[
+                ("src/app.ts", "import { secret } from '../../outside';\nimport { item } from './item';\n"),
+                ("src/item.ts", "export function item() {}\n"),
+                ("app/main.py", "from ... import leak\nfrom ..app import main\n"),
+            ],
+        );
+
+        assert_eq!(snippet_paths(&root, "src/app.ts"), ["src/item.ts"]);
+        assert!(snippet_paths(&root, "app/main.py").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn never_follows_symlinks_out_of_the_project() {
+        let root = project("symlink", &[("src/app.ts", "import { secret } from './linked';\n")]);
+        std::os::unix::fs::symlink(root.parent().unwrap().join("outside.ts"), root.join("src/linked.ts")).unwrap();
+
+        assert!(snippet_paths(&root, "src/app.ts").is_empty());
+    }
+
+    #[test]
+    fn cuts_the_context_on_a_line_boundary() {
+        let root = project(
+            "budget",
+            &[
+                ("src/app.ts", "import { a } from './a';\n"),
+                ("src/a.ts", "export function first(): void {}\nexport function second(): void {}\n"),
+            ],
+        );
+        let collector = ContextCollector::new(&root);
+        let source = "import { a } from './a';\n";
+
+        assert_eq!(
+            collector.collect("src/app.ts", source, 1_000).unwrap(),
+            "// src/a.ts\nexport function first(): void {}\n\nexport function second(): void {}"
+        );
+        assert_eq!(collector.collect("src/app.ts", source, 50).unwrap(), "// src/a.ts\nexport function first(): void {}");
+        assert_eq!(collector.collect("src/app.ts", "import os from 'os';\n", 1_000), None);
+    }
+
+    #[test]
+    fn extracts_rust_signatures() {
+        let content = r#"use std::fmt;
+
+/// A line of a cart
+#[derive(Debug)]
+pub struct Line {
+    pub price: u32,
+}
+
+struct Private;
+
+pub fn total(lines: &[Line]) -> u32 {
+    lines.iter().map(|line| line.price).sum()
+}
+
+fn helper() {}
+
+impl Line {
+    pub fn new(price: u32) -> Self {
+        Self { price }
+    }
+
+    // Doubles the price
+    pub fn double(&self) -> u32 {
+        self.price * 2
+    }
+}
+"#;
+
+        let expected = r#"#[derive(Debug)]
+pub struct Line {
+    pub price: u32,
+}
+
+pub fn total(lines: &[Line]) -> u32
+
+impl Line {
+    pub fn new(price: u32) -> Self
+    pub fn double(&self) -> u32
+}
+"#;
+        assert_eq!(extract_signatures(content, "rs"), expected);
+    }
+
+    #[test]
+    fn extracts_typescript_signatures() {
+        let content = r#"import { Item } from './item';
+
+export interface Cart {
+  items: Item[];
+}
+
+export const EMPTY = 0;
+
+function internal() {}
+
+export async function checkout(cart: Cart): Promise<void> {
+  await pay(cart);
+}
+
+export class Store {
+  private carts: Cart[] = [];
+
+  add(cart: Cart) {
+    this.carts.push(cart);
+  }
+}
+"#;
+
+        let expected = r#"export interface Cart {
+  items: Item[];
+}
+
+export const EMPTY = 0;
+
+export async function checkout(cart: Cart): Promise<void>
+
+export class Store {
+  private carts: Cart[] = [];
+  add(cart: Cart)
+}
+"#;
+        assert_eq!(extract_signatures(content, "ts"), expected);
+    }
+
+    #[test]
+    fn extracts_python_signatures() {
+        let content = r#"import os
+
+
+@dataclass
+class Item:
+    name: str
+    price: int = 0
+
+    def total(self, count: int) -> int:
+        subtotal: int = self.price * count
+        return subtotal
+
+
+def load(path):
+    return Item(os.path.basename(path))
+"#;
+
+        let expected = "@dataclass\nclass Item:\n    name: str\n    price: int = 0\n    def total(self, count: int) -> int:\ndef load(path):";
+        assert_eq!(extract_signatures(content, "py"), expected);
+        assert_eq!(extract_signatures(content, "rb"), "");
+    }
+}
@@ -0,0 +1,731 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// How deep below the project root coverage reports are looked for
+const MAX_REPORT_DEPTH: usize = 4;
+
+/// Directories that never hold the project's own coverage reports
+const SKIPPED_DIRS: &[&str] = &["node_modules", ".git", ".venv", "venv", "vendor", "dist", "build"];
+
+/// File names coverage tools write their reports to by default
+const REPORT_FILE_NAMES: &[&str] = &[
+    "lcov.info",
+    "cobertura.xml",
+    "cobertura-coverage.xml",
+    "coverage.xml",
+    "coverage.json",
+];
+
+/// Most uncovered line ranges and branches listed in a prompt; the rest are summarized
+const MAX_LISTED_GAPS: usize = 40;
+
+/// Format of a coverage report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageFormat {
+    /// `lcov.info` tracefiles, written by Istanbul/nyc, Jest, Vitest, c8, cargo-llvm-cov, ...
+    Lcov,
+    /// Cobertura XML, written by coverage.py, cargo-tarpaulin, JaCoCo converters, ...
+    Cobertura,
+    /// coverage.py's `coverage json` output
+    CoveragePy,
+}
+
+/// Consecutive source lines, starting at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A line with branches the tests never take
+#[derive(Debug, Clone, Serialize)]
+pub struct UncoveredBranch {
+    pub line: usize,
+    /// Which branches are missing, e.g. `1 of 2 branches not taken` or `to line 14`
+    pub description: String,
+}
+
+/// Coverage of one source file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileCoverage {
+    /// Path of the file as reported, relative to the project root when it is inside the project
+    pub path: String,
+    /// Number of executable lines
+    pub lines_found: usize,
+    /// Number of executable lines run by the tests
+    pub lines_hit: usize,
+    pub uncovered_lines: Vec<LineRange>,
+    pub uncovered_branches: Vec<UncoveredBranch>,
+}
+
+impl FileCoverage {
+    /// Share of executable lines covered, in percent
+    pub fn line_rate(&self) -> f64 {
+        if self.lines_found == 0 {
+            return 100.0;
+        }
+        self.lines_hit as f64 * 100.0 / self.lines_found as f64
+    }
+
+    /// The uncovered lines and branches between `start` and `end`, e.g. those of one function
+    pub fn within(&self, start: usize, end: usize) -> FileCoverage {
+        FileCoverage {
+            uncovered_lines: self
+                .uncovered_lines
+                .iter()
+                .filter(|range| range.end >= start && range.start <= end)
+                .map(|range| LineRange {
+                    start: range.start.max(start),
+                    end: range.end.min(end),
+                })
+                .collect(),
+            uncovered_branches: self
+                .uncovered_branches
+                .iter()
+                .filter(|branch| (start..=end).contains(&branch.line))
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+/// A parsed coverage report
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageReport {
+    pub format: CoverageFormat,
+    /// Where the report was read from
+    pub report_path: String,
+    pub files: Vec<FileCoverage>,
+}
+
+impl CoverageReport {
+    /// The coverage of a source file, given relative to the project root
+    ///
+    /// Reports made in another checkout (e.g. in CI) or relative to a source folder are matched
+    /// by path suffix, as long as only one file matches.
+    pub fn for_source(&self, source_file: &str) -> Option<&FileCoverage> {
+        let source = to_slashes(source_file);
+        if let Some(file) = self.files.iter().find(|file| file.path == source) {
+            return Some(file);
+        }
+
+        let mut matches = self.files.iter().filter(|file| {
+            file.path.ends_with(&format!("/{}", source)) || source.ends_with(&format!("/{}", file.path))
+        });
+        let found = matches.next()?;
+        matches.next().is_none().then_some(found)
+    }
+
+    /// The coverage of each of the given source files that the report covers
+    pub fn map_onto<'a>(&self, source_files: impl IntoIterator<Item = &'a String>) -> HashMap<String, FileCoverage> {
+        source_files
+            .into_iter()
+            .filter_map(|source| Some((source.clone(), self.for_source(source)?.clone())))
+            .collect()
+    }
+}
+
+/// Lines and branches of one file as read from a report, before they are summarized
+#[derive(Default)]
+struct RawCoverage {
+    /// Hit count of each executable line
+    lines: BTreeMap<usize, u64>,
+    /// Descriptions of the branches not taken on each line
+    branches: BTreeMap<usize, Vec<String>>,
+}
+
+impl RawCoverage {
+    fn add_line(&mut self, line: usize, hits: u64) {
+        let entry = self.lines.entry(line).or_insert(0);
+        *entry = (*entry).max(hits);
+    }
+
+    fn add_branch(&mut self, line: usize, description: String) {
+        let descriptions = self.branches.entry(line).or_default();
+        if !descriptions.contains(&description) {
+            descriptions.push(description);
+        }
+    }
+
+    fn summarize(self, path: String) -> FileCoverage {
+        let mut uncovered_lines: Vec<LineRange> = Vec::new();
+        for (&line, _) in self.lines.iter().filter(|(_, hits)| **hits == 0) {
+            match uncovered_lines.last_mut() {
+                Some(range) if range.end + 1 == line => range.end = line,
+                _ => uncovered_lines.push(LineRange { start: line, end: line }),
+            }
+        }
+
+        FileCoverage {
+            path,
+            lines_found: self.lines.len(),
+            lines_hit: self.lines.values().filter(|hits| **hits > 0).count(),
+            uncovered_lines,
+            uncovered_branches: self
+                .branches
+                .into_iter()
+                .map(|(line, descriptions)| UncoveredBranch {
+                    line,
+                    description: descriptions.join(", "),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Finds coverage reports in a project, newest first
+pub fn find_coverage_reports(project_dir: &Path) -> Vec<PathBuf> {
+    let mut reports: Vec<(PathBuf, std::time::SystemTime)> = WalkDir::new(project_dir)
+        .max_depth(MAX_REPORT_DEPTH)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !SKIPPED_DIRS.contains(&&*entry.file_name().to_string_lossy()))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            REPORT_FILE_NAMES.contains(&&*name) || name.ends_with(".lcov")
+        })
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((entry.into_path(), modified))
+        })
+        .collect();
+
+    reports.sort_by(|a, b| b.1.cmp(&a.1));
+    reports.into_iter().map(|(path, _)| path).collect()
+}
+
+/// Loads the given report, or the newest one found in the project
+///
+/// Returns None if no report is given and the project has none.
+pub fn load_project_coverage(project_dir: &Path, report_path: Option<&str>) -> Result<Option<CoverageReport>, String> {
+    match report_path.filter(|path| !path.trim().is_empty()) {
+        Some(path) => load_coverage_report(project_dir, &project_dir.join(path)).map(Some),
+        None => {
+            // A `coverage.json` may be another tool's format, so fall through to older reports
+            for path in find_coverage_reports(project_dir) {
+                match load_coverage_report(project_dir, &path) {
+                    Ok(report) => return Ok(Some(report)),
+                    Err(e) => log::warn!("Skipping coverage report {}: {}", path.display(), e),
+                }
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Parses a coverage report, detecting its format from its content
+pub fn load_coverage_report(project_dir: &Path, path: &Path) -> Result<CoverageReport, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let trimmed = text.trim_start();
+
+    let (format, raw, sources) = if trimmed.starts_with('{') {
+        (CoverageFormat::CoveragePy, parse_coverage_py(&text)?, Vec::new())
+    } else if trimmed.starts_with('<') {
+        let (raw, sources) = parse_cobertura(&text)?;
+        (CoverageFormat::Cobertura, raw, sources)
+    } else {
+        (CoverageFormat::Lcov, parse_lcov(&text), Vec::new())
+    };
+    if raw.is_empty() {
+        return Err(format!("{} does not contain any file coverage", path.display()));
+    }
+
+    let report_dir = path.parent().unwrap_or(project_dir);
+    let files = raw
+        .into_iter()
+        .map(|(file, coverage)| coverage.summarize(project_relative(project_dir, report_dir, &sources, &file)))
+        .collect::<Vec<_>>();
+    log::info!("Loaded {:?} coverage of {} files from {}", format, files.len(), path.display());
+
+    Ok(CoverageReport {
+        format,
+        report_path: path.to_string_lossy().into_owned(),
+        files,
+    })
+}
+
+/// Describes what a file's tests leave uncovered, for the test generation prompt
+///
+/// Returns None if everything is covered.
+pub fn format_coverage_gaps(coverage: &FileCoverage) -> Option<String> {
+    if coverage.uncovered_lines.is_empty() && coverage.uncovered_branches.is_empty() {
+        return None;
+    }
+
+    let mut text = format!(
+        "{} of {} executable lines are covered ({:.0}%).",
+        coverage.lines_hit,
+        coverage.lines_found,
+        coverage.line_rate()
+    );
+
+    if !coverage.uncovered_lines.is_empty() {
+        let ranges: Vec<String> = coverage
+            .uncovered_lines
+            .iter()
+            .take(MAX_LISTED_GAPS)
+            .map(|range| {
+                if range.start == range.end {
+                    range.start.to_string()
+                } else {
+                    format!("{}-{}", range.start, range.end)
+                }
+            })
+            .collect();
+        text.push_str(&format!("\nUncovered lines: {}", ranges.join(", ")));
+        if coverage.uncovered_lines.len() > MAX_LISTED_GAPS {
+            text.push_str(&format!(" and {} more ranges", coverage.uncovered_lines.len() - MAX_LISTED_GAPS));
+        }
+    }
+
+    if !coverage.uncovered_branches.is_empty() {
+        text.push_str("\nBranches never taken:");
+        for branch in coverage.uncovered_branches.iter().take(MAX_LISTED_GAPS) {
+            text.push_str(&format!("\n- line {}: {}", branch.line, branch.description));
+        }
+        if coverage.uncovered_branches.len() > MAX_LISTED_GAPS {
+            text.push_str(&format!(
+                "\n- and {} more lines with branches",
+                coverage.uncovered_branches.len() - MAX_LISTED_GAPS
+            ));
+        }
+    }
+
+    Some(text)
+}
+
+/// Parses an lcov tracefile (`SF`, `DA` and `BRDA` records)
+fn parse_lcov(text: &str) -> HashMap<String, RawCoverage> {
+    let mut files: HashMap<String, RawCoverage> = HashMap::new();
+    let mut current: Option<String> = None;
+    // Taken and total branches per line of the current file
+    let mut branches: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+
+    for line in text.lines().map(str::trim) {
+        if let Some(path) = line.strip_prefix("SF:") {
+            finish_lcov_record(&mut files, current.take(), &mut branches);
+            current = Some(path.to_string());
+            files.entry(path.to_string()).or_default();
+        } else if line == "end_of_record" {
+            finish_lcov_record(&mut files, current.take(), &mut branches);
+        } else if let Some(record) = line.strip_prefix("DA:") {
+            let mut fields = record.split(',');
+            let number = fields.next().and_then(|n| n.parse().ok());
+            let hits = fields.next().and_then(|h| h.parse::<f64>().ok());
+            if let (Some(file), Some(number), Some(hits)) = (&current, number, hits) {
+                if let Some(coverage) = files.get_mut(file) {
+                    coverage.add_line(number, hits.max(0.0) as u64);
+                }
+            }
+        } else if let Some(record) = line.strip_prefix("BRDA:") {
+            // BRDA:<line>,<block>,<branch>,<taken>; `-` means the block never ran
+            let fields: Vec<&str> = record.split(',').collect();
+            if let (Some(number), Some(taken)) = (fields.first().and_then(|n| n.parse().ok()), fields.get(3)) {
+                let entry = branches.entry(number).or_insert((0, 0));
+                entry.1 += 1;
+                if taken.parse::<u64>().is_ok_and(|count| count > 0) {
+                    entry.0 += 1;
+                }
+            }
+        }
+    }
+    finish_lcov_record(&mut files, current, &mut branches);
+
+    files
+}
+
+/// Adds the branches counted for an lcov record to its file
+fn finish_lcov_record(
+    files: &mut HashMap<String, RawCoverage>,
+    file: Option<String>,
+    branches: &mut BTreeMap<usize, (usize, usize)>,
+) {
+    let Some(file) = file else {
+        branches.clear();
+        return;
+    };
+    let coverage = files.entry(file).or_default();
+    for (line, (taken, total)) in std::mem::take(branches) {
+        if taken < total {
+            coverage.add_branch(line, format!("{} of {} branches not taken", total - taken, total));
+        }
+    }
+}
+
+/// Parses a Cobertura XML report, returning the file coverage and the report's source roots
+fn parse_cobertura(xml: &str) -> Result<(HashMap<String, RawCoverage>, Vec<String>), String> {
+    let mut reader = Reader::from_str(xml);
+    let mut files: HashMap<String, RawCoverage> = HashMap::new();
+    let mut sources = Vec::new();
+    let mut in_source = false;
+    let mut current: Option<String> = None;
+
+    loop {
+        let (element, is_empty) = match reader.read_event() {
+            Ok(Event::Start(element)) => (element, false),
+            Ok(Event::Empty(element)) => (element, true),
+            Ok(Event::Text(text)) if in_source => {
+                let source = text.unescape().map_err(|e| format!("Invalid Cobertura report: {}", e))?;
+                sources.push(source.trim().to_string());
+                continue;
+            }
+            Ok(Event::End(element)) => {
+                match element.name().as_ref() {
+                    b"source" => in_source = false,
+                    b"class" => current = None,
+                    _ => {}
+                }
+                continue;
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                return Err(format!(
+                    "Invalid Cobertura report at position {}: {}",
+                    reader.buffer_position(),
+                    e
+                ))
+            }
+            Ok(_) => continue,
+        };
+
+        match element.name().as_ref() {
+            b"source" => in_source = !is_empty,
+            b"class" if !is_empty => {
+                current = attribute(&element, "filename");
+                if let Some(file) = &current {
+                    files.entry(file.clone()).or_default();
+                }
+            }
+            b"line" => {
+                let Some(coverage) = current.as_ref().and_then(|file| files.get_mut(file)) else {
+                    continue;
+                };
+                let Some(number) = attribute(&element, "number").and_then(|n| n.parse().ok()) else {
+                    continue;
+                };
+                let hits = attribute(&element, "hits")
+                    .and_then(|h| h.parse::<f64>().ok())
+                    .unwrap_or(0.0);
+                coverage.add_line(number, hits.max(0.0) as u64);
+
+                // condition-coverage="50% (1/2)"
+                if attribute(&element, "branch").as_deref() == Some("true") {
+                    let counts = attribute(&element, "condition-coverage").and_then(|condition| {
+                        let (_, counts) = condition.split_once('(')?;
+                        let (taken, total) = counts.trim_end_matches(')').split_once('/')?;
+                        Some((taken.trim().parse::<usize>().ok()?, total.trim().parse::<usize>().ok()?))
+                    });
+                    if let Some((taken, total)) = counts.filter(|(taken, total)| taken < total) {
+                        coverage.add_branch(number, format!("{} of {} branches not taken", total - taken, total));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((files, sources))
+}
+
+/// Parses the output of coverage.py's `coverage json`
+fn parse_coverage_py(json: &str) -> Result<HashMap<String, RawCoverage>, String> {
+    let report: Value = serde_json::from_str(json).map_err(|e| format!("Invalid coverage JSON: {}", e))?;
+    let files = report
+        .get("files")
+        .and_then(Value::as_object)
+        .ok_or_else(|| "Not a coverage.py JSON report (no `files` object)".to_string())?;
+
+    let line_numbers = |entry: &Value, key: &str| -> Vec<usize> {
+        entry
+            .get(key)
+            .and_then(Value::as_array)
+            .map(|lines| lines.iter().filter_map(|n| n.as_u64()).map(|n| n as usize).collect())
+            .unwrap_or_default()
+    };
+
+    let mut coverage = HashMap::new();
+    for (path, entry) in files {
+        let mut raw = RawCoverage::default();
+        for line in line_numbers(entry, "executed_lines") {
+            raw.add_line(line, 1);
+        }
+        for line in line_numbers(entry, "missing_lines") {
+            raw.add_line(line, 0);
+        }
+
+        // Missing branches are [from, to] arcs; a negative `to` leaves the function
+        let arcs = entry.get("missing_branches").and_then(Value::as_array);
+        for arc in arcs.into_iter().flatten().filter_map(Value::as_array) {
+            let (Some(from), Some(to)) = (arc.first().and_then(Value::as_i64), arc.get(1).and_then(Value::as_i64)) else {
+                continue;
+            };
+            if from <= 0 {
+                continue;
+            }
+            let description = if to < 0 {
+                "branch to the function's exit not taken".to_string()
+            } else {
+                format!("branch to line {} not taken", to)
+            };
+            raw.add_branch(from as usize, description);
+        }
+
+        coverage.insert(path.clone(), raw);
+    }
+    Ok(coverage)
+}
+
+/// Value of an XML attribute
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attribute| attribute.key.as_ref() == name.as_bytes())
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// A reported path relative to the project root, with `/` separators
+///
+/// Relative paths are resolved against the report's source roots, then the project root, then
+/// the report's directory, whichever holds the file. Paths outside the project are kept as they
+/// are so they can still be matched by suffix.
+fn project_relative(project_dir: &Path, report_dir: &Path, sources: &[String], path: &str) -> String {
+    let reported = Path::new(path);
+    let resolved = if reported.is_absolute() {
+        Some(reported.to_path_buf())
+    } else {
+        sources
+            .iter()
+            .map(|source| project_dir.join(source).join(reported))
+            .chain([project_dir.join(reported), report_dir.join(reported)])
+            .find(|candidate| candidate.exists())
+    };
+
+    match resolved.as_deref().and_then(|full| full.strip_prefix(project_dir).ok()) {
+        Some(relative) => to_slashes(&relative.to_string_lossy()),
+        None => to_slashes(path).trim_start_matches("./").to_string(),
+    }
+}
+
+fn to_slashes(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LCOV: &str = "TN:
+SF:src/math.ts
+FN:1,add
+DA:1,3
+DA:2,3
+DA:3,0
+DA:4,0
+DA:6,1
+DA:7,0
+BRDA:2,0,0,3
+BRDA:2,0,1,0
+BRDA:6,1,0,-
+BRDA:6,1,1,-
+LF:6
+LH:3
+end_of_record
+SF:src/done.ts
+DA:1,1
+end_of_record
+";
+
+    const COBERTURA: &str = r#"<?xml version="1.0" ?>
+<coverage line-rate="0.5" branch-rate="0.5" version="7.4.0">
+    <sources>
+        <source>/home/ci/build/project/src</source>
+        <source>lib</source>
+    </sources>
+    <packages>
+        <package name="shop">
+            <classes>
+                <class name="cart.py" filename="shop/cart.py" line-rate="0.5">
+                    <methods/>
+                    <lines>
+                        <line number="1" hits="1"/>
+                        <line number="2" hits="1" branch="true" condition-coverage="50% (1/2)"/>
+                        <line number="3" hits="0"/>
+                        <line number="5" hits="0"/>
+                        <line number="6" hits="4" branch="true" condition-coverage="100% (2/2)"/>
+                    </lines>
+                </class>
+            </classes>
+        </package>
+    </packages>
+</coverage>
+"#;
+
+    const COVERAGE_PY: &str = r#"{
+        "meta": {"version": "7.4.0"},
+        "files": {
+            "app/views.py": {
+                "executed_lines": [1, 2, 4],
+                "missing_lines": [5, 6, 9],
+                "missing_branches": [[4, 5], [9, -1]]
+            }
+        }
+    }"#;
+
+    fn lines(ranges: &[(usize, usize)]) -> Vec<LineRange> {
+        ranges.iter().map(|&(start, end)| LineRange { start, end }).collect()
+    }
+
+    fn branches(coverage: &FileCoverage) -> Vec<(usize, &str)> {
+        coverage
+            .uncovered_branches
+            .iter()
+            .map(|branch| (branch.line, branch.description.as_str()))
+            .collect()
+    }
+
+    fn file(path: &str) -> FileCoverage {
+        FileCoverage {
+            path: path.to_string(),
+            lines_found: 0,
+            lines_hit: 0,
+            uncovered_lines: Vec::new(),
+            uncovered_branches: Vec::new(),
+        }
+    }
+
+    fn report(paths: &[&str]) -> CoverageReport {
+        CoverageReport {
+            format: CoverageFormat::Lcov,
+            report_path: "coverage/lcov.info".to_string(),
+            files: paths.iter().map(|path| file(path)).collect(),
+        }
+    }
+
+    /// A fresh project directory with the given files
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("coverage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn parses_lcov_lines_and_branches() {
+        let files = parse_lcov(LCOV);
+        assert_eq!(files.len(), 2);
+
+        let math = files.into_iter().find(|(path, _)| path == "src/math.ts").unwrap().1.summarize("src/math.ts".into());
+        assert_eq!((math.lines_found, math.lines_hit), (6, 3));
+        assert_eq!(math.uncovered_lines, lines(&[(3, 4), (7, 7)]));
+        // A block that never ran (`-`) has none of its branches taken
+        assert_eq!(branches(&math), vec![(2, "1 of 2 branches not taken"), (6, "2 of 2 branches not taken")]);
+    }
+
+    #[test]
+    fn parses_cobertura_lines_branches_and_sources() {
+        let (files, sources) = parse_cobertura(COBERTURA).unwrap();
+        assert_eq!(sources, vec!["/home/ci/build/project/src".to_string(), "lib".to_string()]);
+
+        let cart = files.into_iter().next().map(|(path, raw)| raw.summarize(path)).unwrap();
+        assert_eq!(cart.path, "shop/cart.py");
+        assert_eq!((cart.lines_found, cart.lines_hit), (5, 3));
+        assert_eq!(cart.uncovered_lines, lines(&[(3, 3), (5, 5)]));
+        assert_eq!(branches(&cart), vec![(2, "1 of 2 branches not taken")]);
+    }
+
+    #[test]
+    fn rejects_malformed_cobertura() {
+        assert!(parse_cobertura("<coverage><packages></coverage>").is_err());
+    }
+
+    #[test]
+    fn parses_coverage_py_json() {
+        let views = parse_coverage_py(COVERAGE_PY).unwrap().remove("app/views.py").unwrap().summarize("app/views.py".into());
+        assert_eq!((views.lines_found, views.lines_hit), (6, 3));
+        assert_eq!(views.uncovered_lines, lines(&[(5, 6), (9, 9)]));
+        assert_eq!(
+            branches(&views),
+            vec![(4, "branch to line 5 not taken"), (9, "branch to the function's exit not taken")]
+        );
+
+        assert!(parse_coverage_py(r#"{"coverageMap": {}}"#).is_err());
+    }
+
+    #[test]
+    fn loads_reports_relative_to_the_project() {
+        let root = project(
+            "load",
+            &[
+                ("lib/shop/cart.py", ""),
+                ("coverage.xml", COBERTURA),
+                ("coverage/lcov.info", LCOV),
+                ("src/math.ts", ""),
+            ],
+        );
+
+        // The relative source root is resolved against the project
+        let cobertura = load_coverage_report(&root, &root.join("coverage.xml")).unwrap();
+        assert_eq!(cobertura.format, CoverageFormat::Cobertura);
+        assert_eq!(cobertura.files[0].path, "lib/shop/cart.py");
+
+        let lcov = load_coverage_report(&root, &root.join("coverage/lcov.info")).unwrap();
+        assert_eq!(lcov.format, CoverageFormat::Lcov);
+        assert!(lcov.for_source("src/math.ts").is_some());
+
+        assert_eq!(find_coverage_reports(&root).len(), 2);
+        assert!(load_coverage_report(&root, &root.join("src/math.ts")).is_err());
+    }
+
+    #[test]
+    fn matches_source_files_by_path_suffix() {
+        let report = report(&["/home/ci/build/project/src/app.ts", "src/util.ts", "lib/util.ts", "views.py"]);
+
+        assert_eq!(report.for_source("src/util.ts").unwrap().path, "src/util.ts");
+        // Reported in another checkout
+        assert_eq!(report.for_source("src/app.ts").unwrap().path, "/home/ci/build/project/src/app.ts");
+        // Reported relative to a source folder
+        assert_eq!(report.for_source("app/views.py").unwrap().path, "views.py");
+        assert_eq!(report.for_source(r"src\util.ts").unwrap().path, "src/util.ts");
+        // `util.ts` alone would match both
+        assert!(report.for_source("util.ts").is_none());
+        // Only whole path components match
+        assert!(report.for_source("pp.ts").is_none());
+        assert!(report.for_source("src/missing.ts").is_none());
+    }
+
+    #[test]
+    fn narrows_and_formats_coverage_gaps() {
+        let coverage = FileCoverage {
+            path: "src/math.ts".to_string(),
+            lines_found: 8,
+            lines_hit: 5,
+            uncovered_lines: lines(&[(3, 4), (10, 10)]),
+            uncovered_branches: vec![UncoveredBranch { line: 2, description: "1 of 2 branches not taken".to_string() }],
+        };
+        assert_eq!(
+            format_coverage_gaps(&coverage).unwrap(),
+            "5 of 8 executable lines are covered (62%).\nUncovered lines: 3-4, 10\nBranches never taken:\n- line 2: 1 of 2 branches not taken"
+        );
+
+        let function = coverage.within(4, 9);
+        assert_eq!(function.uncovered_lines, lines(&[(4, 4)]));
+        assert!(function.uncovered_branches.is_empty());
+        assert!(format_coverage_gaps(&coverage.within(5, 9)).is_none());
+        assert_eq!(file("empty.ts").line_rate(), 100.0);
+    }
+}
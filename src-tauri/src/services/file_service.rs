@@ -1,10 +1,22 @@
 use log::info;
-use std::path::{Path, PathBuf};
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
 use std::collections::HashMap;
 use walkdir::WalkDir;
 use std::fs;
 use std::io::Write;
 
+/// Dependency, build and VCS directories skipped when listing or searching a project
+const SKIPPED_DIR_NAMES: &[&str] = &[
+    ".git", "node_modules", "target", "dist", "build", ".next", "vendor", "__pycache__", ".venv", "venv",
+];
+
+/// Files larger than this are not searched
+const MAX_SEARCHED_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Matched lines longer than this are cut off in search results
+const MAX_MATCH_LINE_CHARS: usize = 200;
+
 /// File service for handling file system operations
 pub struct FileService;
 
@@ -51,6 +63,13 @@ impl FileService {
         Ok(format!("Type: {}, Size: {} bytes", file_type, size))
     }
     
+    /// Reads a text file into a string
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<String, String> {
+        let path_ref = path.as_ref();
+        fs::read_to_string(path_ref)
+            .map_err(|e| format!("Failed to read file {}: {}", path_ref.display(), e))
+    }
+    
     /// Finds source files and their corresponding test files in a directory
     /// Returns a mapping where:
     /// - Key: Source file path (relative to the directory)
@@ -297,36 +316,272 @@ impl FileService {
     /// If the test file doesn't exist, it will be created
     /// If the test file exists, it will be overwritten
     pub fn write_test_file<P: AsRef<Path>>(&self, base_dir: P, source_file: &str, test_content: &str) -> Result<String, String> {
+        // Determine the test file path based on the source file
+        let test_file_path = self.derive_test_file_path(source_file)?;
+        
+        self.write_file(base_dir, &test_file_path, test_content)?;
+        
+        Ok(test_file_path)
+    }
+
+    /// Writes test content to the path the agent suggested for it
+    /// Falls back to the conventional location when the suggestion isn't a test file inside the project,
+    /// or is an existing file other than the source file's own test file, which would be lost
+    pub fn write_test_file_at<P: AsRef<Path>>(
+        &self,
+        base_dir: P,
+        source_file: &str,
+        suggested_path: Option<&str>,
+        test_content: &str,
+    ) -> Result<String, String> {
+        let base_dir = base_dir.as_ref();
+        let test_file_path = match suggested_path {
+            Some(suggested) if self.is_valid_test_path(base_dir, source_file, suggested) => suggested.to_string(),
+            Some(suggested) => {
+                info!("Ignoring suggested test path {} for {}", suggested, source_file);
+                self.derive_test_file_path(source_file)?
+            }
+            None => self.derive_test_file_path(source_file)?,
+        };
+
+        self.write_file(base_dir, &test_file_path, test_content)?;
+
+        Ok(test_file_path)
+    }
+
+    /// Writes content to a file relative to the base directory, creating parent directories as needed
+    pub fn write_file<P: AsRef<Path>>(&self, base_dir: P, relative_path: &str, content: &str) -> Result<(), String> {
         let base_dir = base_dir.as_ref();
         if !self.path_exists(base_dir) {
             return Err(format!("Base directory does not exist: {}", base_dir.display()));
         }
         
-        // Determine the test file path based on the source file
-        let test_file_path = self.derive_test_file_path(source_file)?;
-        
-        // Create the full path by joining the base directory and test file path
-        let full_test_path = base_dir.join(&test_file_path);
+        // Create the full path by joining the base directory and the relative path
+        let full_path = base_dir.join(relative_path);
         
         // Ensure the directory exists
-        if let Some(parent) = full_test_path.parent() {
+        if let Some(parent) = full_path.parent() {
             if !parent.exists() {
                 fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
             }
         }
         
-        // Write the test content to the file
-        let mut file = fs::File::create(&full_test_path)
-            .map_err(|e| format!("Failed to create test file: {}", e))?;
+        // Write the content to the file
+        let mut file = fs::File::create(&full_path)
+            .map_err(|e| format!("Failed to create file {}: {}", full_path.display(), e))?;
         
-        file.write_all(test_content.as_bytes())
-            .map_err(|e| format!("Failed to write test content: {}", e))?;
+        file.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write file {}: {}", full_path.display(), e))?;
         
-        info!("Successfully wrote test file: {}", full_test_path.display());
+        info!("Successfully wrote file: {}", full_path.display());
         
-        Ok(test_file_path)
+        Ok(())
+    }
+
+    /// Finds the test file that already covers a source file, if there is one
+    /// Only the conventional test locations for the source file are checked, starting with the
+    /// path `write_test_file` would use, so the lookup doesn't walk the project
+    pub fn find_existing_test_file<P: AsRef<Path>>(&self, base_dir: P, source_file: &str) -> Option<String> {
+        let base_dir = base_dir.as_ref();
+
+        self.candidate_test_paths(source_file)
+            .into_iter()
+            .find(|candidate| base_dir.join(candidate).is_file())
+    }
+
+    /// The paths a test file for a source file is conventionally found at, most likely first
+    fn candidate_test_paths(&self, source_file: &str) -> Vec<String> {
+        let path = Path::new(source_file);
+        let (Some(stem), Some(extension)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            path.extension().and_then(|e| e.to_str()),
+        ) else {
+            return Vec::new();
+        };
+        let parent: Vec<&str> = path
+            .parent()
+            .and_then(|p| p.to_str())
+            .unwrap_or("")
+            .split('/')
+            .filter(|c| !c.is_empty())
+            .collect();
+        if extension == "rs" {
+            return self.rust_test_paths(source_file, stem, &parent);
+        }
+
+        let names = match extension {
+            "ts" | "js" | "tsx" | "jsx" => vec![format!("{}.test.{}", stem, extension), format!("{}.spec.{}", stem, extension)],
+            "py" => vec![format!("test_{}.py", stem), format!("{}_test.py", stem)],
+            "go" => vec![format!("{}_test.go", stem)],
+            "java" => vec![format!("{}Test.java", stem), format!("{}Tests.java", stem)],
+            "cs" => vec![format!("{}Tests.cs", stem), format!("{}Test.cs", stem)],
+            "rb" => vec![format!("{}_spec.rb", stem), format!("{}_test.rb", stem)],
+            _ => vec![format!("{}.test.{}", stem, extension), format!("test_{}.{}", stem, extension)],
+        };
+
+        // Next to the source, in a test folder beside it, or in a test tree mirroring the source
+        // tree: `src/a/b.py` in `tests/a/`, `src/main/java/x/` in `src/test/java/x/`
+        let test_dirs = ["__tests__", "tests", "test", "spec", "specs"];
+        let mut dirs: Vec<Vec<&str>> = vec![parent.clone()];
+        dirs.extend(test_dirs.iter().map(|dir| [parent.as_slice(), &[*dir]].concat()));
+        if let Some(src) = parent.iter().rposition(|c| *c == "src" || *c == "lib") {
+            let (root, rest) = (&parent[..src], &parent[src + 1..]);
+            if rest.first() == Some(&"main") {
+                dirs.push([root, &[parent[src], "test"], &rest[1..]].concat());
+            }
+            for dir in test_dirs {
+                dirs.push([root, &[dir], rest].concat());
+                dirs.push([root, &[dir]].concat());
+            }
+        }
+
+        let mut candidates: Vec<String> = self.derive_test_file_path(source_file).into_iter().collect();
+        for dir in dirs {
+            for name in &names {
+                let candidate = [dir.as_slice(), &[name.as_str()]].concat().join("/");
+                if candidate != source_file && !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        candidates
+    }
+
+    /// The paths an integration test for a Rust module is conventionally found at
+    ///
+    /// Only the crate's `tests/` directory is checked, since any file in `src/` is a module of
+    /// the crate. Tests are named after the module, so `src/a/b.rs` and `src/a/b/mod.rs` are
+    /// looked for as `tests/b.rs`, `tests/test_b.rs` and `tests/a/b.rs`; `mod.rs` itself is
+    /// never a name, as `tests/mod.rs` is usually a helper module shared by the tests.
+    fn rust_test_paths(&self, source_file: &str, stem: &str, parent: &[&str]) -> Vec<String> {
+        let (crate_dir, module_dirs) = match parent.iter().rposition(|c| *c == "src") {
+            Some(src) => (&parent[..src], &parent[src + 1..]),
+            None => (parent, &parent[parent.len()..]),
+        };
+        let mut module_path = module_dirs.to_vec();
+        if !matches!(stem, "mod" | "lib" | "main") {
+            module_path.push(stem);
+        }
+
+        let mut candidates: Vec<String> = self.derive_test_file_path(source_file).into_iter().collect();
+        // The crate root is only tested by the file new tests are written to
+        let Some((module, module_dirs)) = module_path.split_last() else {
+            return candidates;
+        };
+        let tests_dir = [crate_dir, &["tests"]].concat();
+        for dir in [tests_dir.clone(), [tests_dir.as_slice(), module_dirs].concat()] {
+            for name in [format!("{}.rs", module), format!("test_{}.rs", module)] {
+                let candidate = [dir.as_slice(), &[name.as_str()]].concat().join("/");
+                if !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Resolves a path relative to a project root, refusing any path that leads outside it
+    /// Symlinks are resolved before the check, so a link inside the project can't escape it either
+    pub fn resolve_in_project<P: AsRef<Path>>(&self, root: P, relative_path: &str) -> Result<PathBuf, String> {
+        let root = root.as_ref();
+        let relative = Path::new(relative_path.trim());
+        if !relative.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+            return Err(format!("{} is not a path inside the project", relative_path));
+        }
+
+        let root = root
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve project root {}: {}", root.display(), e))?;
+        let path = root
+            .join(relative)
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", relative_path, e))?;
+        if !path.starts_with(&root) {
+            return Err(format!("{} is not a path inside the project", relative_path));
+        }
+        Ok(path)
+    }
+
+    /// Lists the entries of a directory by name, sorted, with a trailing slash on subdirectories
+    /// Dependency, build and VCS directories are left out
+    pub fn list_dir<P: AsRef<Path>>(&self, dir: P) -> Result<Vec<String>, String> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|e| format!("Failed to list {}: {}", dir.display(), e))?;
+
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let is_dir = entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false);
+                match is_dir {
+                    true if SKIPPED_DIR_NAMES.contains(&name.as_str()) => None,
+                    true => Some(format!("{}/", name)),
+                    false => Some(name),
+                }
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Searches the text files under `dir` for lines matching a regular expression
+    /// Returns the path relative to `root`, 1-based line number and line of up to `max_matches`
+    /// matches. Dependency, build and VCS directories, large files and binary files are skipped.
+    pub fn grep<P: AsRef<Path>>(&self, root: P, dir: &Path, pattern: &Regex, max_matches: usize) -> Vec<(String, usize, String)> {
+        let root = root.as_ref();
+        let mut matches = Vec::new();
+
+        let files = WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                !(entry.file_type().is_dir()
+                    && entry.depth() > 0
+                    && SKIPPED_DIR_NAMES.contains(&entry.file_name().to_string_lossy().as_ref()))
+            })
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| entry.metadata().map(|meta| meta.len() <= MAX_SEARCHED_FILE_BYTES).unwrap_or(false));
+
+        for entry in files {
+            // Files that aren't UTF-8 are binary as far as the search is concerned
+            let Ok(content) = fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            let path = self.get_relative_path(&entry.path().to_path_buf(), &root.to_path_buf());
+            for (index, line) in content.lines().enumerate() {
+                if !pattern.is_match(line) {
+                    continue;
+                }
+                let line = match line.char_indices().nth(MAX_MATCH_LINE_CHARS) {
+                    Some((end, _)) => format!("{}...", &line[..end]),
+                    None => line.to_string(),
+                };
+                matches.push((path.clone(), index + 1, line.trim().to_string()));
+                if matches.len() >= max_matches {
+                    return matches;
+                }
+            }
+        }
+        matches
     }
     
+    /// Whether a suggested test path stays inside the project, is named like a test file,
+    /// has the source file's extension and doesn't replace another file's tests
+    fn is_valid_test_path(&self, base_dir: &Path, source_file: &str, suggested: &str) -> bool {
+        let path = Path::new(suggested);
+        let inside_project = path.components().all(|component| matches!(component, Component::Normal(_)));
+        let same_extension = path.extension() == Path::new(source_file).extension();
+
+        // Prefixed with a slash so that a top-level tests directory is recognized too
+        if !(inside_project && same_extension && suggested != source_file && self.is_test_file(&format!("/{}", suggested))) {
+            return false;
+        }
+        // An existing file is only written over if it is the source file's own test file
+        !base_dir.join(path).exists()
+            || self.find_existing_test_file(base_dir, source_file).as_deref() == Some(suggested)
+    }
+
     /// Derives the test file path based on the source file path
     fn derive_test_file_path(&self, source_file: &str) -> Result<String, String> {
         let path = Path::new(source_file);
@@ -356,21 +611,32 @@ impl FileService {
             "java" => format!("{}Test.java", file_stem),
             "cs" => format!("{}Tests.cs", file_stem),
             "rb" => format!("{}_spec.rb", file_stem),
-            "rs" => format!("{}_test.rs", file_stem), // Rust tests typically use _test suffix in tests directory
+            "rs" => format!("{}.rs", file_stem), // Replaced below by a crate-level integration test
             _ => format!("{}.test.{}", file_stem, extension) // Default to .test.ext pattern
         };
         
         // Language-specific test directory handling
         match extension {
             "rs" => {
-                // Rust tests are typically in a tests directory at the module level
-                let rust_test_dir = if parent.is_empty() {
-                    "tests".to_string()
-                } else {
-                    format!("{}/tests", parent)
+                // Only files in a crate's `tests/` directory are compiled without a `mod` declaration,
+                // so Rust tests are integration tests named after the module path: `src/a/b.rs` is
+                // tested by `tests/a_b.rs` and `crates/x/src/a/mod.rs` by `crates/x/tests/a.rs`
+                let components: Vec<&str> = parent.split('/').filter(|c| !c.is_empty()).collect();
+                let (crate_dir, module_dirs) = match components.iter().rposition(|c| *c == "src") {
+                    Some(src) => (components[..src].join("/"), &components[src + 1..]),
+                    None => (components.join("/"), &components[components.len()..]),
                 };
-                
-                Ok(format!("{}/{}", rust_test_dir, test_file_name))
+                let mut module_path: Vec<&str> = module_dirs.to_vec();
+                if !matches!(file_stem, "mod" | "lib" | "main") || module_path.is_empty() {
+                    module_path.push(file_stem);
+                }
+                let test_name = format!("{}.rs", module_path.join("_"));
+
+                Ok(if crate_dir.is_empty() {
+                    format!("tests/{}", test_name)
+                } else {
+                    format!("{}/tests/{}", crate_dir, test_name)
+                })
             },
             "py" => {
                 // Python tests might be in tests folder or in the same directory
@@ -414,3 +680,164 @@ impl FileService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh project directory with `src/lib.rs`, and a directory next to it that is outside the project
+    fn temp_project(name: &str) -> (PathBuf, PathBuf) {
+        let base = std::env::temp_dir().join(format!("file-service-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let root = base.join("project");
+        let outside = base.join("outside");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn answer() -> u32 { 42 }\n").unwrap();
+        fs::write(outside.join("secret.txt"), "secret\n").unwrap();
+        (root, outside)
+    }
+
+    #[test]
+    fn resolves_paths_inside_the_project() {
+        let (root, _) = temp_project("inside");
+        let service = FileService::new();
+        let expected = root.join("src/lib.rs").canonicalize().unwrap();
+
+        assert_eq!(service.resolve_in_project(&root, "src/lib.rs").unwrap(), expected);
+        assert_eq!(service.resolve_in_project(&root, "./src/lib.rs").unwrap(), expected);
+        assert_eq!(service.resolve_in_project(&root, " src/lib.rs ").unwrap(), expected);
+        assert_eq!(service.resolve_in_project(&root, ".").unwrap(), root.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn refuses_parent_directory_components() {
+        let (root, _) = temp_project("parent");
+        let service = FileService::new();
+
+        assert!(service.resolve_in_project(&root, "../outside/secret.txt").is_err());
+        assert!(service.resolve_in_project(&root, "src/../../outside/secret.txt").is_err());
+        // Even when the path would end up inside the project again
+        assert!(service.resolve_in_project(&root, "src/../src/lib.rs").is_err());
+        assert!(service.resolve_in_project(&root, "..").is_err());
+    }
+
+    #[test]
+    fn refuses_absolute_paths() {
+        let (root, outside) = temp_project("absolute");
+        let service = FileService::new();
+
+        let absolute = outside.join("secret.txt");
+        assert!(service.resolve_in_project(&root, absolute.to_str().unwrap()).is_err());
+        // An absolute path into the project is still refused; tools only take relative paths
+        let inside = root.join("src/lib.rs");
+        assert!(service.resolve_in_project(&root, inside.to_str().unwrap()).is_err());
+        assert!(service.resolve_in_project(&root, "/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn refuses_windows_prefixes() {
+        let (root, _) = temp_project("prefix");
+        let service = FileService::new();
+
+        for path in [r"C:\Windows\win.ini", r"C:secret.txt", r"\\server\share\secret.txt", r"\\?\C:\secret.txt"] {
+            assert!(service.resolve_in_project(&root, path).is_err(), "{} was accepted", path);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_symlinks_that_lead_outside() {
+        let (root, outside) = temp_project("symlink");
+        let service = FileService::new();
+        std::os::unix::fs::symlink(&outside, root.join("escape")).unwrap();
+        std::os::unix::fs::symlink(outside.join("secret.txt"), root.join("src/secret.txt")).unwrap();
+        std::os::unix::fs::symlink(root.join("src/lib.rs"), root.join("lib.rs")).unwrap();
+
+        assert!(service.resolve_in_project(&root, "escape/secret.txt").is_err());
+        assert!(service.resolve_in_project(&root, "escape").is_err());
+        assert!(service.resolve_in_project(&root, "src/secret.txt").is_err());
+        // Links that stay inside the project are fine
+        assert_eq!(
+            service.resolve_in_project(&root, "lib.rs").unwrap(),
+            root.join("src/lib.rs").canonicalize().unwrap()
+        );
+    }
+
+    #[test]
+    fn refuses_missing_files() {
+        let (root, _) = temp_project("missing");
+        assert!(FileService::new().resolve_in_project(&root, "src/missing.rs").is_err());
+    }
+
+    #[test]
+    fn finds_existing_tests_at_conventional_locations() {
+        let (root, _) = temp_project("existing");
+        let service = FileService::new();
+        for test_file in [
+            "src/components/Button.spec.tsx",
+            "tests/utils/test_money.py",
+            "src/test/java/shop/CartTest.java",
+            "crates/core/tests/parser.rs",
+            "server/handler_test.go",
+        ] {
+            let path = root.join(test_file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let found = |source_file: &str| service.find_existing_test_file(&root, source_file);
+        assert_eq!(found("src/components/Button.tsx").as_deref(), Some("src/components/Button.spec.tsx"));
+        assert_eq!(found("src/utils/money.py").as_deref(), Some("tests/utils/test_money.py"));
+        assert_eq!(found("src/main/java/shop/Cart.java").as_deref(), Some("src/test/java/shop/CartTest.java"));
+        assert_eq!(found("crates/core/src/parser.rs").as_deref(), Some("crates/core/tests/parser.rs"));
+        assert_eq!(found("server/handler.go").as_deref(), Some("server/handler_test.go"));
+
+        // A source file is never its own test, and other files' tests don't count
+        assert_eq!(found("src/lib.rs"), None);
+        assert_eq!(found("src/components/Link.tsx"), None);
+    }
+
+    #[test]
+    fn looks_for_rust_tests_by_module_in_the_tests_directory() {
+        let service = FileService::new();
+        assert_eq!(
+            service.candidate_test_paths("src/net/mod.rs"),
+            ["tests/net.rs", "tests/test_net.rs"]
+        );
+        assert_eq!(
+            service.candidate_test_paths("crates/core/src/net/http.rs"),
+            [
+                "crates/core/tests/net_http.rs",
+                "crates/core/tests/http.rs",
+                "crates/core/tests/test_http.rs",
+                "crates/core/tests/net/http.rs",
+                "crates/core/tests/net/test_http.rs",
+            ]
+        );
+        assert_eq!(service.candidate_test_paths("src/lib.rs"), ["tests/lib.rs"]);
+
+        // A shared helper module or a sibling source module is not a test of the file
+        let (root, _) = temp_project("rust-candidates");
+        for file in ["tests/mod.rs", "src/tests/foo.rs"] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        assert_eq!(service.find_existing_test_file(&root, "src/x/mod.rs"), None);
+        assert_eq!(service.find_existing_test_file(&root, "src/foo.rs"), None);
+    }
+
+    #[test]
+    fn prefers_the_path_new_tests_are_written_to() {
+        let (root, _) = temp_project("derived");
+        let service = FileService::new();
+        for test_file in ["src/__tests__/cart.test.ts", "src/cart.test.ts"] {
+            let path = root.join(test_file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        assert_eq!(service.find_existing_test_file(&root, "src/cart.ts").as_deref(), Some("src/__tests__/cart.test.ts"));
+    }
+}
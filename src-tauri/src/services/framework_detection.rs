@@ -0,0 +1,507 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// JavaScript test runners in order of preference when a project lists several
+pub(crate) const JS_FRAMEWORKS: &[&str] = &["vitest", "jest", "mocha", "ava"];
+
+/// Config file names (without extension) that identify a JavaScript test runner
+const JS_CONFIG_FILES: &[(&str, &str)] = &[
+    ("vitest.config", "vitest"),
+    ("vitest.workspace", "vitest"),
+    ("jest.config", "jest"),
+    (".mocharc", "mocha"),
+    ("ava.config", "ava"),
+];
+
+/// Extensions JavaScript test runner config files are written with
+const JS_CONFIG_EXTENSIONS: &[&str] = &["ts", "mts", "cts", "js", "mjs", "cjs", "json", "yml", "yaml"];
+
+/// Rust crates that change how tests are written, in order of preference
+const RUST_FRAMEWORKS: &[&str] = &["rstest", "test-case", "proptest"];
+
+/// Go test libraries, by module path prefix
+const GO_FRAMEWORKS: &[(&str, &str)] = &[
+    ("github.com/onsi/ginkgo", "ginkgo"),
+    ("github.com/stretchr/testify", "testify"),
+];
+
+/// Java test frameworks, by the Maven artifact that pulls them in
+const JAVA_FRAMEWORKS: &[(&str, &str)] = &[
+    ("junit-jupiter", "junit5"),
+    ("junit-bom", "junit5"),
+    ("testng", "testng"),
+    ("junit", "junit4"),
+];
+
+/// A test framework found in a project's manifests
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectedFramework {
+    /// Framework name as used in prompt template keys, e.g. `vitest` or `pytest`
+    pub name: String,
+    /// Version required by the manifest, without range operators, if one is given
+    pub version: Option<String>,
+    /// Manifest or config file the framework was found in, relative to the project root
+    pub manifest: String,
+}
+
+/// Finds the test framework a project uses for a language
+///
+/// Manifests are looked for in the directory of `source_file` and each parent up to the
+/// project root, so packages of a monorepo can use different frameworks.
+pub fn detect_test_framework(project_dir: &Path, source_file: Option<&str>, language: &str) -> Option<DetectedFramework> {
+    let start = source_file
+        .and_then(|file| Path::new(file).parent())
+        .map(|dir| project_dir.join(dir))
+        .unwrap_or_else(|| project_dir.to_path_buf());
+
+    let mut dir = Some(start.as_path());
+    while let Some(current) = dir {
+        if let Some((name, version, manifest)) = detect_in_dir(current, language) {
+            let manifest = manifest
+                .strip_prefix(project_dir)
+                .unwrap_or(&manifest)
+                .to_string_lossy()
+                .into_owned();
+            log::info!("Detected test framework {} {:?} from {}", name, version, manifest);
+            return Some(DetectedFramework { name, version, manifest });
+        }
+        if current == project_dir {
+            break;
+        }
+        dir = current.parent().filter(|parent| parent.starts_with(project_dir));
+    }
+
+    None
+}
+
+/// The framework and version to generate tests for
+///
+/// A framework the caller names is used as is; otherwise the detected one is. The version is
+/// only known when it comes from the project.
+pub fn resolve_test_framework(
+    project_dir: &Path,
+    source_file: Option<&str>,
+    language: &str,
+    requested: Option<String>,
+) -> (Option<String>, Option<String>) {
+    let requested = requested.filter(|framework| !framework.trim().is_empty());
+    let detected = detect_test_framework(project_dir, source_file, language);
+
+    match (requested, detected) {
+        (Some(requested), Some(detected)) if requested.eq_ignore_ascii_case(&detected.name) => {
+            (Some(requested), detected.version)
+        }
+        (Some(requested), _) => (Some(requested), None),
+        (None, Some(detected)) => (Some(detected.name), detected.version),
+        (None, None) => (None, None),
+    }
+}
+
+type Detection = (String, Option<String>, PathBuf);
+
+/// Looks for the manifests of a language in one directory
+fn detect_in_dir(dir: &Path, language: &str) -> Option<Detection> {
+    match language.to_lowercase().as_str() {
+        "javascript" | "typescript" | "react" => detect_javascript(dir),
+        "python" => detect_python(dir),
+        "rust" => detect_rust(dir),
+        "go" => detect_go(dir),
+        "java" | "kotlin" => detect_java(dir),
+        _ => None,
+    }
+}
+
+fn detect_javascript(dir: &Path) -> Option<Detection> {
+    let package_path = dir.join("package.json");
+    let package: Option<Value> = fs::read_to_string(&package_path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok());
+    let dependency_version = |name: &str| {
+        let package = package.as_ref()?;
+        ["devDependencies", "dependencies"]
+            .iter()
+            .find_map(|section| package.get(section)?.get(name)?.as_str())
+            .map(str::to_string)
+    };
+
+    // A runner's own config file is the strongest signal
+    for (stem, framework) in JS_CONFIG_FILES {
+        for extension in JS_CONFIG_EXTENSIONS {
+            let config = dir.join(format!("{}.{}", stem, extension));
+            if config.is_file() {
+                let version = dependency_version(*framework).and_then(|spec| clean_version(&spec));
+                return Some((framework.to_string(), version, config));
+            }
+        }
+    }
+    let rc = dir.join(".mocharc");
+    if rc.is_file() {
+        let version = dependency_version("mocha").and_then(|spec| clean_version(&spec));
+        return Some(("mocha".to_string(), version, rc));
+    }
+
+    let package = package.as_ref()?;
+    let test_script = package
+        .get("scripts")
+        .and_then(|scripts| scripts.get("test"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    // Then whichever runner the test script calls, is configured in package.json or is installed
+    let frameworks = || JS_FRAMEWORKS.iter().copied();
+    let framework = frameworks()
+        .find(|framework| test_script.split_whitespace().any(|word| word == *framework))
+        .or_else(|| frameworks().find(|framework| package.get(*framework).is_some()))
+        .or_else(|| frameworks().find(|framework| dependency_version(framework).is_some()))?;
+    let version = dependency_version(framework).and_then(|spec| clean_version(&spec));
+    Some((framework.to_string(), version, package_path))
+}
+
+fn detect_python(dir: &Path) -> Option<Detection> {
+    // Files that configure pytest, with the section that marks it
+    let configs = [
+        ("pytest.ini", ""),
+        ("pyproject.toml", "[tool.pytest"),
+        ("setup.cfg", "[tool:pytest]"),
+        ("tox.ini", "[pytest]"),
+    ];
+    // Files that can pin pytest's version
+    let requirements = ["pyproject.toml", "requirements-dev.txt", "requirements.txt", "setup.cfg", "tox.ini"];
+    let version = || {
+        requirements.iter().find_map(|file| {
+            let text = fs::read_to_string(dir.join(file)).ok()?;
+            text.lines().find_map(|line| requirement_version(line, "pytest"))
+        })
+    };
+
+    for (file, marker) in configs {
+        let path = dir.join(file);
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if text.contains(marker) {
+            return Some(("pytest".to_string(), version(), path));
+        }
+    }
+
+    // pytest listed as a dependency without any configuration
+    for file in requirements {
+        let path = dir.join(file);
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if text.lines().any(|line| mentions_package(line, "pytest")) {
+            let version = text.lines().find_map(|line| requirement_version(line, "pytest"));
+            return Some(("pytest".to_string(), version, path));
+        }
+    }
+
+    None
+}
+
+fn detect_rust(dir: &Path) -> Option<Detection> {
+    let path = dir.join("Cargo.toml");
+    let text = fs::read_to_string(&path).ok()?;
+
+    let mut in_dev_dependencies = false;
+    let mut found: Vec<(&str, Option<String>)> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_dev_dependencies = line.contains("dev-dependencies");
+            continue;
+        }
+        if !in_dev_dependencies {
+            continue;
+        }
+        let Some((name, spec)) = line.split_once('=') else {
+            continue;
+        };
+        if let Some(framework) = RUST_FRAMEWORKS.iter().find(|framework| name.trim() == **framework) {
+            found.push((*framework, cargo_version(spec)));
+        }
+    }
+
+    // Without any of these crates, tests use the built-in harness and the default guidance applies
+    let (framework, version) = RUST_FRAMEWORKS
+        .iter()
+        .find_map(|framework| found.iter().find(|(name, _)| name == framework))?;
+    Some((framework.to_string(), version.clone(), path))
+}
+
+fn detect_go(dir: &Path) -> Option<Detection> {
+    let path = dir.join("go.mod");
+    let text = fs::read_to_string(&path).ok()?;
+
+    for (module, framework) in GO_FRAMEWORKS {
+        let line = text.lines().map(str::trim).find(|line| {
+            line.trim_start_matches("require").trim_start().starts_with(*module)
+        });
+        if let Some(line) = line {
+            let version = line.split_whitespace().find(|word| word.starts_with('v')).and_then(clean_version);
+            return Some((framework.to_string(), version, path));
+        }
+    }
+    None
+}
+
+fn detect_java(dir: &Path) -> Option<Detection> {
+    let pom = dir.join("pom.xml");
+    if let Ok(text) = fs::read_to_string(&pom) {
+        for (artifact, framework) in JAVA_FRAMEWORKS {
+            let tag = format!("<artifactId>{}</artifactId>", artifact);
+            if let Some(position) = text.find(&tag) {
+                // The version follows the artifact ID within the same dependency
+                let dependency = &text[position..];
+                let dependency = &dependency[..dependency.find("</dependency>").unwrap_or(dependency.len())];
+                let version = dependency
+                    .split_once("<version>")
+                    .and_then(|(_, rest)| rest.split_once("</version>"))
+                    .and_then(|(version, _)| clean_version(version));
+                return Some((framework.to_string(), version, pom));
+            }
+        }
+    }
+
+    for file in ["build.gradle", "build.gradle.kts"] {
+        let path = dir.join(file);
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for (artifact, framework) in JAVA_FRAMEWORKS {
+            // Coordinates are `group:artifact:version`
+            let coordinate = text.lines().find_map(|line| {
+                line.split(['"', '\''])
+                    .find(|part| part.split(':').nth(1) == Some(*artifact))
+                    .map(str::to_string)
+            });
+            if let Some(coordinate) = coordinate {
+                let version = coordinate.split(':').nth(2).and_then(clean_version);
+                return Some((framework.to_string(), version, path));
+            }
+        }
+    }
+
+    None
+}
+
+/// Whether a requirements or pyproject line names the package (and not e.g. `pytest-cov`)
+fn mentions_package(line: &str, package: &str) -> bool {
+    let line = line.trim().trim_start_matches(['"', '\'']);
+    line.strip_prefix(package).is_some_and(|rest| {
+        !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    })
+}
+
+/// The version in a requirement like `pytest>=7.4`, `"pytest ~= 8.0",` or `pytest = "^7.4"`
+fn requirement_version(line: &str, package: &str) -> Option<String> {
+    if !mentions_package(line, package) {
+        return None;
+    }
+    let spec = line.trim().trim_start_matches(['"', '\''])[package.len()..].trim_start();
+    spec.starts_with(['=', '<', '>', '~', '!', '^', '"'])
+        .then(|| clean_version(spec))
+        .flatten()
+}
+
+/// The version of a Cargo dependency, written as `"1.2"` or `{ version = "1.2", ... }`
+fn cargo_version(spec: &str) -> Option<String> {
+    let spec = spec.trim();
+    if spec.starts_with('"') {
+        return clean_version(spec);
+    }
+    let (_, rest) = spec.split_once("version")?;
+    let (_, rest) = rest.split_once('=')?;
+    clean_version(rest.trim_start().split(',').next()?)
+}
+
+/// Strips quotes and range operators, returning None for specs that aren't a version number
+fn clean_version(spec: &str) -> Option<String> {
+    let version: String = spec
+        .trim()
+        .trim_start_matches(|c: char| matches!(c, '"' | '\'' | '=' | '<' | '>' | '~' | '^' | '!' | 'v' | ' '))
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+        .collect();
+
+    version.starts_with(|c: char| c.is_ascii_digit()).then_some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh project directory with the given files
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("framework-detection-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn detect(root: &Path, source_file: Option<&str>, language: &str) -> Option<(String, Option<String>, String)> {
+        detect_test_framework(root, source_file, language).map(|found| (found.name, found.version, found.manifest))
+    }
+
+    fn found(name: &str, version: Option<&str>, manifest: &str) -> Option<(String, Option<String>, String)> {
+        Some((name.to_string(), version.map(str::to_string), manifest.to_string()))
+    }
+
+    #[test]
+    fn prefers_a_runner_config_file_over_package_json() {
+        let root = project(
+            "js-config",
+            &[
+                ("package.json", r#"{"scripts": {"test": "jest"}, "devDependencies": {"jest": "^29.7.0", "vitest": "^1.2.0"}}"#),
+                ("vitest.config.mts", "export default {}"),
+            ],
+        );
+        assert_eq!(detect(&root, None, "typescript"), found("vitest", Some("1.2.0"), "vitest.config.mts"));
+    }
+
+    #[test]
+    fn finds_the_runner_in_the_test_script_then_the_dependencies() {
+        let root = project(
+            "js-script",
+            &[("package.json", r#"{"scripts": {"test": "mocha --recursive"}, "devDependencies": {"jest": "29.0.0", "mocha": "~10.2.0"}}"#)],
+        );
+        assert_eq!(detect(&root, None, "javascript"), found("mocha", Some("10.2.0"), "package.json"));
+
+        let root = project("js-deps", &[("package.json", r#"{"dependencies": {"jest": "latest"}}"#)]);
+        assert_eq!(detect(&root, None, "javascript"), found("jest", None, "package.json"));
+
+        let root = project("js-none", &[("package.json", r#"{"dependencies": {"react": "^18.0.0"}}"#)]);
+        assert_eq!(detect(&root, None, "javascript"), None);
+    }
+
+    #[test]
+    fn uses_the_nearest_package_of_a_monorepo() {
+        let root = project(
+            "monorepo",
+            &[
+                ("package.json", r#"{"devDependencies": {"jest": "^29.0.0"}}"#),
+                ("packages/web/package.json", r#"{"devDependencies": {"vitest": "^1.6.0"}}"#),
+                ("packages/web/src/app.ts", ""),
+                ("packages/api/src/server.ts", ""),
+            ],
+        );
+        assert_eq!(
+            detect(&root, Some("packages/web/src/app.ts"), "typescript"),
+            found("vitest", Some("1.6.0"), "packages/web/package.json")
+        );
+        assert_eq!(
+            detect(&root, Some("packages/api/src/server.ts"), "typescript"),
+            found("jest", Some("29.0.0"), "package.json")
+        );
+    }
+
+    #[test]
+    fn detects_pytest_from_config_or_requirements() {
+        let root = project(
+            "pyproject",
+            &[(
+                "pyproject.toml",
+                "[project]\ndependencies = [\n    \"pytest>=7.4\",\n    \"pytest-cov\",\n]\n\n[tool.pytest.ini_options]\naddopts = \"-q\"\n",
+            )],
+        );
+        assert_eq!(detect(&root, None, "python"), found("pytest", Some("7.4"), "pyproject.toml"));
+
+        let root = project("requirements", &[("requirements-dev.txt", "black==24.1.0\npytest==8.0.2\n")]);
+        assert_eq!(detect(&root, None, "python"), found("pytest", Some("8.0.2"), "requirements-dev.txt"));
+
+        // A pytest plugin alone doesn't mean pytest is configured
+        let root = project("plugin-only", &[("requirements.txt", "pytest-cov>=4.1\n")]);
+        assert_eq!(detect(&root, None, "python"), None);
+    }
+
+    #[test]
+    fn detects_rust_test_crates_in_dev_dependencies_only() {
+        let root = project(
+            "rstest",
+            &[(
+                "Cargo.toml",
+                "[package]\nname = \"demo\"\n\n[dependencies]\nserde = \"1\"\n\n[dev-dependencies]\nproptest = { version = \"1.4\", default-features = false }\nrstest = \"0.18.2\"\n",
+            )],
+        );
+        assert_eq!(detect(&root, Some("src/lib.rs"), "rust"), found("rstest", Some("0.18.2"), "Cargo.toml"));
+
+        let root = project(
+            "proptest",
+            &[("Cargo.toml", "[dev-dependencies]\nproptest = { version = \"1.4\", default-features = false }\n")],
+        );
+        assert_eq!(detect(&root, None, "rust"), found("proptest", Some("1.4"), "Cargo.toml"));
+
+        let root = project("rust-plain", &[("Cargo.toml", "[dependencies]\nrstest = \"0.18\"\n")]);
+        assert_eq!(detect(&root, None, "rust"), None);
+    }
+
+    #[test]
+    fn detects_go_test_libraries() {
+        let root = project(
+            "go",
+            &[(
+                "go.mod",
+                "module example.com/demo\n\ngo 1.21\n\nrequire (\n\tgithub.com/google/uuid v1.6.0\n\tgithub.com/stretchr/testify v1.8.4\n)\n",
+            )],
+        );
+        assert_eq!(detect(&root, None, "go"), found("testify", Some("1.8.4"), "go.mod"));
+    }
+
+    #[test]
+    fn detects_java_frameworks_in_maven_and_gradle() {
+        let root = project(
+            "maven",
+            &[(
+                "pom.xml",
+                "<project><dependencies>\n<dependency>\n<groupId>org.junit.jupiter</groupId>\n<artifactId>junit-jupiter</artifactId>\n<version>5.10.0</version>\n<scope>test</scope>\n</dependency>\n</dependencies></project>",
+            )],
+        );
+        assert_eq!(detect(&root, None, "java"), found("junit5", Some("5.10.0"), "pom.xml"));
+
+        let root = project(
+            "gradle",
+            &[("build.gradle.kts", "dependencies {\n    testImplementation(\"org.testng:testng:7.9.0\")\n}\n")],
+        );
+        assert_eq!(detect(&root, None, "java"), found("testng", Some("7.9.0"), "build.gradle.kts"));
+    }
+
+    #[test]
+    fn resolves_the_requested_framework_against_the_detected_one() {
+        let root = project("resolve", &[("package.json", r#"{"devDependencies": {"jest": "^29.7.0"}}"#)]);
+
+        assert_eq!(
+            resolve_test_framework(&root, None, "javascript", None),
+            (Some("jest".to_string()), Some("29.7.0".to_string()))
+        );
+        assert_eq!(
+            resolve_test_framework(&root, None, "javascript", Some("Jest".to_string())),
+            (Some("Jest".to_string()), Some("29.7.0".to_string()))
+        );
+        // The detected version says nothing about another framework
+        assert_eq!(
+            resolve_test_framework(&root, None, "javascript", Some("vitest".to_string())),
+            (Some("vitest".to_string()), None)
+        );
+        assert_eq!(
+            resolve_test_framework(&root, None, "javascript", Some(" ".to_string())),
+            (Some("jest".to_string()), Some("29.7.0".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_version_specs() {
+        assert_eq!(requirement_version("\"pytest ~= 8.0\",", "pytest"), Some("8.0".to_string()));
+        assert_eq!(requirement_version("pytest = \"^7.4\"", "pytest"), Some("7.4".to_string()));
+        assert_eq!(requirement_version("pytest", "pytest"), None);
+        assert_eq!(requirement_version("pytest-asyncio==0.23", "pytest"), None);
+        assert_eq!(cargo_version(" { version = \"1.4\", features = [\"std\"] }"), Some("1.4".to_string()));
+        assert_eq!(clean_version("workspace:*"), None);
+        assert_eq!(clean_version("v1.8.4"), Some("1.8.4".to_string()));
+    }
+}
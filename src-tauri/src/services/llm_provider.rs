@@ -0,0 +1,213 @@
+use langchain_rust::{
+    language_models::llm::LLM,
+    llm::{Claude, OpenAI, OpenAIConfig},
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::services::mock_provider::{MockProvider, RecordingProvider};
+
+/// Default model used when the caller does not pick one for Anthropic
+pub const DEFAULT_ANTHROPIC_MODEL: &str = "claude-3-7-sonnet-20250219";
+
+/// Default model used when the caller does not pick one for OpenAI
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+
+/// The kinds of LLM backends the agent service can talk to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// Anthropic's Messages API
+    Anthropic,
+    /// OpenAI's hosted Chat Completions API
+    #[serde(rename = "openai")]
+    OpenAi,
+    /// Any server speaking the OpenAI Chat Completions protocol (vLLM, Ollama, LM Studio, ...)
+    #[serde(rename = "openai_compatible")]
+    OpenAiCompatible,
+    /// Offline provider that replays recorded fixtures, for tests and CI
+    Mock,
+}
+
+impl ProviderKind {
+    /// Parses a provider name as sent by the frontend
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "anthropic" | "claude" => Ok(ProviderKind::Anthropic),
+            "openai" => Ok(ProviderKind::OpenAi),
+            "openai_compatible" | "openai-compatible" | "self_hosted" | "self-hosted" => {
+                Ok(ProviderKind::OpenAiCompatible)
+            }
+            "mock" => Ok(ProviderKind::Mock),
+            other => Err(format!("Unknown LLM provider: {}", other)),
+        }
+    }
+}
+
+/// Settings chosen at `initialize_agent` time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub kind: ProviderKind,
+    /// API key for the provider; may be empty for self-hosted endpoints
+    pub api_key: String,
+    /// Model name; falls back to the provider default when not set
+    pub model: Option<String>,
+    /// Base URL of the API, e.g. `http://localhost:8000/v1`
+    pub base_url: Option<String>,
+    /// Directory the mock provider replays fixtures from, and recorded fixtures are written to
+    #[serde(default)]
+    pub fixtures_dir: Option<String>,
+    /// Save every response of a real provider as a fixture in `fixtures_dir`
+    #[serde(default)]
+    pub record_fixtures: bool,
+}
+
+/// A configured LLM backend the agent service can create clients from
+pub trait LlmProvider: Send + Sync {
+    /// Which backend this provider talks to
+    fn kind(&self) -> ProviderKind;
+
+    /// The model used for every request
+    fn model(&self) -> &str;
+
+    /// Creates a fresh client for a single request or chain
+    fn llm(&self) -> Box<dyn LLM>;
+}
+
+/// Provider backed by Anthropic's Claude models
+pub struct AnthropicProvider {
+    client: Claude,
+    model: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: String, model: Option<String>) -> Self {
+        let model = model.unwrap_or_else(|| DEFAULT_ANTHROPIC_MODEL.to_string());
+        let client = Claude::default()
+            .with_api_key(api_key)
+            .with_model(model.clone());
+
+        Self { client, model }
+    }
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Anthropic
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn llm(&self) -> Box<dyn LLM> {
+        Box::new(self.client.clone())
+    }
+}
+
+/// Provider backed by OpenAI or any OpenAI-compatible server
+pub struct OpenAiProvider {
+    kind: ProviderKind,
+    client: OpenAI<OpenAIConfig>,
+    model: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(kind: ProviderKind, api_key: String, model: Option<String>, base_url: Option<String>) -> Self {
+        let model = model.unwrap_or_else(|| DEFAULT_OPENAI_MODEL.to_string());
+
+        let mut config = OpenAIConfig::default().with_api_key(api_key);
+        if let Some(base_url) = base_url {
+            config = config.with_api_base(base_url.trim_end_matches('/'));
+        }
+
+        let client = OpenAI::new(config).with_model(model.clone());
+
+        Self { kind, client, model }
+    }
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn kind(&self) -> ProviderKind {
+        self.kind
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn llm(&self) -> Box<dyn LLM> {
+        Box::new(self.client.clone())
+    }
+}
+
+/// Builds the provider described by the given configuration
+pub fn build_provider(config: ProviderConfig) -> Result<Arc<dyn LlmProvider>, String> {
+    let fixtures_dir = config
+        .fixtures_dir
+        .clone()
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from);
+
+    if !config.record_fixtures {
+        return build_client_provider(config, fixtures_dir);
+    }
+    if config.kind == ProviderKind::Mock {
+        return Err("Fixtures can only be recorded from a real provider, not the mock provider".to_string());
+    }
+    let fixtures_dir =
+        fixtures_dir.ok_or_else(|| "A fixtures directory is required to record fixtures".to_string())?;
+    let inner = build_client_provider(config, None)?;
+    Ok(Arc::new(RecordingProvider::new(inner, fixtures_dir)))
+}
+
+/// Builds the provider that talks to the configured backend
+fn build_client_provider(config: ProviderConfig, fixtures_dir: Option<PathBuf>) -> Result<Arc<dyn LlmProvider>, String> {
+    let model = config.model.filter(|m| !m.trim().is_empty());
+    let base_url = config.base_url.filter(|u| !u.trim().is_empty());
+
+    match config.kind {
+        ProviderKind::Anthropic => {
+            if config.api_key.is_empty() {
+                return Err("An API key is required for the Anthropic provider".to_string());
+            }
+            // The underlying Claude client always targets api.anthropic.com
+            if base_url.is_some() {
+                return Err(
+                    "The Anthropic provider does not support a custom base URL; use the openai_compatible provider for local servers"
+                        .to_string(),
+                );
+            }
+            Ok(Arc::new(AnthropicProvider::new(config.api_key, model)))
+        }
+        ProviderKind::OpenAi => {
+            if config.api_key.is_empty() {
+                return Err("An API key is required for the OpenAI provider".to_string());
+            }
+            Ok(Arc::new(OpenAiProvider::new(ProviderKind::OpenAi, config.api_key, model, base_url)))
+        }
+        ProviderKind::OpenAiCompatible => {
+            let base_url = base_url.ok_or_else(|| {
+                "A base URL is required for the openai_compatible provider".to_string()
+            })?;
+            let model = model.ok_or_else(|| {
+                "A model name is required for the openai_compatible provider".to_string()
+            })?;
+            Ok(Arc::new(OpenAiProvider::new(
+                ProviderKind::OpenAiCompatible,
+                config.api_key,
+                Some(model),
+                Some(base_url),
+            )))
+        }
+        ProviderKind::Mock => {
+            let fixtures_dir = fixtures_dir
+                .ok_or_else(|| "A fixtures directory is required for the mock provider".to_string())?;
+            if !fixtures_dir.is_dir() {
+                return Err(format!("Fixtures directory does not exist: {}", fixtures_dir.display()));
+            }
+            Ok(Arc::new(MockProvider::new(fixtures_dir, model)))
+        }
+    }
+}
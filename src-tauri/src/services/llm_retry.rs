@@ -0,0 +1,446 @@
+use rand::Rng;
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+/// Number of times a transient LLM error is retried unless configured otherwise
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry; doubled for every further attempt
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Upper bound on the computed backoff delay (a longer retry hint is still honored)
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Upper bound on how long a provider's retry hint can make us wait
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// What went wrong with a provider call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmErrorKind {
+    /// The provider is throttling us (HTTP 429)
+    RateLimited,
+    /// The provider is overloaded or had an internal error (HTTP 5xx, 529)
+    Overloaded,
+    /// The request could not reach the provider or timed out
+    Network,
+    /// The API key is missing, invalid or lacks permission
+    Auth,
+    /// The prompt does not fit in the model's context window
+    ContextLength,
+    /// Anything else, including invalid requests and exhausted quotas
+    Other,
+}
+
+impl LlmErrorKind {
+    /// Whether retrying the same request later may succeed
+    pub fn is_transient(self) -> bool {
+        matches!(self, LlmErrorKind::RateLimited | LlmErrorKind::Overloaded | LlmErrorKind::Network)
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            LlmErrorKind::RateLimited => "Rate limited by the LLM provider",
+            LlmErrorKind::Overloaded => "The LLM provider is overloaded",
+            LlmErrorKind::Network => "Could not reach the LLM provider",
+            LlmErrorKind::Auth => "The LLM provider rejected the API key",
+            LlmErrorKind::ContextLength => "The request is too large for the model's context window",
+            LlmErrorKind::Other => "The LLM provider returned an error",
+        }
+    }
+}
+
+/// A classified error from an LLM provider call
+#[derive(Debug, Clone)]
+pub struct LlmError {
+    pub kind: LlmErrorKind,
+    /// The provider's error message
+    pub message: String,
+    /// How long the provider's message asked us to wait before retrying, if it said
+    pub retry_after: Option<Duration>,
+}
+
+impl LlmError {
+    /// Classifies a provider error from its message
+    ///
+    /// The LangChain clients only surface provider errors as text, so the kind is recovered
+    /// from the wording the Anthropic and OpenAI APIs use. They never expose the response
+    /// headers, so a Retry-After header is not seen; only a wait mentioned in the message
+    /// itself, like OpenAI's "Please try again in 20s", is honored.
+    pub fn classify(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let lower = message.to_lowercase();
+        let mentions = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+
+        let kind = if mentions(&[
+            "context length",
+            "context_length",
+            "maximum context",
+            "context window",
+            "prompt is too long",
+            "too many tokens",
+        ]) {
+            LlmErrorKind::ContextLength
+        } else if mentions(&[
+            "authentication",
+            "api key",
+            "api_key",
+            "x-api-key",
+            "unauthorized",
+            "permission denied",
+        ]) {
+            LlmErrorKind::Auth
+        } else if mentions(&["insufficient_quota", "exceeded your current quota"]) {
+            // Billing problems come back as 429s but waiting won't fix them
+            LlmErrorKind::Other
+        } else if mentions(&["rate limit", "rate_limit", "too many requests"]) {
+            LlmErrorKind::RateLimited
+        } else if mentions(&[
+            "overloaded",
+            "internal error",
+            "internal server error",
+            "service unavailable",
+            "bad gateway",
+            "server_error",
+        ]) {
+            LlmErrorKind::Overloaded
+        } else if mentions(&["error decoding response body"]) {
+            // The Claude client only maps a few statuses to errors; on any other one it tries
+            // to read the error body as a response, so 500s and 529s arrive as decode errors.
+            // A rejected request (400) looks the same and is retried a few times needlessly.
+            LlmErrorKind::Overloaded
+        } else if mentions(&[
+            "network request failed",
+            "error sending request",
+            "connection",
+            "timed out",
+            "timeout",
+        ]) {
+            LlmErrorKind::Network
+        } else {
+            LlmErrorKind::Other
+        };
+
+        let retry_after = parse_retry_after(&lower);
+        Self { kind, message, retry_after }
+    }
+}
+
+impl fmt::Display for LlmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.description(), self.message)
+    }
+}
+
+impl From<LlmError> for String {
+    fn from(error: LlmError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Details of a retry that is about to happen, reported to the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryNotice {
+    /// Which retry this is, starting at 1
+    pub attempt: u32,
+    /// How many retries are allowed in total
+    pub max_retries: u32,
+    /// Why the previous attempt failed
+    pub kind: LlmErrorKind,
+    /// How long we wait before retrying, in milliseconds
+    pub delay_ms: u64,
+    /// The error of the previous attempt
+    pub error: String,
+}
+
+/// How transient LLM errors are retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// Backoff ceiling for the first retry
+    pub initial_delay: Duration,
+    /// Backoff ceiling for later retries
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_delay: INITIAL_RETRY_DELAY,
+            max_delay: MAX_RETRY_DELAY,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Decides whether to retry after `error`, which ended attempt number `attempt`
+    /// (starting at 1); returns the retry to report and wait for, or None to give up
+    pub fn next_retry(&self, attempt: u32, error: &LlmError) -> Option<RetryNotice> {
+        if !error.kind.is_transient() || attempt > self.max_retries {
+            return None;
+        }
+
+        // Exponential backoff with full jitter, so parallel requests don't retry in lockstep
+        let ceiling = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        let backoff = Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling.as_millis() as u64));
+
+        // Never retry sooner than the provider asked us to
+        let delay = match error.retry_after {
+            Some(retry_after) => backoff.max(retry_after.min(MAX_RETRY_AFTER)),
+            None => backoff,
+        };
+
+        log::warn!(
+            "LLM call failed ({:?}), retry {}/{} in {}ms: {}",
+            error.kind,
+            attempt,
+            self.max_retries,
+            delay.as_millis(),
+            error.message
+        );
+
+        Some(RetryNotice {
+            attempt,
+            max_retries: self.max_retries,
+            kind: error.kind,
+            delay_ms: delay.as_millis() as u64,
+            error: error.to_string(),
+        })
+    }
+}
+
+/// Runs a provider call, retrying transient failures according to the policy
+///
+/// `operation` is called once per attempt; `on_retry` is told about each retry before
+/// the wait starts.
+pub async fn with_retry<T, F, Fut, R>(policy: RetryPolicy, mut operation: F, mut on_retry: R) -> Result<T, LlmError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, LlmError>>,
+    R: FnMut(&RetryNotice),
+{
+    let mut attempt = 0;
+    loop {
+        let error = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+
+        attempt += 1;
+        let Some(retry) = policy.next_retry(attempt, &error) else {
+            return Err(error);
+        };
+        on_retry(&retry);
+        tokio::time::sleep(Duration::from_millis(retry.delay_ms)).await;
+    }
+}
+
+/// Finds a "try again in N seconds" style hint in the text of a lowercased error message
+fn parse_retry_after(lower: &str) -> Option<Duration> {
+    const MARKERS: &[&str] = &["try again in", "retry after"];
+
+    MARKERS.iter().find_map(|marker| {
+        let start = lower.find(marker)? + marker.len();
+        let rest = lower[start..].trim_start();
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..number_len].parse().ok()?;
+        let unit = rest[number_len..].trim_start();
+
+        let seconds = if unit.starts_with("ms") {
+            value / 1000.0
+        } else if unit.starts_with('m') {
+            value * 60.0
+        } else {
+            value
+        };
+        (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// A policy that retries without waiting
+    fn instant_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy { max_retries, initial_delay: Duration::ZERO, max_delay: Duration::ZERO }
+    }
+
+    fn error(kind: LlmErrorKind) -> LlmError {
+        LlmError { kind, message: "failed".to_string(), retry_after: None }
+    }
+
+    #[test]
+    fn classifies_provider_messages() {
+        let kind = |message: &str| LlmError::classify(message).kind;
+
+        assert_eq!(
+            kind("LLM error: Anthropic error: Anthropic API error: Rate limit exceeded - Rate Limit Exceeded"),
+            LlmErrorKind::RateLimited
+        );
+        assert_eq!(
+            kind("Anthropic error: Anthropic API error: Overloaded - Service Unavailable"),
+            LlmErrorKind::Overloaded
+        );
+        assert_eq!(
+            kind("Anthropic error: Anthropic API error: Authentication failed - Invalid API Key"),
+            LlmErrorKind::Auth
+        );
+        assert_eq!(
+            kind("OpenAI error: invalid_request_error: This model's maximum context length is 128000 tokens"),
+            LlmErrorKind::ContextLength
+        );
+        assert_eq!(kind("OpenAI error: You exceeded your current quota"), LlmErrorKind::Other);
+        assert_eq!(
+            kind("Network request failed: error sending request for url (https://api.openai.com/v1/chat/completions)"),
+            LlmErrorKind::Network
+        );
+        assert_eq!(kind("Operation timed out"), LlmErrorKind::Network);
+        assert_eq!(kind("Content not found in response"), LlmErrorKind::Other);
+    }
+
+    #[test]
+    fn treats_undecodable_responses_as_overloaded() {
+        // What the Claude client reports for a 500 or 529
+        let error = LlmError::classify("LLM error: Network request failed: error decoding response body");
+        assert_eq!(error.kind, LlmErrorKind::Overloaded);
+        assert!(error.kind.is_transient());
+    }
+
+    #[test]
+    fn reads_a_wait_from_the_message_text() {
+        let error = LlmError::classify(
+            "OpenAI error: Rate limit reached for gpt-4o-mini on tokens per min. Please try again in 1.5s.",
+        );
+        assert_eq!(error.kind, LlmErrorKind::RateLimited);
+        assert_eq!(error.retry_after, Some(Duration::from_millis(1500)));
+
+        assert_eq!(LlmError::classify("Please try again in 20ms").retry_after, Some(Duration::from_millis(20)));
+        assert_eq!(LlmError::classify("Retry after 2 minutes").retry_after, Some(Duration::from_secs(120)));
+        assert_eq!(LlmError::classify("Rate limit exceeded").retry_after, None);
+    }
+
+    #[test]
+    fn never_retries_permanent_errors() {
+        let policy = RetryPolicy::default();
+        for kind in [LlmErrorKind::Auth, LlmErrorKind::ContextLength, LlmErrorKind::Other] {
+            assert!(policy.next_retry(1, &error(kind)).is_none(), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn stops_after_the_last_retry() {
+        let policy = RetryPolicy { max_retries: 2, ..RetryPolicy::default() };
+        let overloaded = error(LlmErrorKind::Overloaded);
+
+        let retry = policy.next_retry(2, &overloaded).unwrap();
+        assert_eq!((retry.attempt, retry.max_retries, retry.kind), (2, 2, LlmErrorKind::Overloaded));
+        assert!(policy.next_retry(3, &overloaded).is_none());
+        assert!(instant_policy(0).next_retry(1, &overloaded).is_none());
+    }
+
+    #[test]
+    fn keeps_the_jittered_backoff_under_its_ceiling() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+        };
+        let network = error(LlmErrorKind::Network);
+
+        for _ in 0..50 {
+            assert!(policy.next_retry(1, &network).unwrap().delay_ms <= 100);
+            assert!(policy.next_retry(2, &network).unwrap().delay_ms <= 200);
+            // Doubling stops at the maximum delay
+            assert!(policy.next_retry(8, &network).unwrap().delay_ms <= 500);
+        }
+    }
+
+    #[test]
+    fn waits_at_least_as_long_as_the_provider_asked() {
+        let policy = RetryPolicy::default();
+        let asked = |seconds| LlmError {
+            retry_after: Some(Duration::from_secs(seconds)),
+            ..error(LlmErrorKind::RateLimited)
+        };
+
+        assert!(policy.next_retry(1, &asked(45)).unwrap().delay_ms >= 45_000);
+        // A hint longer than the cap is cut down to it
+        assert_eq!(policy.next_retry(1, &asked(600)).unwrap().delay_ms, MAX_RETRY_AFTER.as_millis() as u64);
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors_until_one_succeeds() {
+        let attempts = Cell::new(0);
+        let mut notices = Vec::new();
+
+        let result = with_retry(
+            instant_policy(3),
+            || {
+                attempts.set(attempts.get() + 1);
+                let attempt = attempts.get();
+                async move {
+                    if attempt < 3 {
+                        Err(error(LlmErrorKind::RateLimited))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            },
+            |notice| notices.push(notice.attempt),
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(notices, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_the_first_auth_or_context_length_error() {
+        for kind in [LlmErrorKind::Auth, LlmErrorKind::ContextLength] {
+            let attempts = Cell::new(0);
+            let mut retried = false;
+
+            let result: Result<(), LlmError> = with_retry(
+                instant_policy(3),
+                || {
+                    attempts.set(attempts.get() + 1);
+                    async move { Err(error(kind)) }
+                },
+                |_| retried = true,
+            )
+            .await;
+
+            assert_eq!(result.unwrap_err().kind, kind);
+            assert_eq!(attempts.get(), 1);
+            assert!(!retried);
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_the_last_error_once_retries_run_out() {
+        let attempts = Cell::new(0);
+        let result: Result<(), LlmError> = with_retry(
+            instant_policy(2),
+            || {
+                attempts.set(attempts.get() + 1);
+                async move { Err(error(LlmErrorKind::Overloaded)) }
+            },
+            |_| {},
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err().kind, LlmErrorKind::Overloaded);
+        assert_eq!(attempts.get(), 3);
+    }
+}
@@ -0,0 +1,406 @@
+use async_trait::async_trait;
+use futures::Stream;
+use langchain_rust::{
+    language_models::{llm::LLM, GenerateResult, LLMError},
+    schemas::{messages::Message, StreamData},
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::services::llm_provider::{LlmProvider, ProviderKind};
+use crate::services::response_cache::ResponseCache;
+
+/// Model name reported by the mock provider unless one is configured
+pub const DEFAULT_MOCK_MODEL: &str = "mock";
+
+/// File in the fixtures directory holding rule-based responses
+pub const MOCK_RULES_FILE: &str = "rules.json";
+
+/// A recorded response, stored as `<prompt hash>.json` in the fixtures directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Fixture {
+    /// Model that produced the response, for reference
+    model: String,
+    /// The prompt the response answers, for reference; only the hash is used for lookups
+    prompt: Vec<FixtureMessage>,
+    response: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FixtureMessage {
+    role: String,
+    content: String,
+}
+
+/// A response returned for any prompt that contains all of the given strings
+#[derive(Debug, Clone, Deserialize)]
+struct MockRule {
+    contains: Vec<String>,
+    response: String,
+}
+
+/// Reads and writes the fixtures in one directory
+#[derive(Debug, Clone)]
+struct FixtureStore {
+    dir: PathBuf,
+}
+
+impl FixtureStore {
+    /// Hash of a prompt, used as the fixture's file name
+    fn prompt_hash(messages: &[Message]) -> String {
+        let parts: Vec<String> = messages
+            .iter()
+            .flat_map(|message| [format!("{:?}", message.message_type), message.content.clone()])
+            .collect();
+        ResponseCache::key(&parts.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    /// The recorded response for a prompt, or the first rule matching it
+    fn lookup(&self, messages: &[Message]) -> Result<String, String> {
+        let hash = Self::prompt_hash(messages);
+        let path = self.dir.join(format!("{}.json", hash));
+        if path.exists() {
+            let data = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read fixture {}: {}", path.display(), e))?;
+            let fixture: Fixture = serde_json::from_str(&data)
+                .map_err(|e| format!("Failed to parse fixture {}: {}", path.display(), e))?;
+            return Ok(fixture.response);
+        }
+
+        let prompt = messages.iter().map(|message| message.content.as_str()).collect::<Vec<_>>().join("\n");
+        if let Some(rule) = self
+            .rules()?
+            .into_iter()
+            .find(|rule| rule.contains.iter().all(|needle| prompt.contains(needle.as_str())))
+        {
+            return Ok(rule.response);
+        }
+
+        Err(format!(
+            "No mock fixture for prompt {} in {} and no rule matches it; record one with a real provider and `record_fixtures`",
+            hash,
+            self.dir.display()
+        ))
+    }
+
+    /// Rules from the rules file, if there is one
+    fn rules(&self) -> Result<Vec<MockRule>, String> {
+        let path = self.dir.join(MOCK_RULES_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let data =
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read mock rules {}: {}", path.display(), e))?;
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse mock rules {}: {}", path.display(), e))
+    }
+
+    /// Saves a response as the fixture for its prompt
+    fn record(&self, messages: &[Message], model: &str, response: &str) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create fixtures directory {}: {}", self.dir.display(), e))?;
+
+        let fixture = Fixture {
+            model: model.to_string(),
+            prompt: messages
+                .iter()
+                .map(|message| FixtureMessage {
+                    role: format!("{:?}", message.message_type),
+                    content: message.content.clone(),
+                })
+                .collect(),
+            response: response.to_string(),
+        };
+        let path = self.dir.join(format!("{}.json", Self::prompt_hash(messages)));
+        let data = serde_json::to_string_pretty(&fixture).map_err(|e| e.to_string())?;
+        fs::write(&path, data).map_err(|e| format!("Failed to write fixture {}: {}", path.display(), e))?;
+
+        log::info!("Recorded fixture {}", path.display());
+        Ok(())
+    }
+}
+
+/// Provider that answers from recorded fixtures and rules, without any network access
+pub struct MockProvider {
+    fixtures: FixtureStore,
+    model: String,
+}
+
+impl MockProvider {
+    pub fn new(fixtures_dir: PathBuf, model: Option<String>) -> Self {
+        Self {
+            fixtures: FixtureStore { dir: fixtures_dir },
+            model: model.unwrap_or_else(|| DEFAULT_MOCK_MODEL.to_string()),
+        }
+    }
+}
+
+impl LlmProvider for MockProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Mock
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn llm(&self) -> Box<dyn LLM> {
+        Box::new(MockLlm {
+            fixtures: self.fixtures.clone(),
+        })
+    }
+}
+
+/// Client of the mock provider
+#[derive(Clone)]
+struct MockLlm {
+    fixtures: FixtureStore,
+}
+
+#[async_trait]
+impl LLM for MockLlm {
+    async fn generate(&self, messages: &[Message]) -> Result<GenerateResult, LLMError> {
+        let generation = self.fixtures.lookup(messages).map_err(LLMError::OtherError)?;
+        Ok(GenerateResult {
+            tokens: None,
+            generation,
+        })
+    }
+
+    async fn stream(
+        &self,
+        messages: &[Message],
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError> {
+        let result = self.generate(messages).await?;
+        Ok(single_chunk(result))
+    }
+}
+
+/// Wraps a real provider and saves each of its responses as a fixture for the mock provider
+pub struct RecordingProvider {
+    inner: Arc<dyn LlmProvider>,
+    fixtures: FixtureStore,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, fixtures_dir: PathBuf) -> Self {
+        log::info!("Recording {:?} responses as fixtures in {}", inner.kind(), fixtures_dir.display());
+        Self {
+            inner,
+            fixtures: FixtureStore { dir: fixtures_dir },
+        }
+    }
+}
+
+impl LlmProvider for RecordingProvider {
+    fn kind(&self) -> ProviderKind {
+        self.inner.kind()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn llm(&self) -> Box<dyn LLM> {
+        Box::new(RecordingLlm {
+            inner: self.inner.clone(),
+            fixtures: self.fixtures.clone(),
+        })
+    }
+}
+
+/// Client that forwards to a real provider and records its responses
+#[derive(Clone)]
+struct RecordingLlm {
+    inner: Arc<dyn LlmProvider>,
+    fixtures: FixtureStore,
+}
+
+#[async_trait]
+impl LLM for RecordingLlm {
+    async fn generate(&self, messages: &[Message]) -> Result<GenerateResult, LLMError> {
+        let result = self.inner.llm().generate(messages).await?;
+        // A failed recording shouldn't fail the request it was made for
+        if let Err(e) = self.fixtures.record(messages, self.inner.model(), &result.generation) {
+            log::error!("{}", e);
+        }
+        Ok(result)
+    }
+
+    /// Responses are recorded whole, so they are generated in one call and sent as a single chunk
+    async fn stream(
+        &self,
+        messages: &[Message],
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError> {
+        let result = self.generate(messages).await?;
+        Ok(single_chunk(result))
+    }
+}
+
+/// A stream that yields a whole response as one chunk
+fn single_chunk(result: GenerateResult) -> Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>> {
+    let chunk = StreamData {
+        value: serde_json::Value::String(result.generation.clone()),
+        tokens: result.tokens,
+        content: result.generation,
+    };
+    Box::pin(futures::stream::once(async move { Ok(chunk) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::agent_service::{AgentService, TestGenerationRequest};
+    use crate::services::file_service::FileService;
+    use crate::services::llm_provider::ProviderConfig;
+    use crate::services::test_extraction::extract_test_code;
+    use crate::services::test_runner::run_test_file;
+    use futures::StreamExt;
+    use std::path::Path;
+
+    /// A fresh directory for one test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mock-provider-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn prompt(source: &str) -> Vec<Message> {
+        vec![
+            Message::new_system_message("You write unit tests."),
+            Message::new_human_message(format!("Write tests for:\n{}", source)),
+        ]
+    }
+
+    fn write_rules(dir: &Path, rules: serde_json::Value) {
+        fs::write(dir.join(MOCK_RULES_FILE), rules.to_string()).unwrap();
+    }
+
+    #[test]
+    fn answers_from_the_first_matching_rule() {
+        let dir = temp_dir("rules");
+        write_rules(
+            &dir,
+            serde_json::json!([
+                { "contains": ["fn add", "fn sub"], "response": "both" },
+                { "contains": ["fn add"], "response": "add only" },
+            ]),
+        );
+        let store = FixtureStore { dir };
+
+        assert_eq!(store.lookup(&prompt("fn add() {}\nfn sub() {}")).unwrap(), "both");
+        assert_eq!(store.lookup(&prompt("fn add() {}")).unwrap(), "add only");
+
+        let error = store.lookup(&prompt("fn mul() {}")).unwrap_err();
+        assert!(error.contains(&FixtureStore::prompt_hash(&prompt("fn mul() {}"))), "{}", error);
+    }
+
+    #[test]
+    fn recorded_fixtures_win_over_rules() {
+        let dir = temp_dir("record");
+        write_rules(&dir, serde_json::json!([{ "contains": ["fn add"], "response": "from rule" }]));
+        let store = FixtureStore { dir: dir.clone() };
+
+        store.record(&prompt("fn add() {}"), "claude-test", "from fixture").unwrap();
+
+        assert_eq!(store.lookup(&prompt("fn add() {}")).unwrap(), "from fixture");
+        // Only the exact prompt replays the fixture
+        assert_eq!(store.lookup(&prompt("fn add() { }")).unwrap(), "from rule");
+
+        let path = dir.join(format!("{}.json", FixtureStore::prompt_hash(&prompt("fn add() {}"))));
+        let fixture: Fixture = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(fixture.model, "claude-test");
+        assert_eq!(fixture.prompt.len(), 2);
+    }
+
+    #[test]
+    fn the_message_role_is_part_of_the_prompt_hash() {
+        let as_system = vec![Message::new_system_message("fn add() {}")];
+        let as_human = vec![Message::new_human_message("fn add() {}")];
+        assert_ne!(FixtureStore::prompt_hash(&as_system), FixtureStore::prompt_hash(&as_human));
+    }
+
+    #[test]
+    fn reports_broken_rules_files() {
+        let dir = temp_dir("broken");
+        fs::write(dir.join(MOCK_RULES_FILE), "[{ \"contains\": ").unwrap();
+        let error = FixtureStore { dir }.lookup(&prompt("fn add() {}")).unwrap_err();
+        assert!(error.starts_with("Failed to parse mock rules"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn streams_the_whole_response_as_one_chunk() {
+        let dir = temp_dir("stream");
+        write_rules(&dir, serde_json::json!([{ "contains": ["fn add"], "response": "streamed" }]));
+        let llm = MockProvider::new(dir, None).llm();
+
+        let chunks: Vec<StreamData> = llm
+            .stream(&prompt("fn add() {}"))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].content, "streamed");
+    }
+
+    /// Generates tests for a small crate with the mock provider, writes them and runs them with Cargo
+    #[tokio::test]
+    async fn generated_tests_are_written_and_run() {
+        let project = temp_dir("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[package]\nname = \"answer\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        let source = "pub fn answer() -> u32 {\n    42\n}\n";
+        fs::write(project.join("src/lib.rs"), source).unwrap();
+
+        let fixtures = temp_dir("project-fixtures");
+        write_rules(
+            &fixtures,
+            serde_json::json!([{
+                "contains": ["pub fn answer() -> u32"],
+                "response": "Here are the tests:\n\n```rust\nuse answer::answer;\n\n#[test]\nfn answers_42() {\n    assert_eq!(answer(), 42);\n}\n```\n",
+            }]),
+        );
+
+        let agent = AgentService::new();
+        agent
+            .initialize(ProviderConfig {
+                kind: ProviderKind::Mock,
+                api_key: String::new(),
+                model: None,
+                base_url: None,
+                fixtures_dir: Some(fixtures.to_string_lossy().to_string()),
+                record_fixtures: false,
+            })
+            .await
+            .unwrap();
+
+        let response = agent
+            .generate_tests(TestGenerationRequest {
+                code: source.to_string(),
+                language: "rust".to_string(),
+                project_dir: Some(project.clone()),
+                source_path: Some("src/lib.rs".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(!response.cached);
+
+        let test_code = extract_test_code(&response.content, "rust").unwrap();
+        let test_file = FileService::new().write_test_file(&project, "src/lib.rs", &test_code).unwrap();
+        assert_eq!(test_file, "tests/lib.rs");
+
+        let outcome = run_test_file(&project, &test_file, "rust", None).await.unwrap();
+        assert!(outcome.passed, "{}\n{}", outcome.command, outcome.output);
+        assert!(outcome.output.contains("answers_42"), "{}", outcome.output);
+    }
+}
@@ -5,6 +5,29 @@ pub mod auth_service;
 pub mod file_service;
 pub mod file_watcher_service;
 pub mod agent_service;
+pub mod agent_events;
+pub mod llm_provider;
+pub mod mock_provider;
+pub mod llm_retry;
+pub mod test_extraction;
+pub mod structured_output;
+pub mod test_runner;
+pub mod context_collector;
+pub mod test_merge;
+pub mod prompt_registry;
+pub mod source_chunker;
+pub mod usage_ledger;
+pub mod response_cache;
+pub mod chat_sessions;
+pub mod syntax_validation;
+pub mod symbol_locator;
+pub mod batch_generation;
+pub mod secret_store;
+pub mod framework_detection;
+pub mod coverage;
+pub mod test_review;
+pub mod redaction;
+pub mod agent_tools;
 
 pub use auth_service::AuthService;
 pub use file_service::FileService;
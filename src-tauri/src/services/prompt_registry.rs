@@ -0,0 +1,507 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Folder inside a project that holds project-local prompt templates
+pub const PROJECT_PROMPT_DIR: &str = ".vibede/prompts";
+
+/// File extension of templates stored on disk
+const TEMPLATE_EXTENSION: &str = "txt";
+
+/// System prompt for test generation
+pub const TEST_SYSTEM_TEMPLATE: &str = "test_system";
+/// User message for test generation
+pub const TEST_USER_TEMPLATE: &str = "test_user";
+/// Language/framework specific guidance inserted into the test generation system prompt
+pub const TEST_GUIDANCE_TEMPLATE: &str = "test_guidance";
+/// Response format appended to the test generation system prompt when a structured result is requested
+pub const TEST_JSON_OUTPUT_TEMPLATE: &str = "test_output_json";
+/// System prompt for reviewing an existing test file
+pub const TEST_REVIEW_TEMPLATE: &str = "test_review";
+/// Tool instructions appended to the system prompt when the agent may call project tools
+pub const AGENT_TOOLS_TEMPLATE: &str = "agent_tools";
+
+/// Templates shipped with the app, used when no override exists on disk
+///
+/// Keys are `name`, `name.language` or `name.language.framework`; the most specific
+/// match for a request wins.
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    (
+        TEST_SYSTEM_TEMPLATE,
+        r#"You are a specialized test generation agent. Your task is to analyze the code provided and generate comprehensive test cases.
+
+Follow these guidelines:
+1. Create thorough test cases covering all functionality in the code
+2. Include tests for edge cases and error handling
+3. Ensure the tests are well-organized and commented
+4. Use {{language}}{{#framework}} and the {{framework}} testing framework{{#framework_version}} (version {{framework_version}}){{/framework_version}}{{/framework}}{{^framework}} best practices for testing{{/framework}}
+5. {{guidance}}{{#existing_tests}}
+6. Tests already exist for this code. Generate ONLY test cases for behavior the existing tests do not cover. Do not repeat or rewrite existing tests; respond with just the new test cases and the imports they need, in the same style and structure as the existing tests{{/existing_tests}}
+
+Respond ONLY with the generated test code, without explanations or commentary outside the code."#,
+    ),
+    (
+        TEST_USER_TEMPLATE,
+        r#"{{#context}}These are the signatures of local types and functions the code depends on. Use them to construct real values instead of guessing:
+
+```
+{{context}}
+```
+
+{{/context}}{{#part}}The source file is too large to send at once, so this is part {{part}} of it. The file's header (package, imports and module-level declarations) is repeated at the top; generate tests only for the items that follow it. Include the imports the tests need.
+
+{{/part}}{{#symbol}}The code to test is `{{symbol}}` from the source file below, which is shown for context so the tests can use its imports, types and helpers. Generate tests only for `{{symbol}}`, not for anything else in the file:
+
+```
+{{file}}
+```
+
+{{/symbol}}Here is the code to generate tests for:
+
+```
+{{code}}
+```
+
+{{#existing_tests}}These tests already exist:
+
+```
+{{existing_tests}}
+```
+
+{{/existing_tests}}{{#coverage}}A coverage report of the existing tests shows what they miss. Line numbers refer to the whole source file. Focus the new tests on running these lines and taking these branches rather than on behavior that is already covered:
+
+{{coverage}}
+
+{{/coverage}}Generate comprehensive tests for this code."#,
+    ),
+    (
+        TEST_GUIDANCE_TEMPLATE,
+        "Follow best practices for the language's standard testing framework.",
+    ),
+    (
+        TEST_JSON_OUTPUT_TEMPLATE,
+        r#"Instead of plain test code, respond ONLY with a JSON object in this format, without a code fence or any text around it:
+
+{
+  "test_path": "path of the test file relative to the project root, following the conventions of {{language}}{{#framework}} and {{framework}}{{/framework}}",
+  "content": "the complete test file",
+  "covered_functions": ["names of the functions and methods the tests exercise"],
+  "test_cases": [{"name": "name of the test", "description": "what the test checks"}],
+  "dev_dependencies": ["packages the tests need beyond the testing framework itself, or an empty list"]
+}{{#source_path}}
+
+The code is from {{source_path}}, relative to the project root.{{/source_path}}"#,
+    ),
+    (
+        TEST_REVIEW_TEMPLATE,
+        r#"You are a specialized test review agent. Your task is to review an existing test file against the source code it tests and point out how to make it better. The tests use {{language}}{{#framework}} and the {{framework}} testing framework{{/framework}}.
+
+Look for:
+1. Untested branches: conditions, error paths and early returns in the source code that no test takes
+2. Weak assertions: tests that would still pass if the code were wrong, e.g. only checking that a value is defined, that a function was called, or a snapshot of everything
+3. Brittle mocks: mocks of internal details or call order instead of behavior, and mocks that let the test pass without running the code under test
+4. Missing cases: inputs and scenarios worth adding, such as boundaries, empty values and failures
+
+Both files are shown with line numbers. Refer to lines by those numbers: lines of the source file for untested branches and suggested cases, lines of the test file for weak assertions and brittle mocks. Only report real problems; an empty list is fine.
+
+Respond ONLY with a JSON object in this format, without a code fence or any text around it:
+
+{
+  "summary": "one or two sentences on the overall quality of the tests",
+  "untested_branches": [{"line": 0, "issue": "the branch no test takes", "suggestion": "how to test it"}],
+  "weak_assertions": [{"line": 0, "issue": "why the assertion is weak", "suggestion": "a stronger assertion"}],
+  "brittle_mocks": [{"line": 0, "issue": "why the mock is brittle", "suggestion": "a sturdier alternative"}],
+  "suggested_cases": [{"name": "name of the new test", "description": "what it checks", "line": 0}]
+}"#,
+    ),
+    (
+        AGENT_TOOLS_TEMPLATE,
+        r#"Before answering, you can look around the project with tools, e.g. to read the code the source file imports, see how existing tests are set up or find helpers and fixtures to reuse. All paths are relative to the project root.
+
+Available tools:
+
+{{tools}}
+
+To call a tool, respond with ONLY a JSON object like {"tool": "read_file", "input": {"path": "src/utils.ts"}}, without a code fence or any text around it. Call one tool per response; its result is sent back to you. You can make up to {{max_steps}} tool calls. Once you know enough, respond with your final answer as described above instead of a tool call."#,
+    ),
+    (
+        "test_guidance.javascript",
+        "Use the testing framework the project already uses, or Jest if it has none. Include proper imports and follow that framework's conventions for structuring tests.",
+    ),
+    (
+        "test_guidance.typescript",
+        "Use the testing framework the project already uses, or Jest if it has none. Include proper imports and follow that framework's conventions for structuring tests.",
+    ),
+    (
+        "test_guidance.javascript.jest",
+        "Use Jest for testing. Include proper imports and describe/it blocks.",
+    ),
+    (
+        "test_guidance.typescript.jest",
+        "Use Jest for testing. Include proper imports and describe/it blocks.",
+    ),
+    (
+        "test_guidance.javascript.vitest",
+        "Use Vitest for testing. Import describe, it, expect and vi from 'vitest' and use vi.fn/vi.mock instead of Jest's mocks.",
+    ),
+    (
+        "test_guidance.typescript.vitest",
+        "Use Vitest for testing. Import describe, it, expect and vi from 'vitest' and use vi.fn/vi.mock instead of Jest's mocks.",
+    ),
+    (
+        "test_guidance.javascript.mocha",
+        "Use Mocha for testing with describe/it blocks. Use the assertion library the project already uses, or node:assert.",
+    ),
+    (
+        "test_guidance.typescript.mocha",
+        "Use Mocha for testing with describe/it blocks. Use the assertion library the project already uses, or node:assert.",
+    ),
+    (
+        "test_guidance.javascript.ava",
+        "Use AVA for testing. Import test from 'ava' and write each case as test('name', t => ...) with t.is, t.deepEqual and t.throws assertions; AVA has no describe blocks or global expect.",
+    ),
+    (
+        "test_guidance.typescript.ava",
+        "Use AVA for testing. Import test from 'ava' and write each case as test('name', t => ...) with t.is, t.deepEqual and t.throws assertions; AVA has no describe blocks or global expect.",
+    ),
+    (
+        "test_guidance.python",
+        "Use pytest for testing. Include proper imports and test_ prefixed functions.",
+    ),
+    (
+        "test_guidance.rust",
+        "Use Rust's built-in testing framework with #[test] annotations. The tests are an integration test file in the crate's tests/ directory, so import the code through the crate's name (e.g. `use my_crate::module::Item;`), only use its public API and don't wrap the tests in a `mod tests` block.",
+    ),
+];
+
+/// Where a template was loaded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptSource {
+    /// Shipped with the app
+    Builtin,
+    /// The `prompts` folder in the app data directory
+    App,
+    /// The project's `.vibede/prompts` folder
+    Project,
+}
+
+impl PromptSource {
+    /// Parses the scope a template is saved to
+    pub fn parse_scope(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "app" => Ok(PromptSource::App),
+            "project" => Ok(PromptSource::Project),
+            other => Err(format!("Invalid prompt template scope: {} (expected app or project)", other)),
+        }
+    }
+}
+
+/// A prompt template and where it came from
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTemplate {
+    /// Full key, e.g. `test_guidance.python.pytest`
+    pub key: String,
+    /// Template name without the language and framework parts
+    pub name: String,
+    /// Language the template applies to, if it is language specific
+    pub language: Option<String>,
+    /// Framework the template applies to, if it is framework specific
+    pub framework: Option<String>,
+    /// Where the effective version of the template was loaded from
+    pub source: PromptSource,
+    /// Path of the template file, for templates loaded from disk
+    pub path: Option<String>,
+    /// The template text
+    pub content: String,
+}
+
+/// Loads prompt templates from the app data directory and project-local folders,
+/// falling back to the built-in defaults
+///
+/// Templates are read from disk on every lookup so edits apply without a restart.
+/// Project templates take precedence over app templates, which take precedence over
+/// the built-in ones.
+#[derive(Clone, Default)]
+pub struct PromptRegistry {
+    /// The app-wide template folder, set once the app data directory is known
+    app_dir: Arc<RwLock<Option<PathBuf>>>,
+}
+
+impl PromptRegistry {
+    /// Create a registry with no app-wide template folder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the app-wide template folder
+    pub fn set_app_dir(&self, dir: PathBuf) {
+        if let Ok(mut app_dir) = self.app_dir.write() {
+            log::info!("Loading prompt templates from {}", dir.display());
+            *app_dir = Some(dir);
+        }
+    }
+
+    /// Find the template to use for a name, language and framework
+    ///
+    /// The most specific key wins (`name.language.framework`, then `name.language`, then
+    /// `name`); for the same key, project templates win over app and built-in ones.
+    pub fn resolve(
+        &self,
+        name: &str,
+        language: &str,
+        framework: Option<&str>,
+        project_dir: Option<&Path>,
+    ) -> Result<PromptTemplate, String> {
+        let language = normalize(language);
+        let framework = framework.map(normalize).filter(|f| !f.is_empty());
+
+        let mut candidates = Vec::new();
+        if let Some(framework) = &framework {
+            candidates.push(format!("{}.{}.{}", name, language, framework));
+        }
+        if !language.is_empty() {
+            candidates.push(format!("{}.{}", name, language));
+        }
+        candidates.push(name.to_string());
+
+        for key in candidates {
+            if let Some(template) = self.lookup(&key, project_dir) {
+                return Ok(template);
+            }
+        }
+
+        Err(format!("No prompt template found for {}", name))
+    }
+
+    /// Read the effective template for an exact key
+    pub fn read(&self, key: &str, project_dir: Option<&Path>) -> Result<PromptTemplate, String> {
+        let key = validate_key(key)?;
+        self.lookup(&key, project_dir)
+            .ok_or_else(|| format!("Prompt template not found: {}", key))
+    }
+
+    /// List the effective template for every known key
+    pub fn list(&self, project_dir: Option<&Path>) -> Vec<PromptTemplate> {
+        let mut templates: BTreeMap<String, PromptTemplate> = BUILTIN_TEMPLATES
+            .iter()
+            .map(|(key, content)| (key.to_string(), template(key, PromptSource::Builtin, None, content.to_string())))
+            .collect();
+
+        // Later sources override earlier ones
+        for (source, dir) in self.directories(project_dir).into_iter().rev() {
+            for (key, path) in list_template_files(&dir) {
+                match fs::read_to_string(&path) {
+                    Ok(content) => {
+                        templates.insert(key.clone(), template(&key, source, Some(&path), content));
+                    }
+                    Err(e) => log::warn!("Failed to read prompt template {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        templates.into_values().collect()
+    }
+
+    /// Save a template to the app-wide or project folder, overriding the built-in version
+    pub fn save(
+        &self,
+        key: &str,
+        content: &str,
+        scope: PromptSource,
+        project_dir: Option<&Path>,
+    ) -> Result<PromptTemplate, String> {
+        let key = validate_key(key)?;
+        let dir = match scope {
+            PromptSource::Project => project_dir
+                .map(|dir| dir.join(PROJECT_PROMPT_DIR))
+                .ok_or_else(|| "A project directory is required to save a project prompt template".to_string())?,
+            PromptSource::App => self
+                .app_dir()
+                .ok_or_else(|| "The app prompt template directory is not available".to_string())?,
+            PromptSource::Builtin => return Err("Built-in prompt templates cannot be modified".to_string()),
+        };
+
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create prompt template directory {}: {}", dir.display(), e))?;
+
+        let path = dir.join(format!("{}.{}", key, TEMPLATE_EXTENSION));
+        fs::write(&path, content)
+            .map_err(|e| format!("Failed to write prompt template {}: {}", path.display(), e))?;
+
+        log::info!("Saved prompt template {} to {}", key, path.display());
+        Ok(template(&key, scope, Some(&path), content.to_string()))
+    }
+
+    /// Find a template by exact key, checking the project folder, app folder and built-ins in turn
+    fn lookup(&self, key: &str, project_dir: Option<&Path>) -> Option<PromptTemplate> {
+        for (source, dir) in self.directories(project_dir) {
+            let path = dir.join(format!("{}.{}", key, TEMPLATE_EXTENSION));
+            if !path.is_file() {
+                continue;
+            }
+            match fs::read_to_string(&path) {
+                Ok(content) => return Some(template(key, source, Some(&path), content)),
+                Err(e) => log::warn!("Failed to read prompt template {}: {}", path.display(), e),
+            }
+        }
+
+        BUILTIN_TEMPLATES
+            .iter()
+            .find(|(builtin_key, _)| *builtin_key == key)
+            .map(|(_, content)| template(key, PromptSource::Builtin, None, content.to_string()))
+    }
+
+    /// Template folders to search, highest precedence first
+    fn directories(&self, project_dir: Option<&Path>) -> Vec<(PromptSource, PathBuf)> {
+        let mut dirs = Vec::new();
+        if let Some(project_dir) = project_dir {
+            dirs.push((PromptSource::Project, project_dir.join(PROJECT_PROMPT_DIR)));
+        }
+        if let Some(app_dir) = self.app_dir() {
+            dirs.push((PromptSource::App, app_dir));
+        }
+        dirs
+    }
+
+    fn app_dir(&self) -> Option<PathBuf> {
+        self.app_dir.read().ok().and_then(|dir| dir.clone())
+    }
+}
+
+/// Render a template, substituting `{{variable}}` placeholders
+///
+/// `{{#variable}}...{{/variable}}` is only kept when the variable is set and not empty, and
+/// `{{^variable}}...{{/variable}}` only when it is missing or empty. Values are inserted as-is
+/// and never re-scanned, so code containing braces is safe. Unknown placeholders render empty.
+pub fn render_template(template: &str, variables: &HashMap<&str, String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("}}") else {
+            // Unclosed placeholder; keep the remaining text verbatim
+            output.push_str(&rest[start..]);
+            return output;
+        };
+
+        let tag = after_open[..end].trim();
+        rest = &after_open[end + 2..];
+
+        if let Some(name) = tag.strip_prefix('#').or_else(|| tag.strip_prefix('^')) {
+            let name = name.trim();
+            let closing = format!("{{{{/{}}}}}", name);
+            let (section, remainder) = match rest.find(&closing) {
+                Some(close) => (&rest[..close], &rest[close + closing.len()..]),
+                None => (rest, ""),
+            };
+            rest = remainder;
+
+            let is_set = variables.get(name).is_some_and(|value| !value.trim().is_empty());
+            if is_set == tag.starts_with('#') {
+                output.push_str(&render_template(section, variables));
+            }
+        } else if tag.starts_with('/') {
+            // Stray closing tag
+        } else {
+            match variables.get(tag) {
+                Some(value) => output.push_str(value),
+                None => log::warn!("Prompt template uses unknown variable: {}", tag),
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn template(key: &str, source: PromptSource, path: Option<&Path>, content: String) -> PromptTemplate {
+    let mut parts = key.splitn(3, '.');
+    PromptTemplate {
+        key: key.to_string(),
+        name: parts.next().unwrap_or_default().to_string(),
+        language: parts.next().map(|s| s.to_string()),
+        framework: parts.next().map(|s| s.to_string()),
+        source,
+        path: path.map(|p| p.to_string_lossy().to_string()),
+        content,
+    }
+}
+
+/// Template files in a folder, keyed by template key
+fn list_template_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == TEMPLATE_EXTENSION))
+        .filter_map(|path| {
+            let key = path.file_stem()?.to_str()?.to_string();
+            validate_key(&key).ok().map(|key| (key, path))
+        })
+        .collect()
+}
+
+/// Lowercases a language or framework name for use in a key
+fn normalize(value: &str) -> String {
+    value.trim().to_lowercase().replace(' ', "-")
+}
+
+/// Checks that a key is safe to use as a file name
+fn validate_key(key: &str) -> Result<String, String> {
+    let key = key.trim().to_lowercase();
+    let valid = !key.is_empty()
+        && !key.starts_with('.')
+        && !key.ends_with('.')
+        && !key.contains("..")
+        && key.split('.').count() <= 3
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+' | '#'));
+
+    if valid {
+        Ok(key)
+    } else {
+        Err(format!("Invalid prompt template key: {}", key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::framework_detection::JS_FRAMEWORKS;
+
+    #[test]
+    fn has_guidance_for_every_detected_js_framework() {
+        let registry = PromptRegistry::new();
+        for language in ["javascript", "typescript"] {
+            for framework in JS_FRAMEWORKS {
+                let guidance = registry
+                    .resolve(TEST_GUIDANCE_TEMPLATE, language, Some(framework), None)
+                    .unwrap();
+                assert_eq!(guidance.key, format!("{}.{}.{}", TEST_GUIDANCE_TEMPLATE, language, framework));
+                assert!(guidance.content.to_lowercase().contains(framework), "{}", guidance.key);
+            }
+        }
+    }
+
+    #[test]
+    fn falls_back_to_less_specific_guidance() {
+        let registry = PromptRegistry::new();
+
+        let guidance = registry.resolve(TEST_GUIDANCE_TEMPLATE, "JavaScript", Some("jasmine"), None).unwrap();
+        assert_eq!(guidance.key, "test_guidance.javascript");
+        assert!(guidance.content.contains("the project already uses"));
+
+        let guidance = registry.resolve(TEST_GUIDANCE_TEMPLATE, "python", Some("pytest"), None).unwrap();
+        assert_eq!(guidance.key, "test_guidance.python");
+
+        let guidance = registry.resolve(TEST_GUIDANCE_TEMPLATE, "go", None, None).unwrap();
+        assert_eq!((guidance.key.as_str(), guidance.source), (TEST_GUIDANCE_TEMPLATE, PromptSource::Builtin));
+    }
+}
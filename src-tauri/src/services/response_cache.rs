@@ -0,0 +1,359 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Folder in the app data directory that holds cached responses
+pub const RESPONSE_CACHE_DIR: &str = "cache/responses";
+
+/// How long a cached response is used unless configured otherwise
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Total size of the cache, in bytes, unless configured otherwise
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+/// File extension of cache entries
+const ENTRY_EXTENSION: &str = "json";
+
+/// A cached LLM response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// When the response was stored, in seconds since the Unix epoch
+    created_at: u64,
+    /// Model that produced the response
+    model: String,
+    content: String,
+}
+
+/// How long entries live and how large the cache may grow
+#[derive(Debug, Clone, Copy)]
+pub struct CacheSettings {
+    /// Entries older than this are ignored
+    pub ttl: Duration,
+    /// Total size of all entries before the oldest are evicted
+    pub max_bytes: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_CACHE_TTL,
+            max_bytes: DEFAULT_CACHE_MAX_BYTES,
+        }
+    }
+}
+
+/// Number and total size of the cached responses
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    /// Number of cached responses, including expired ones not yet removed
+    pub entries: usize,
+    /// Total size of the cache entries
+    pub bytes: u64,
+    /// Configured time to live, in seconds
+    pub ttl_secs: u64,
+    /// Configured size limit, in bytes
+    pub max_bytes: u64,
+}
+
+/// Persistent cache of LLM responses, keyed by a hash of everything that determines the response
+///
+/// Each entry is a JSON file named after its key. Expired entries are ignored and removed
+/// when read; the oldest entries are evicted once the cache grows past its size limit.
+#[derive(Clone, Default)]
+pub struct ResponseCache {
+    /// The cache folder, set once the app data directory is known
+    dir: Arc<RwLock<Option<PathBuf>>>,
+    settings: Arc<RwLock<CacheSettings>>,
+}
+
+impl ResponseCache {
+    /// Create a cache that stores nothing until a directory is set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the folder cache entries are stored in
+    pub fn set_dir(&self, dir: PathBuf) {
+        if let Ok(mut cache_dir) = self.dir.write() {
+            log::info!("Caching agent responses in {}", dir.display());
+            *cache_dir = Some(dir);
+        }
+    }
+
+    /// Change how long entries live and how large the cache may grow
+    pub fn configure(&self, settings: CacheSettings) {
+        if let Ok(mut current) = self.settings.write() {
+            *current = settings;
+        }
+        self.evict();
+    }
+
+    /// Hash the parts that determine a response into a cache key
+    /// Parts are length-prefixed so that moving text between them changes the key
+    pub fn key(parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher.finalize().iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+    }
+
+    /// Look up a response, ignoring (and removing) it if it has expired
+    pub fn get(&self, key: &str) -> Option<String> {
+        let path = self.entry_path(key)?;
+        let data = fs::read_to_string(&path).ok()?;
+
+        let entry: CacheEntry = match serde_json::from_str(&data) {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("Removing unreadable cache entry {}: {}", path.display(), e);
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+        };
+
+        if now_secs().saturating_sub(entry.created_at) > self.settings().ttl.as_secs() {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+
+        log::info!("Using cached {} response {}", entry.model, key);
+        Some(entry.content)
+    }
+
+    /// Store a response, evicting the oldest entries if the cache grows too large
+    pub fn put(&self, key: &str, model: &str, content: &str) {
+        let Some(path) = self.entry_path(key) else {
+            return;
+        };
+
+        let entry = CacheEntry {
+            created_at: now_secs(),
+            model: model.to_string(),
+            content: content.to_string(),
+        };
+        let result = serde_json::to_string(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|data| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                fs::write(&path, data).map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(()) => self.evict(),
+            Err(e) => log::error!("Failed to cache response in {}: {}", path.display(), e),
+        }
+    }
+
+    /// Remove every cached response
+    /// Returns how many entries were removed
+    pub fn clear(&self) -> Result<usize, String> {
+        let Some(dir) = self.dir() else {
+            return Ok(0);
+        };
+
+        let mut removed = 0;
+        for (path, _, _) in list_entries(&dir) {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove cache entry {}: {}", path.display(), e))?;
+            removed += 1;
+        }
+
+        log::info!("Cleared {} cached responses", removed);
+        Ok(removed)
+    }
+
+    /// Number and total size of the cached responses
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.dir().map(|dir| list_entries(&dir)).unwrap_or_default();
+        let settings = self.settings();
+        CacheStats {
+            entries: entries.len(),
+            bytes: entries.iter().map(|(_, size, _)| size).sum(),
+            ttl_secs: settings.ttl.as_secs(),
+            max_bytes: settings.max_bytes,
+        }
+    }
+
+    /// Delete the oldest entries until the cache fits in its size limit
+    fn evict(&self) {
+        let Some(dir) = self.dir() else {
+            return;
+        };
+        let max_bytes = self.settings().max_bytes;
+
+        let mut entries = list_entries(&dir);
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        if total <= max_bytes {
+            return;
+        }
+
+        entries.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => total = total.saturating_sub(size),
+                Err(e) => log::warn!("Failed to evict cache entry {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> Option<PathBuf> {
+        self.dir().map(|dir| dir.join(format!("{}.{}", key, ENTRY_EXTENSION)))
+    }
+
+    fn dir(&self) -> Option<PathBuf> {
+        self.dir.read().ok().and_then(|dir| dir.clone())
+    }
+
+    fn settings(&self) -> CacheSettings {
+        self.settings.read().map(|settings| *settings).unwrap_or_default()
+    }
+}
+
+/// Cache entry files in a folder with their size and modification time
+fn list_entries(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == ENTRY_EXTENSION))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            Some((entry.path(), metadata.len(), modified))
+        })
+        .collect()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cache in a fresh directory
+    fn cache(name: &str) -> (ResponseCache, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("response-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = ResponseCache::new();
+        cache.set_dir(dir.clone());
+        (cache, dir)
+    }
+
+    /// Pretend an entry was last written `secs_ago` seconds ago
+    fn age(cache: &ResponseCache, key: &str, secs_ago: u64) {
+        let file = fs::File::options().write(true).open(cache.entry_path(key).unwrap()).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(secs_ago)).unwrap();
+    }
+
+    #[test]
+    fn returns_stored_responses() {
+        let (cache, _) = cache("hit");
+        let key = ResponseCache::key(&["Anthropic", "claude", "system", "user"]);
+
+        assert_eq!(cache.get(&key), None);
+        cache.put(&key, "claude", "#[test]\nfn works() {}\n");
+        assert_eq!(cache.get(&key).as_deref(), Some("#[test]\nfn works() {}\n"));
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn stores_nothing_without_a_directory() {
+        let cache = ResponseCache::new();
+        cache.put("key", "model", "content");
+        assert_eq!(cache.get("key"), None);
+        assert_eq!(cache.clear().unwrap(), 0);
+    }
+
+    #[test]
+    fn keys_differ_by_provider_model_and_prompt() {
+        let key = ResponseCache::key(&["Anthropic", "claude-3-7-sonnet", "system", "user"]);
+
+        assert_eq!(key, ResponseCache::key(&["Anthropic", "claude-3-7-sonnet", "system", "user"]));
+        assert_ne!(key, ResponseCache::key(&["OpenAi", "claude-3-7-sonnet", "system", "user"]));
+        assert_ne!(key, ResponseCache::key(&["Anthropic", "claude-3-5-haiku", "system", "user"]));
+        assert_ne!(key, ResponseCache::key(&["Anthropic", "claude-3-7-sonnet", "system", "user "]));
+        // Moving text from one part to the next changes the key
+        assert_ne!(key, ResponseCache::key(&["Anthropic", "claude-3-7-sonnet", "systemuser", ""]));
+
+        let (cache, _) = cache("provider-keys");
+        cache.put(&key, "claude-3-7-sonnet", "from claude");
+        assert_eq!(cache.get(&ResponseCache::key(&["OpenAi", "gpt-4o-mini", "system", "user"])), None);
+    }
+
+    #[test]
+    fn drops_expired_entries() {
+        let (cache, _) = cache("ttl");
+        cache.configure(CacheSettings { ttl: Duration::from_secs(60), ..CacheSettings::default() });
+
+        let entry = CacheEntry {
+            created_at: now_secs() - 61,
+            model: "gpt-4o-mini".to_string(),
+            content: "stale".to_string(),
+        };
+        let path = cache.entry_path("stale").unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, serde_json::to_string(&entry).unwrap()).unwrap();
+
+        assert_eq!(cache.get("stale"), None);
+        assert!(!path.exists());
+
+        cache.put("fresh", "gpt-4o-mini", "fresh");
+        assert_eq!(cache.get("fresh").as_deref(), Some("fresh"));
+    }
+
+    #[test]
+    fn evicts_the_oldest_entries_first() {
+        let (cache, dir) = cache("eviction");
+        for (key, secs_ago) in [("newest", 10), ("oldest", 30), ("middle", 20)] {
+            cache.put(key, "model", &"x".repeat(100));
+            age(&cache, key, secs_ago);
+        }
+        let entry_size = fs::metadata(cache.entry_path("newest").unwrap()).unwrap().len();
+
+        // Room for two entries
+        cache.configure(CacheSettings { max_bytes: entry_size * 2, ..CacheSettings::default() });
+        assert_eq!(cache.get("oldest"), None);
+        assert!(cache.get("middle").is_some());
+        assert!(cache.get("newest").is_some());
+
+        // Storing another one evicts the next oldest
+        cache.put("latest", "model", &"x".repeat(100));
+        assert_eq!(cache.get("middle"), None);
+        assert_eq!(list_entries(&dir).len(), 2);
+        assert!(cache.stats().bytes <= entry_size * 2);
+    }
+
+    #[test]
+    fn removes_unreadable_entries_and_clears() {
+        let (cache, _) = cache("clear");
+        cache.put("good", "model", "content");
+        let broken = cache.entry_path("broken").unwrap();
+        fs::write(&broken, "not json").unwrap();
+
+        assert_eq!(cache.get("broken"), None);
+        assert!(!broken.exists());
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.get("good"), None);
+    }
+}
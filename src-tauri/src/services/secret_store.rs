@@ -0,0 +1,614 @@
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rand::RngCore;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::services::llm_provider::{ProviderConfig, ProviderKind};
+
+/// File in the app data directory holding the encrypted secrets
+pub const SECRETS_FILE: &str = "secrets.enc";
+
+/// File in the app data directory holding the key the secrets are encrypted with
+pub const SECRETS_KEY_FILE: &str = "secrets.key";
+
+/// File in the app data directory holding the settings of the provider last used
+pub const PROVIDER_SETTINGS_FILE: &str = "provider_settings.json";
+
+/// Keychain entry read to check that the keychain works; it is never written
+const KEYCHAIN_PROBE: &str = "probe";
+
+/// Length of an AES-256-GCM key
+const KEY_LEN: usize = 32;
+
+/// Length of an AES-GCM nonce
+const NONCE_LEN: usize = 12;
+
+/// Every provider a key can be stored for
+const ALL_PROVIDERS: &[ProviderKind] = &[
+    ProviderKind::Anthropic,
+    ProviderKind::OpenAi,
+    ProviderKind::OpenAiCompatible,
+    ProviderKind::Mock,
+];
+
+/// Providers tried, in order, when initializing the agent from a stored key at startup and
+/// there are no stored provider settings
+const STARTUP_PROVIDERS: &[ProviderKind] = &[ProviderKind::Anthropic, ProviderKind::OpenAi];
+
+/// Somewhere secrets can be kept, e.g. an encrypted file or the OS keychain
+pub trait SecretBackend: Send + Sync {
+    fn get(&self, name: &str) -> Result<Option<String>, String>;
+
+    fn set(&self, name: &str, value: &str) -> Result<(), String>;
+
+    /// Returns false if there was no secret with that name
+    fn delete(&self, name: &str) -> Result<bool, String>;
+
+    /// Removes every secret in `names`, including ones that can no longer be read
+    fn reset(&self, names: &[String]) -> Result<(), String> {
+        for name in names {
+            self.delete(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where API keys are kept: the OS keychain where there is a usable one, otherwise an
+/// encrypted file in `data_dir`
+///
+/// Secrets left in the encrypted file from before the keychain was usable are moved into it.
+pub fn open_backend(service: &str, data_dir: PathBuf) -> Arc<dyn SecretBackend> {
+    let file = EncryptedFileBackend::new(data_dir);
+    let keychain = match KeychainBackend::open(service) {
+        Ok(keychain) => keychain,
+        Err(e) => {
+            log::warn!("No usable OS keychain, keeping API keys in an encrypted file: {}", e);
+            return Arc::new(file);
+        }
+    };
+    if let Err(e) = file.move_into(&keychain) {
+        log::warn!("Failed to move stored API keys into the OS keychain: {}", e);
+    }
+    Arc::new(keychain)
+}
+
+/// Keeps secrets in the OS keychain: the macOS Keychain, the Windows Credential Manager or
+/// the Secret Service on Linux
+pub struct KeychainBackend {
+    /// Service the entries are stored under
+    service: String,
+}
+
+impl KeychainBackend {
+    /// The keychain, failing if the OS has none the app can use
+    pub fn open(service: &str) -> Result<Self, String> {
+        let backend = Self {
+            service: service.to_string(),
+        };
+        // Looking up a missing entry only succeeds if the keychain itself works
+        backend.get(KEYCHAIN_PROBE)?;
+        Ok(backend)
+    }
+
+    fn entry(&self, name: &str) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(&self.service, name).map_err(|e| format!("Failed to open keychain entry {}: {}", name, e))
+    }
+}
+
+impl SecretBackend for KeychainBackend {
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        match self.entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("Failed to read {} from the keychain: {}", name, e)),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        self.entry(name)?
+            .set_password(value)
+            .map_err(|e| format!("Failed to store {} in the keychain: {}", name, e))
+    }
+
+    fn delete(&self, name: &str) -> Result<bool, String> {
+        match self.entry(name)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(format!("Failed to delete {} from the keychain: {}", name, e)),
+        }
+    }
+}
+
+/// Keeps secrets in an AES-256-GCM encrypted file, where there is no usable OS keychain
+///
+/// The encryption key is generated on first use and kept in a separate file that only the
+/// current user can read. This keeps keys out of plain-text files and out of the webview's
+/// storage, but anyone who can read both files can decrypt them.
+pub struct EncryptedFileBackend {
+    dir: PathBuf,
+    /// Serializes read-modify-write updates of the secrets file
+    lock: Mutex<()>,
+}
+
+impl EncryptedFileBackend {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read_all(&self) -> Result<BTreeMap<String, String>, String> {
+        let path = self.dir.join(SECRETS_FILE);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let encoded = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let data = STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?;
+        if data.len() < NONCE_LEN {
+            return Err(format!("{} is corrupted", path.display()));
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| format!("Failed to decrypt {}; the key file may have changed", path.display()))?;
+        serde_json::from_slice(&plaintext).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    fn write_all(&self, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+        let plaintext = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher()?
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| "Failed to encrypt secrets".to_string())?;
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        write_private(&self.dir.join(SECRETS_FILE), STANDARD.encode(data).as_bytes())
+    }
+
+    /// The cipher for the stored key, generating the key if there are no secrets yet
+    ///
+    /// A missing key is never replaced while secrets encrypted with it exist; they would
+    /// silently become unreadable.
+    fn cipher(&self) -> Result<Aes256Gcm, String> {
+        let path = self.dir.join(SECRETS_KEY_FILE);
+        let key = if path.exists() {
+            let key = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if key.len() != KEY_LEN {
+                return Err(format!("{} is corrupted", path.display()));
+            }
+            key
+        } else if self.dir.join(SECRETS_FILE).exists() {
+            return Err(format!("{} is missing, so the stored secrets can't be decrypted", path.display()));
+        } else {
+            let mut key = vec![0u8; KEY_LEN];
+            rand::thread_rng().fill_bytes(&mut key);
+            write_private(&path, &key)?;
+            key
+        };
+
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+    }
+
+    /// Deletes the secrets and their key
+    fn remove_files(&self) -> Result<(), String> {
+        for name in [SECRETS_FILE, SECRETS_KEY_FILE] {
+            let path = self.dir.join(name);
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Failed to delete {}: {}", path.display(), e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves every secret into `other`, then deletes the files; nothing is deleted if a move fails
+    fn move_into(&self, other: &dyn SecretBackend) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        if !self.dir.join(SECRETS_FILE).exists() {
+            return Ok(());
+        }
+
+        let secrets = self.read_all()?;
+        for (name, value) in &secrets {
+            other.set(name, value)?;
+        }
+        self.remove_files()?;
+        log::info!("Moved {} stored secret(s) out of {}", secrets.len(), self.dir.join(SECRETS_FILE).display());
+        Ok(())
+    }
+}
+
+impl SecretBackend for EncryptedFileBackend {
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        Ok(self.read_all()?.remove(name))
+    }
+
+    /// Fails, rather than dropping the other secrets, if the stored ones can't be read
+    fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        let mut secrets = self.read_all()?;
+        secrets.insert(name.to_string(), value.to_string());
+        self.write_all(&secrets)
+    }
+
+    fn delete(&self, name: &str) -> Result<bool, String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        let mut secrets = self.read_all()?;
+        if secrets.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.write_all(&secrets)?;
+        Ok(true)
+    }
+
+    /// Deletes the secrets file and its key, whether or not they can be read
+    fn reset(&self, _names: &[String]) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        self.remove_files()?;
+        log::warn!("Deleted the secrets store in {}", self.dir.display());
+        Ok(())
+    }
+}
+
+/// The non-secret settings of the provider the Agent service was last initialized with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderSettings {
+    pub kind: ProviderKind,
+    pub model: Option<String>,
+    pub base_url: Option<String>,
+}
+
+/// Stores the API keys of the LLM providers so the frontend never has to keep them, and the
+/// settings that go with them so the agent can be initialized again at startup
+#[derive(Clone, Default)]
+pub struct ApiKeyStore {
+    /// Where keys are kept, set once the app data directory is known
+    backend: Arc<RwLock<Option<Arc<dyn SecretBackend>>>>,
+    /// Path of the provider settings file, set once the app data directory is known
+    settings_path: Arc<RwLock<Option<PathBuf>>>,
+}
+
+impl ApiKeyStore {
+    /// Create a store that is unavailable until a backend is set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set where keys are kept
+    pub fn set_backend(&self, backend: Arc<dyn SecretBackend>) {
+        if let Ok(mut current) = self.backend.write() {
+            *current = Some(backend);
+        }
+    }
+
+    /// Set the directory the provider settings are kept in
+    pub fn set_dir(&self, dir: PathBuf) {
+        if let Ok(mut path) = self.settings_path.write() {
+            *path = Some(dir.join(PROVIDER_SETTINGS_FILE));
+        }
+    }
+
+    /// The stored key for a provider, if there is one
+    pub fn get(&self, provider: ProviderKind) -> Result<Option<String>, String> {
+        self.backend()?.get(&secret_name(provider))
+    }
+
+    /// Store the key for a provider, replacing any previous one
+    pub fn set(&self, provider: ProviderKind, api_key: &str) -> Result<(), String> {
+        if api_key.trim().is_empty() {
+            return Err("The API key is empty".to_string());
+        }
+        self.backend()?.set(&secret_name(provider), api_key.trim())?;
+        log::info!("Stored API key for {:?}", provider);
+        Ok(())
+    }
+
+    /// Remove the stored key for a provider
+    /// Returns false if no key was stored
+    pub fn clear(&self, provider: ProviderKind) -> Result<bool, String> {
+        let removed = self.backend()?.delete(&secret_name(provider))?;
+        if removed {
+            log::info!("Removed API key for {:?}", provider);
+        }
+        Ok(removed)
+    }
+
+    /// Remove the stored keys of every provider, even if the store can no longer be read
+    ///
+    /// This is the way out when the store is unreadable, e.g. after its key file was deleted,
+    /// and is only done when the user asks for it.
+    pub fn reset(&self) -> Result<(), String> {
+        let names: Vec<String> = ALL_PROVIDERS.iter().map(|&kind| secret_name(kind)).collect();
+        self.backend()?.reset(&names)?;
+        log::info!("Removed the API keys of all providers");
+        Ok(())
+    }
+
+    /// Remember the provider, model and base URL the Agent service was initialized with
+    ///
+    /// The mock provider is never remembered. Failures are logged rather than returned since
+    /// the agent already works; only the next start is affected.
+    pub fn save_settings(&self, config: &ProviderConfig) {
+        if config.kind == ProviderKind::Mock {
+            return;
+        }
+        let Some(path) = self.settings_path() else {
+            return;
+        };
+
+        let settings = ProviderSettings {
+            kind: config.kind,
+            model: config.model.clone().filter(|m| !m.trim().is_empty()),
+            base_url: config.base_url.clone().filter(|u| !u.trim().is_empty()),
+        };
+        let result = serde_json::to_string_pretty(&settings)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                fs::write(&path, json).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::warn!("Failed to save provider settings to {}: {}", path.display(), e);
+        }
+    }
+
+    /// The settings of the provider last used, if any were saved
+    pub fn settings(&self) -> Option<ProviderSettings> {
+        let path = self.settings_path()?;
+        let content = fs::read_to_string(&path).ok()?;
+        serde_json::from_str(&content)
+            .map_err(|e| log::warn!("Ignoring invalid provider settings in {}: {}", path.display(), e))
+            .ok()
+    }
+
+    /// The configuration to initialize the Agent service with at startup, if there is one
+    ///
+    /// Prefers the provider last used, with its model and base URL, when the key it needs is
+    /// stored. Otherwise it is the first provider in `STARTUP_PROVIDERS` with a stored key,
+    /// with its default settings; providers that can't work without settings, like a
+    /// self-hosted server, are not tried then.
+    pub fn startup_config(&self) -> Option<ProviderConfig> {
+        let stored_key = |kind: ProviderKind| match self.get(kind) {
+            Ok(key) => key,
+            Err(e) => {
+                log::warn!("Failed to read stored API key for {:?}: {}", kind, e);
+                None
+            }
+        };
+        let config = |kind, api_key, model, base_url| ProviderConfig {
+            kind,
+            api_key,
+            model,
+            base_url,
+            fixtures_dir: None,
+            record_fixtures: false,
+        };
+
+        if let Some(settings) = self.settings() {
+            let api_key = stored_key(settings.kind);
+            // Self-hosted servers often don't check the key
+            if api_key.is_some() || settings.kind == ProviderKind::OpenAiCompatible {
+                return Some(config(settings.kind, api_key.unwrap_or_default(), settings.model, settings.base_url));
+            }
+        }
+
+        STARTUP_PROVIDERS
+            .iter()
+            .find_map(|&kind| stored_key(kind).map(|api_key| config(kind, api_key, None, None)))
+    }
+
+    fn settings_path(&self) -> Option<PathBuf> {
+        self.settings_path.read().ok().and_then(|path| path.clone())
+    }
+
+    fn backend(&self) -> Result<Arc<dyn SecretBackend>, String> {
+        self.backend
+            .read()
+            .ok()
+            .and_then(|backend| backend.clone())
+            .ok_or_else(|| "API key storage is not available".to_string())
+    }
+}
+
+fn secret_name(provider: ProviderKind) -> String {
+    format!("api_key.{:?}", provider).to_lowercase()
+}
+
+/// Writes a file only the current user can read, replacing it if it exists
+fn write_private(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    file.write_all(content)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file backend in a fresh directory
+    fn file_backend(name: &str) -> (EncryptedFileBackend, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("secret-store-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        (EncryptedFileBackend::new(dir.clone()), dir)
+    }
+
+    #[test]
+    fn stores_reads_and_deletes_secrets() {
+        let (backend, dir) = file_backend("round-trip");
+
+        assert_eq!(backend.get("api_key.anthropic").unwrap(), None);
+        backend.set("api_key.anthropic", "sk-ant-first").unwrap();
+        assert_eq!(backend.get("api_key.anthropic").unwrap().as_deref(), Some("sk-ant-first"));
+
+        backend.set("api_key.anthropic", "sk-ant-second").unwrap();
+        assert_eq!(backend.get("api_key.anthropic").unwrap().as_deref(), Some("sk-ant-second"));
+
+        // The file never holds the secret in plain text
+        let stored = fs::read_to_string(dir.join(SECRETS_FILE)).unwrap();
+        assert!(!stored.contains("sk-ant"));
+
+        assert!(backend.delete("api_key.anthropic").unwrap());
+        assert!(!backend.delete("api_key.anthropic").unwrap());
+        assert_eq!(backend.get("api_key.anthropic").unwrap(), None);
+    }
+
+    #[test]
+    fn keeps_other_secrets_when_one_is_set() {
+        let (backend, dir) = file_backend("second-key");
+        backend.set("api_key.anthropic", "sk-ant-key").unwrap();
+        backend.set("api_key.openai", "sk-openai-key").unwrap();
+
+        // A fresh backend reads the same files
+        let reopened = EncryptedFileBackend::new(dir);
+        assert_eq!(reopened.get("api_key.anthropic").unwrap().as_deref(), Some("sk-ant-key"));
+        assert_eq!(reopened.get("api_key.openai").unwrap().as_deref(), Some("sk-openai-key"));
+    }
+
+    #[test]
+    fn refuses_to_overwrite_secrets_it_cannot_decrypt() {
+        let (backend, dir) = file_backend("missing-key");
+        backend.set("api_key.anthropic", "sk-ant-key").unwrap();
+        let stored = fs::read(dir.join(SECRETS_FILE)).unwrap();
+        fs::remove_file(dir.join(SECRETS_KEY_FILE)).unwrap();
+
+        let error = backend.set("api_key.openai", "sk-openai-key").unwrap_err();
+        assert!(error.contains("is missing"), "{}", error);
+        assert!(backend.get("api_key.anthropic").is_err());
+        // Neither file was replaced
+        assert_eq!(fs::read(dir.join(SECRETS_FILE)).unwrap(), stored);
+        assert!(!dir.join(SECRETS_KEY_FILE).exists());
+    }
+
+    #[test]
+    fn a_reset_clears_an_unreadable_store() {
+        let (backend, dir) = file_backend("reset");
+        backend.set("api_key.anthropic", "sk-ant-key").unwrap();
+        fs::write(dir.join(SECRETS_KEY_FILE), [0u8; KEY_LEN]).unwrap();
+        assert!(backend.get("api_key.anthropic").unwrap_err().contains("Failed to decrypt"));
+
+        let store = ApiKeyStore::new();
+        store.set_backend(Arc::new(backend));
+        store.reset().unwrap();
+
+        assert_eq!(store.get(ProviderKind::Anthropic).unwrap(), None);
+        store.set(ProviderKind::OpenAi, " sk-openai-key ").unwrap();
+        assert_eq!(store.get(ProviderKind::OpenAi).unwrap().as_deref(), Some("sk-openai-key"));
+    }
+
+    /// A key store with a file backend and settings in a fresh directory
+    fn key_store(name: &str) -> (ApiKeyStore, PathBuf) {
+        let (backend, dir) = file_backend(name);
+        let store = ApiKeyStore::new();
+        store.set_backend(Arc::new(backend));
+        store.set_dir(dir.clone());
+        (store, dir)
+    }
+
+    fn provider_config(kind: ProviderKind, model: Option<&str>, base_url: Option<&str>) -> ProviderConfig {
+        ProviderConfig {
+            kind,
+            api_key: String::new(),
+            model: model.map(String::from),
+            base_url: base_url.map(String::from),
+            fixtures_dir: None,
+            record_fixtures: false,
+        }
+    }
+
+    #[test]
+    fn starts_with_the_saved_provider_settings() {
+        let (store, dir) = key_store("startup-settings");
+        store.set(ProviderKind::Anthropic, "sk-ant-key").unwrap();
+        store.set(ProviderKind::OpenAi, "sk-openai-key").unwrap();
+        store.save_settings(&provider_config(ProviderKind::OpenAi, Some("gpt-4o"), None));
+        assert!(dir.join(PROVIDER_SETTINGS_FILE).exists());
+
+        let config = store.startup_config().unwrap();
+        assert_eq!((config.kind, config.api_key.as_str()), (ProviderKind::OpenAi, "sk-openai-key"));
+        assert_eq!(config.model.as_deref(), Some("gpt-4o"));
+    }
+
+    #[test]
+    fn starts_a_self_hosted_server_without_a_key() {
+        let (store, _) = key_store("startup-compatible");
+        store.save_settings(&provider_config(
+            ProviderKind::OpenAiCompatible,
+            Some("llama3"),
+            Some("http://localhost:11434/v1"),
+        ));
+
+        let config = store.startup_config().unwrap();
+        assert_eq!(config.kind, ProviderKind::OpenAiCompatible);
+        assert_eq!(config.api_key, "");
+        assert_eq!(config.model.as_deref(), Some("llama3"));
+        assert_eq!(config.base_url.as_deref(), Some("http://localhost:11434/v1"));
+    }
+
+    #[test]
+    fn falls_back_to_a_stored_key_with_default_settings() {
+        let (store, _) = key_store("startup-fallback");
+        assert!(store.startup_config().is_none());
+
+        // The saved provider's key is gone, and the mock provider is never saved
+        store.save_settings(&provider_config(ProviderKind::Anthropic, Some("claude-3-5-haiku-latest"), None));
+        store.save_settings(&provider_config(ProviderKind::Mock, None, None));
+        assert_eq!(store.settings().unwrap().kind, ProviderKind::Anthropic);
+        store.set(ProviderKind::OpenAi, "sk-openai-key").unwrap();
+
+        let config = store.startup_config().unwrap();
+        assert_eq!(config.kind, ProviderKind::OpenAi);
+        assert_eq!((config.model, config.base_url), (None, None));
+    }
+
+    #[test]
+    fn api_key_store_keeps_keys_per_provider() {
+        let (backend, _) = file_backend("providers");
+        let store = ApiKeyStore::new();
+        assert!(store.get(ProviderKind::Anthropic).is_err());
+
+        store.set_backend(Arc::new(backend));
+        assert!(store.set(ProviderKind::Anthropic, "  ").is_err());
+        store.set(ProviderKind::Anthropic, "sk-ant-key").unwrap();
+        store.set(ProviderKind::OpenAi, "sk-openai-key").unwrap();
+
+        assert!(store.clear(ProviderKind::Anthropic).unwrap());
+        assert!(!store.clear(ProviderKind::Anthropic).unwrap());
+        assert_eq!(store.get(ProviderKind::OpenAi).unwrap().as_deref(), Some("sk-openai-key"));
+    }
+}
@@ -0,0 +1,430 @@
+use log::info;
+
+/// Default maximum number of characters of source code sent in one test generation request
+pub const DEFAULT_CHUNK_SIZE: usize = 24_000;
+
+/// Splits a source file into chunks of whole top-level items for chunked test generation
+///
+/// Everything before the first top-level item (package line, imports, module docs) is the
+/// file's header and is repeated at the start of every chunk. Items are grouped in order
+/// until a chunk would exceed `max_chars`. A class, impl or module larger than that is split
+/// into groups of its members, each wrapped in the container's opening and closing lines;
+/// any other item larger than that is sent on its own. Files that fit in `max_chars`, or in
+/// which no items are recognized, come back as a single chunk holding the whole file.
+pub fn split_source(code: &str, language: &str, max_chars: usize) -> Vec<String> {
+    if code.len() <= max_chars {
+        return vec![code.to_string()];
+    }
+
+    let language = language.to_lowercase();
+    let lines: Vec<&str> = code.lines().collect();
+    let starts = item_starts(&lines, &language, false);
+    let Some(&first) = starts.first() else {
+        return vec![code.to_string()];
+    };
+
+    let header = lines[..first].join("\n");
+    let header = header.trim_end();
+    let budget = max_chars.saturating_sub(header.len()).max(1);
+
+    let mut pieces = Vec::new();
+    for (index, &start) in starts.iter().enumerate() {
+        let end = starts.get(index + 1).copied().unwrap_or(lines.len());
+        split_item(&lines[start..end], &language, budget, &Container::default(), &mut pieces);
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current: Option<(&Container, String)> = None;
+    for piece in &pieces {
+        let fits = current.as_ref().is_some_and(|(container, items)| {
+            *container == &piece.container && container.len() + items.len() + piece.text.len() + 2 <= budget
+        });
+        match current.as_mut() {
+            Some((_, items)) if fits => {
+                items.push_str("\n\n");
+                items.push_str(&piece.text);
+            }
+            _ => {
+                if let Some((container, items)) = current.take() {
+                    chunks.push(with_header(header, &container.wrap(&items)));
+                }
+                current = Some((&piece.container, piece.text.clone()));
+            }
+        }
+    }
+    if let Some((container, items)) = current {
+        chunks.push(with_header(header, &container.wrap(&items)));
+    }
+
+    info!(
+        "Split {} characters of {} source into {} chunks",
+        code.len(),
+        language,
+        chunks.len()
+    );
+    chunks
+}
+
+/// The opening and closing lines of the containers a piece was split out of, outermost first
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Container {
+    opening: Vec<String>,
+    closing: Vec<String>,
+}
+
+impl Container {
+    /// The container nested in this one with the given opening and closing lines
+    fn enter(&self, opening: String, closing: String) -> Container {
+        let mut inner = self.clone();
+        inner.opening.push(opening);
+        inner.closing.push(closing);
+        inner
+    }
+
+    /// Characters the opening and closing lines add to a chunk
+    fn len(&self) -> usize {
+        self.opening.iter().chain(&self.closing).map(|lines| lines.len() + 1).sum()
+    }
+
+    /// Puts a container's members back between its opening and closing lines
+    fn wrap(&self, members: &str) -> String {
+        let mut parts: Vec<&str> = self.opening.iter().map(String::as_str).collect();
+        parts.push(members);
+        parts.extend(self.closing.iter().rev().map(String::as_str).filter(|lines| !lines.is_empty()));
+        parts.join("\n")
+    }
+}
+
+/// Consecutive lines of one item, or of members of the same container, to put in a chunk
+struct Piece {
+    container: Container,
+    text: String,
+}
+
+/// Adds an item to `pieces`, split into its members if it is a container over the budget
+fn split_item(lines: &[&str], language: &str, budget: usize, container: &Container, pieces: &mut Vec<Piece>) {
+    let lines = &lines[lines.iter().take_while(|line| line.trim().is_empty()).count()..];
+    let text = lines.join("\n");
+    let text = text.trim_end();
+
+    let bounds = if container.len() + text.len() > budget {
+        container_bounds(lines, language)
+    } else {
+        None
+    };
+    let members = bounds.map(|(open, close)| member_starts(&lines[open + 1..close], language));
+    let (Some((open, close)), Some(members)) = (bounds, members.filter(|m| !m.is_empty())) else {
+        pieces.push(Piece { container: container.clone(), text: text.to_string() });
+        return;
+    };
+
+    // One-line members at the top of the body (fields, `use` lines, docstrings) go with the
+    // opening lines, so that every chunk has them
+    let body = &lines[open + 1..close];
+    let member_end = |index: usize| members.get(index + 1).copied().unwrap_or(body.len());
+    let code_lines = |index: usize| {
+        body[members[index]..member_end(index)]
+            .iter()
+            .map(|line| line.trim_start())
+            .filter(|line| !line.is_empty() && !is_leading_line(line, language))
+            .count()
+    };
+    let Some(first) = (0..members.len()).find(|&index| code_lines(index) > 1) else {
+        pieces.push(Piece { container: container.clone(), text: text.to_string() });
+        return;
+    };
+
+    let mut opening = lines[..open + 1 + members[first]].join("\n").trim_end().to_string();
+    if first > 0 {
+        opening.push('\n');
+    }
+    let closing = lines[close..].join("\n");
+    let inner = container.enter(opening, closing.trim_end().to_string());
+    for index in first..members.len() {
+        split_item(&body[members[index]..member_end(index)], language, budget, &inner, pieces);
+    }
+}
+
+/// The lines that open and close the body of a class, impl or module, if the item is one
+fn container_bounds(lines: &[&str], language: &str) -> Option<(usize, usize)> {
+    let item = lines.iter().position(|line| {
+        let trimmed = line.trim_start();
+        !trimmed.is_empty() && !is_leading_line(trimmed, language)
+    })?;
+    if !is_container_start(lines[item].trim_start(), language) {
+        return None;
+    }
+
+    let (open, close) = match language {
+        // Python bodies end with the item, Ruby's with `end`
+        "python" => (item + lines[item..].iter().position(|l| l.trim_end().ends_with(':'))?, lines.len()),
+        "ruby" => (item, lines.iter().rposition(|l| l.trim() == "end")?),
+        _ => (
+            item + lines[item..].iter().position(|l| l.contains('{'))?,
+            lines.iter().rposition(|l| l.trim_start().starts_with('}'))?,
+        ),
+    };
+    (open < close).then_some((open, close))
+}
+
+/// Indices of the lines that start each member of a container's body
+fn member_starts(body: &[&str], language: &str) -> Vec<usize> {
+    let indent = body
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let dedented: Vec<&str> = body.iter().map(|line| line.get(indent..).unwrap_or("")).collect();
+    item_starts(&dedented, language, true)
+}
+
+/// Prepends the shared header to a chunk's items
+fn with_header(header: &str, items: &str) -> String {
+    if header.is_empty() {
+        format!("{}\n", items)
+    } else {
+        format!("{}\n\n{}\n", header, items)
+    }
+}
+
+/// Indices of the lines that start each top-level item
+/// Doc comments, attributes and decorators directly above an item belong to it. With `members`,
+/// every top-level statement starts an item, since class members have no keyword in common.
+fn item_starts(lines: &[&str], language: &str, members: bool) -> Vec<usize> {
+    let braces = language != "python";
+    let mut starts = Vec::new();
+    let mut depth: i32 = 0;
+    // First line of the comments/attributes seen since the last top-level code line
+    let mut leading: Option<usize> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        let top_level = depth <= 0 && line.len() == trimmed.len();
+
+        if top_level && !trimmed.is_empty() {
+            if is_leading_line(trimmed, language) {
+                leading.get_or_insert(index);
+            } else {
+                let continues = trimmed.starts_with(['{', '}', ')', ']']);
+                if is_item_start(trimmed, language) || (members && !continues) {
+                    starts.push(leading.unwrap_or(index));
+                }
+                leading = None;
+            }
+        } else if !trimmed.is_empty() && !trimmed.starts_with('*') {
+            // Indented code ends a run of comments; ` * ...` lines continue a block comment
+            leading = None;
+        }
+
+        if braces {
+            for c in line.chars() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    starts
+}
+
+/// Returns true for comment, attribute and decorator lines that attach to the next item
+fn is_leading_line(trimmed: &str, language: &str) -> bool {
+    match language {
+        "python" => trimmed.starts_with('#') || trimmed.starts_with('@'),
+        "rust" => trimmed.starts_with("//") || trimmed.starts_with("#[") || trimmed.starts_with("/*"),
+        _ => {
+            trimmed.starts_with("//")
+                || trimmed.starts_with("/*")
+                || trimmed.starts_with("*/")
+                || trimmed.starts_with('@')
+                || trimmed.starts_with('[')
+        }
+    }
+}
+
+/// Returns true for a top-level line that starts a function, type or impl block
+fn is_item_start(trimmed: &str, language: &str) -> bool {
+    let Some((modifiers, keywords, _)) = item_keywords(language) else {
+        return false;
+    };
+    let rest = strip_modifiers(trimmed, modifiers);
+    keywords.iter().any(|keyword| rest.starts_with(keyword))
+}
+
+/// Returns true for a line that starts a class, impl or module whose members can be split up
+fn is_container_start(trimmed: &str, language: &str) -> bool {
+    let Some((modifiers, _, containers)) = item_keywords(language) else {
+        return false;
+    };
+    let rest = strip_modifiers(trimmed, modifiers);
+    containers.iter().any(|keyword| rest.starts_with(keyword))
+}
+
+/// Modifiers that may precede an item, keywords that start one, and keywords that start a container
+fn item_keywords(language: &str) -> Option<(&'static [&'static str], &'static [&'static str], &'static [&'static str])> {
+    Some(match language {
+        "rust" => (
+            &["pub(crate) ", "pub(super) ", "pub ", "unsafe ", "async ", "const ", "extern \"C\" "],
+            &["fn ", "impl ", "impl<", "struct ", "enum ", "trait ", "mod ", "union ", "macro_rules!"],
+            &["impl ", "impl<", "trait ", "mod "],
+        ),
+        "python" => (&["async "], &["def ", "class "], &["class "]),
+        "javascript" | "typescript" | "react" => (
+            &["export default ", "export ", "declare ", "abstract ", "async "],
+            &["function ", "function*", "class ", "interface ", "type ", "enum ", "const ", "let ", "namespace "],
+            &["class ", "namespace "],
+        ),
+        "go" => (&[], &["func ", "type ", "var ", "const "], &[]),
+        "java" | "kotlin" | "c#" | "csharp" => (
+            &[
+                "public ", "private ", "protected ", "internal ", "static ", "final ", "abstract ",
+                "sealed ", "partial ", "data ", "open ",
+            ],
+            &["class ", "interface ", "enum ", "record ", "struct ", "object ", "fun ", "namespace "],
+            &["class ", "interface ", "enum ", "record ", "struct ", "object ", "namespace "],
+        ),
+        "ruby" => (&[], &["def ", "class ", "module "], &["class ", "module "]),
+        _ => return None,
+    })
+}
+
+/// The rest of a line after any leading modifiers
+fn strip_modifiers<'a>(trimmed: &'a str, modifiers: &[&str]) -> &'a str {
+    let mut rest = trimmed;
+    while let Some(modifier) = modifiers.iter().find(|m| rest.starts_with(*m)) {
+        rest = &rest[modifier.len()..];
+    }
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A method of `lines` lines, each long enough to make the method about 40 characters a line
+    fn java_method(name: &str, lines: usize) -> String {
+        let body: String = (0..lines).map(|i| format!("        total += price({});\n", i)).collect();
+        format!("    // Computes {}\n    public int {}() {{\n        int total = 0;\n{}        return total;\n    }}\n", name, name, body)
+    }
+
+    #[test]
+    fn returns_small_files_whole() {
+        let code = "fn a() {}\n\nfn b() {}\n";
+        assert_eq!(split_source(code, "rust", 1_000), vec![code.to_string()]);
+        assert_eq!(split_source("just some text\n".repeat(10).as_str(), "rust", 20).len(), 1);
+    }
+
+    #[test]
+    fn groups_top_level_items_and_repeats_the_header() {
+        let code = "use std::fmt;\n\n/// Adds\nfn add() {\n    1 + 1;\n}\n\n#[inline]\nfn sub() {\n    1 - 1;\n}\n\nfn mul() {\n    1 * 1;\n}\n";
+
+        assert_eq!(
+            split_source(code, "rust", 80),
+            [
+                "use std::fmt;\n\n/// Adds\nfn add() {\n    1 + 1;\n}\n\n#[inline]\nfn sub() {\n    1 - 1;\n}\n",
+                "use std::fmt;\n\nfn mul() {\n    1 * 1;\n}\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_a_single_java_class_into_its_methods() {
+        let code = format!(
+            "package shop;\n\nimport java.util.List;\n\n@Service\npublic class Cart {{\n    private final List<Item> items;\n\n{}\n{}\n{}}}\n",
+            java_method("first", 4),
+            java_method("second", 4),
+            java_method("third", 4)
+        );
+
+        let chunks = split_source(&code, "java", 400);
+        assert_eq!(chunks.len(), 3, "{:#?}", chunks);
+        for chunk in &chunks {
+            assert!(chunk.len() <= 400, "{}", chunk);
+            assert!(chunk.starts_with("package shop;\n\nimport java.util.List;\n\n@Service\npublic class Cart {\n"), "{}", chunk);
+            assert!(chunk.ends_with("    }\n}\n"), "{}", chunk);
+        }
+        // Fields are repeated in every chunk
+        assert!(chunks.iter().all(|chunk| chunk.contains("public class Cart {\n    private final List<Item> items;\n\n    // Computes ")));
+        assert!(chunks[0].contains("    // Computes first\n    public int first()"));
+        assert!(chunks[1].contains("    // Computes second\n    public int second()"));
+        assert!(chunks[2].contains("public int third()"));
+    }
+
+    #[test]
+    fn splits_a_large_rust_impl_and_keeps_small_items_whole() {
+        let methods: String = ["parse", "peek", "advance"]
+            .iter()
+            .map(|name| format!("    #[inline]\n    pub fn {}(&mut self) -> usize {{\n        self.position += 1;\n        self.position\n    }}\n\n", name))
+            .collect();
+        let code = format!(
+            "use std::fmt;\n\npub struct Parser {{\n    position: usize,\n}}\n\nimpl<'a> Parser {{\n{}\n}}\n",
+            methods.trim_end()
+        );
+
+        let chunks = split_source(&code, "rust", 140);
+        assert_eq!(
+            chunks[0],
+            "use std::fmt;\n\npub struct Parser {\n    position: usize,\n}\n"
+        );
+        assert_eq!(
+            chunks[1],
+            "use std::fmt;\n\nimpl<'a> Parser {\n    #[inline]\n    pub fn parse(&mut self) -> usize {\n        self.position += 1;\n        self.position\n    }\n}\n"
+        );
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks[3].contains("pub fn advance") && chunks[3].ends_with("    }\n}\n"));
+    }
+
+    #[test]
+    fn splits_nested_containers() {
+        let methods = |prefix: &str| -> String {
+            (0..3)
+                .map(|i| format!("        fn {}_{}() {{\n            assert!(true);\n        }}\n", prefix, i))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let code = format!(
+            "#[cfg(test)]\nmod tests {{\n    use super::*;\n\n    impl Helper {{\n{}    }}\n}}\n",
+            methods("helper")
+        );
+
+        let chunks = split_source(&code, "rust", 120);
+        assert!(chunks.len() > 1, "{:#?}", chunks);
+        for chunk in &chunks {
+            assert!(chunk.starts_with("#[cfg(test)]\nmod tests {\n    use super::*;\n\n    impl Helper {\n        fn helper_"), "{}", chunk);
+            assert!(chunk.ends_with("    }\n}\n"), "{}", chunk);
+        }
+    }
+
+    #[test]
+    fn splits_a_python_class_without_a_closing_line() {
+        let methods: String = (0..3)
+            .map(|i| format!("    @property\n    def value_{}(self):\n        return self.values[{}] * 2\n\n", i, i))
+            .collect();
+        let code = format!("import math\n\n\nclass Cart:\n    \"\"\"A cart.\"\"\"\n\n{}", methods);
+
+        let chunks = split_source(&code, "python", 140);
+        assert_eq!(chunks.len(), 3, "{:#?}", chunks);
+        assert_eq!(
+            chunks[0],
+            "import math\n\nclass Cart:\n    \"\"\"A cart.\"\"\"\n\n    @property\n    def value_0(self):\n        return self.values[0] * 2\n"
+        );
+        assert_eq!(
+            chunks[2],
+            "import math\n\nclass Cart:\n    \"\"\"A cart.\"\"\"\n\n    @property\n    def value_2(self):\n        return self.values[2] * 2\n"
+        );
+    }
+
+    #[test]
+    fn sends_an_oversized_function_on_its_own() {
+        let body: String = (0..10).map(|i| format!("    let _x{} = {};\n", i, i)).collect();
+        let code = format!("fn small() {{}}\n\nfn large() {{\n{}}}\n", body);
+
+        let chunks = split_source(&code, "rust", 80);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1], format!("fn large() {{\n{}}}\n", body));
+    }
+}
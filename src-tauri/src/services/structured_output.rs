@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+use crate::services::test_extraction::extract_test_code;
+
+/// One test case of a generated suite
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedTestCase {
+    /// Name of the test function or `it`/`test` block
+    pub name: String,
+    /// What the test checks
+    #[serde(default)]
+    pub description: String,
+}
+
+/// A generated test file with a description of what it covers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedTestSuite {
+    /// Where the model suggests writing the file, relative to the project root
+    #[serde(default)]
+    pub test_path: Option<String>,
+    /// The complete test file
+    pub content: String,
+    /// Functions and methods of the source code the tests exercise
+    #[serde(default)]
+    pub covered_functions: Vec<String>,
+    #[serde(default)]
+    pub test_cases: Vec<GeneratedTestCase>,
+    /// Packages the tests need that the project may not have yet
+    #[serde(default)]
+    pub dev_dependencies: Vec<String>,
+}
+
+/// Parses and validates a structured test generation response
+///
+/// Models sometimes wrap the JSON in a code fence or add a sentence around it, so the
+/// outermost object is parsed. The file content goes through the same extraction and
+/// checks as plain responses.
+pub fn parse_test_suite(response: &str, language: &str) -> Result<GeneratedTestSuite, String> {
+    let json = outermost_json_object(response)
+        .ok_or_else(|| "The generated response does not contain a JSON object".to_string())?;
+
+    let mut suite: GeneratedTestSuite = serde_json::from_str(json)
+        .map_err(|e| format!("The generated response is not a valid test suite: {}", e))?;
+
+    suite.content = extract_test_code(&suite.content, language)?;
+    suite.test_path = suite
+        .test_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty());
+    suite.covered_functions.retain(|name| !name.trim().is_empty());
+    suite.dev_dependencies.retain(|name| !name.trim().is_empty());
+
+    Ok(suite)
+}
+
+/// The text from the first `{` to the last `}` of a response, where a JSON answer would be
+/// if the model wrapped it in a code fence or prose
+pub fn outermost_json_object(response: &str) -> Option<&str> {
+    match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => Some(&response[start..=end]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_outermost_json_object() {
+        assert_eq!(outermost_json_object("{\"a\": {\"b\": 1}}"), Some("{\"a\": {\"b\": 1}}"));
+        assert_eq!(
+            outermost_json_object("Sure:\n```json\n{\"a\": 1}\n```\nDone."),
+            Some("{\"a\": 1}")
+        );
+        assert_eq!(outermost_json_object("no object here"), None);
+        assert_eq!(outermost_json_object("} backwards {"), None);
+    }
+
+    #[test]
+    fn parses_a_fenced_test_suite() {
+        let response = r#"```json
+{
+  "test_path": "  tests/cart.test.ts ",
+  "content": "```ts\nimport { total } from '../src/cart';\n\ntest('totals', () => expect(total([])).toBe(0));\n```",
+  "covered_functions": ["total", " "],
+  "test_cases": [{"name": "totals"}]
+}
+```"#;
+
+        let suite = parse_test_suite(response, "typescript").unwrap();
+        assert_eq!(suite.test_path.as_deref(), Some("tests/cart.test.ts"));
+        assert!(suite.content.starts_with("import { total }"));
+        assert_eq!(suite.covered_functions, ["total"]);
+        assert_eq!(suite.test_cases[0].description, "");
+    }
+
+    #[test]
+    fn rejects_responses_without_a_suite() {
+        assert!(parse_test_suite("I can't do that.", "typescript")
+            .unwrap_err()
+            .contains("does not contain a JSON object"));
+        assert!(parse_test_suite("{\"test_path\": \"a.test.ts\"}", "typescript")
+            .unwrap_err()
+            .contains("not a valid test suite"));
+    }
+}
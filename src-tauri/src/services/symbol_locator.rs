@@ -0,0 +1,436 @@
+use serde::Serialize;
+use tree_sitter::{Node, Parser};
+
+use crate::services::syntax_validation::grammars;
+
+/// Node kinds that define a function, method, class or type, with the field holding their name
+const DEFINITION_KINDS: &[(&str, &str)] = &[
+    // Rust
+    ("function_item", "name"),
+    ("struct_item", "name"),
+    ("enum_item", "name"),
+    ("trait_item", "name"),
+    ("impl_item", "type"),
+    ("mod_item", "name"),
+    // TypeScript / JavaScript
+    ("function_declaration", "name"),
+    ("generator_function_declaration", "name"),
+    ("class_declaration", "name"),
+    ("abstract_class_declaration", "name"),
+    ("method_definition", "name"),
+    ("variable_declarator", "name"),
+    // Python
+    ("function_definition", "name"),
+    ("class_definition", "name"),
+    // Go
+    ("method_declaration", "name"),
+    ("type_spec", "name"),
+    // Java
+    ("constructor_declaration", "name"),
+    ("interface_declaration", "name"),
+    ("enum_declaration", "name"),
+    ("record_declaration", "name"),
+];
+
+/// Values that make a `const name = ...` declaration a function or class worth testing
+const FUNCTION_VALUE_KINDS: &[&str] = &["arrow_function", "function", "function_expression", "class"];
+
+/// Wrappers whose text belongs to the definition inside them (decorators, `export`)
+const WRAPPER_KINDS: &[&str] = &["decorated_definition", "export_statement", "lexical_declaration"];
+
+/// Comments and attributes directly above a definition that belong to it
+const LEADING_KINDS: &[&str] = &["comment", "line_comment", "block_comment", "attribute_item", "decorator"];
+
+/// A function, method or class found in a source file
+#[derive(Debug, Clone, Serialize)]
+pub struct LocatedSymbol {
+    /// The name that was looked up
+    pub name: String,
+    /// Kind of the definition's syntax node, e.g. `function_item` or `method_definition`
+    pub kind: String,
+    /// First line of the definition, including its doc comments and decorators, starting at 1
+    pub start_line: usize,
+    /// Last line of the definition, starting at 1
+    pub end_line: usize,
+    /// Source of the definition (of every overload, for overloaded methods)
+    pub code: String,
+}
+
+/// A definition whose name matched the lookup
+struct Match<'tree> {
+    node: Node<'tree>,
+    /// Names of the classes, impls and modules the definition is nested in, outermost first
+    owners: Vec<String>,
+}
+
+/// The source file as context for a symbol's tests, cut at a line boundary to fit `max_chars`
+pub fn file_context(code: &str, max_chars: usize) -> String {
+    if code.len() <= max_chars {
+        return code.to_string();
+    }
+
+    let mut context = String::new();
+    for line in code.lines() {
+        if context.len() + line.len() + 1 > max_chars {
+            break;
+        }
+        context.push_str(line);
+        context.push('\n');
+    }
+    context.push_str("... (rest of the file omitted)\n");
+    context
+}
+
+/// Finds a function, method or class in a source file by name
+///
+/// `symbol` may be qualified with its enclosing class, impl or Go receiver type, as in
+/// `Parser.parse` or `Parser::parse`, to pick one of several definitions with the same name.
+/// Overloads of the same method are returned together.
+pub fn locate_symbol(code: &str, language: &str, symbol: &str) -> Result<LocatedSymbol, String> {
+    let path: Vec<&str> = symbol
+        .split(['.', ':'])
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    let Some((name, qualifiers)) = path.split_last() else {
+        return Err("A symbol name is required".to_string());
+    };
+
+    let grammars = grammars(language);
+    if grammars.is_empty() {
+        return Err(format!("Finding symbols is not supported for {}", language));
+    }
+
+    for grammar in grammars {
+        let mut parser = Parser::new();
+        parser
+            .set_language(grammar)
+            .map_err(|e| format!("Failed to load the {} grammar: {}", language, e))?;
+        let Some(tree) = parser.parse(code, None) else {
+            continue;
+        };
+
+        let mut matches = Vec::new();
+        collect_matches(tree.root_node(), code, name, &mut Vec::new(), &mut matches);
+        matches.retain(|found| {
+            qualifiers
+                .iter()
+                .all(|qualifier| found.owners.iter().any(|owner| owner == qualifier))
+        });
+        if matches.is_empty() {
+            continue;
+        }
+
+        // Same name in different classes is ambiguous; same name in the same class is overloading
+        if matches.iter().any(|found| found.owners != matches[0].owners) {
+            let locations: Vec<String> = matches
+                .iter()
+                .map(|found| {
+                    let mut qualified = found.owners.clone();
+                    qualified.push(name.to_string());
+                    format!("{} (line {})", qualified.join("."), found.node.start_position().row + 1)
+                })
+                .collect();
+            return Err(format!(
+                "`{}` matches several definitions: {}; qualify it with its class or type",
+                symbol,
+                locations.join(", ")
+            ));
+        }
+
+        let lines: Vec<&str> = code.lines().collect();
+        let ranges: Vec<(usize, usize)> = matches.iter().map(|found| definition_lines(found.node)).collect();
+        let start_line = ranges.iter().map(|(start, _)| *start).min().unwrap_or(0);
+        let end_line = ranges.iter().map(|(_, end)| *end).max().unwrap_or(0);
+        let source = ranges
+            .iter()
+            .map(|&(start, end)| lines[start..=end.min(lines.len().saturating_sub(1))].join("\n"))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        return Ok(LocatedSymbol {
+            name: symbol.to_string(),
+            kind: matches[0].node.kind().to_string(),
+            start_line: start_line + 1,
+            end_line: end_line + 1,
+            code: source,
+        });
+    }
+
+    Err(format!("Could not find a function, method or class named `{}`", symbol))
+}
+
+/// Walks the tree, collecting definitions named `name` with the definitions enclosing them
+fn collect_matches<'tree>(
+    node: Node<'tree>,
+    code: &str,
+    name: &str,
+    owners: &mut Vec<String>,
+    matches: &mut Vec<Match<'tree>>,
+) {
+    let definition_name = definition_name(node, code);
+    if definition_name.as_deref() == Some(name) {
+        // Go methods are declared outside their type, so the receiver type acts as the owner
+        let mut match_owners = owners.clone();
+        match_owners.extend(receiver_type(node, code));
+        matches.push(Match {
+            node,
+            owners: match_owners,
+        });
+    }
+
+    let pushed = definition_name.is_some();
+    if let Some(definition_name) = definition_name {
+        owners.push(definition_name);
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_matches(child, code, name, owners, matches);
+    }
+
+    if pushed {
+        owners.pop();
+    }
+}
+
+/// The name of a definition node, or None if the node isn't one
+fn definition_name(node: Node, code: &str) -> Option<String> {
+    let (_, field) = DEFINITION_KINDS.iter().find(|(kind, _)| *kind == node.kind())?;
+
+    if node.kind() == "variable_declarator" {
+        let value = node.child_by_field_name("value")?;
+        if !FUNCTION_VALUE_KINDS.contains(&value.kind()) {
+            return None;
+        }
+    }
+
+    let name = node.child_by_field_name(field)?;
+    let text = name.utf8_text(code.as_bytes()).ok()?;
+    // `impl<T> Trait for Foo<T>` is named after `Foo`
+    Some(text.split('<').next().unwrap_or(text).trim().to_string())
+}
+
+/// The receiver type of a Go method, e.g. `Server` for `func (s *Server) Start()`
+fn receiver_type(node: Node, code: &str) -> Option<String> {
+    if node.kind() != "method_declaration" {
+        return None;
+    }
+    let receiver = node.child_by_field_name("receiver")?;
+    let text = receiver.utf8_text(code.as_bytes()).ok()?;
+    text.trim_matches(|c| c == '(' || c == ')')
+        .split_whitespace()
+        .last()
+        .map(|type_name| type_name.trim_start_matches('*').to_string())
+}
+
+/// Zero-based first and last line of a definition, including its wrappers, doc comments and attributes
+fn definition_lines(node: Node) -> (usize, usize) {
+    let mut outer = node;
+    while let Some(parent) = outer.parent() {
+        if !WRAPPER_KINDS.contains(&parent.kind()) {
+            break;
+        }
+        outer = parent;
+    }
+
+    let mut start = outer.start_position().row;
+    let mut previous = outer.prev_sibling();
+    while let Some(sibling) = previous {
+        // Only comments that end on the line right above belong to the definition
+        if !LEADING_KINDS.contains(&sibling.kind()) || sibling.end_position().row + 1 < start {
+            break;
+        }
+        start = sibling.start_position().row;
+        previous = sibling.prev_sibling();
+    }
+
+    (start, outer.end_position().row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = r#"use std::fmt;
+
+/// Parses input
+pub struct Parser {
+    input: String,
+}
+
+impl Parser {
+    /// Parses the whole input
+    #[inline]
+    pub fn parse(&self) -> usize {
+        self.input.len()
+    }
+}
+
+impl fmt::Display for Parser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.input)
+    }
+}
+
+pub struct Lexer;
+
+impl Lexer {
+    pub fn parse(&self) -> usize {
+        0
+    }
+}
+"#;
+
+    const TYPESCRIPT: &str = r#"import { Item } from "./item";
+
+// Adds two numbers
+export const add = (a: number, b: number): number => a + b;
+
+const LIMIT = 10;
+
+export class Cart {
+  private items: Item[] = [];
+
+  total(): number {
+    return this.items.reduce((sum, item) => sum + item.price, 0);
+  }
+}
+"#;
+
+    const PYTHON: &str = r#"import functools
+
+
+class Cart:
+    def __init__(self):
+        self.items = []
+
+    @functools.lru_cache
+    def total(self):
+        return sum(item.price for item in self.items)
+
+
+def total(carts):
+    return sum(cart.total() for cart in carts)
+"#;
+
+    const GO: &str = r#"package server
+
+type Server struct {
+	addr string
+}
+
+// Start listens on the address
+func (s *Server) Start() error {
+	return nil
+}
+
+type Worker struct{}
+
+func (w Worker) Start() error {
+	return nil
+}
+"#;
+
+    const JAVA: &str = r#"public class Calculator {
+    public int add(int a, int b) {
+        return a + b;
+    }
+
+    public double add(double a, double b) {
+        return a + b;
+    }
+}
+"#;
+
+    fn lines(found: &LocatedSymbol) -> (usize, usize) {
+        (found.start_line, found.end_line)
+    }
+
+    #[test]
+    fn finds_rust_definitions_with_their_docs_and_attributes() {
+        let parse = locate_symbol(RUST, "rust", "Parser::parse").unwrap();
+        assert_eq!(parse.kind, "function_item");
+        assert_eq!(lines(&parse), (9, 13));
+        assert!(parse.code.starts_with("    /// Parses the whole input\n    #[inline]\n    pub fn parse"));
+
+        // A type comes with its impl blocks, but not with other types' impls
+        let parser = locate_symbol(RUST, "rust", "Parser").unwrap();
+        assert_eq!(parser.kind, "struct_item");
+        assert_eq!(lines(&parser), (3, 20));
+        assert!(parser.code.contains("impl fmt::Display for Parser"));
+        assert!(!parser.code.contains("Lexer"));
+
+        // Trait impls are owned by the type they are for
+        assert_eq!(lines(&locate_symbol(RUST, "rust", "Parser.fmt").unwrap()), (17, 19));
+    }
+
+    #[test]
+    fn refuses_ambiguous_names() {
+        let error = locate_symbol(RUST, "rust", "parse").unwrap_err();
+        assert!(error.contains("Parser.parse (line 11)"), "{}", error);
+        assert!(error.contains("Lexer.parse (line 25)"), "{}", error);
+
+        let error = locate_symbol(GO, "go", "Start").unwrap_err();
+        assert!(error.contains("Server.Start") && error.contains("Worker.Start"), "{}", error);
+
+        let error = locate_symbol(PYTHON, "python", "total").unwrap_err();
+        assert!(error.contains("Cart.total"), "{}", error);
+    }
+
+    #[test]
+    fn finds_typescript_functions_and_methods() {
+        let add = locate_symbol(TYPESCRIPT, "typescript", "add").unwrap();
+        assert_eq!(add.kind, "variable_declarator");
+        assert_eq!(add.code, "// Adds two numbers\nexport const add = (a: number, b: number): number => a + b;");
+
+        let total = locate_symbol(TYPESCRIPT, "typescript", "Cart.total").unwrap();
+        assert_eq!(total.kind, "method_definition");
+        assert_eq!(lines(&total), (11, 13));
+
+        // Constants that aren't functions aren't symbols to test
+        assert!(locate_symbol(TYPESCRIPT, "typescript", "LIMIT").is_err());
+    }
+
+    #[test]
+    fn finds_python_methods_with_their_decorators() {
+        let total = locate_symbol(PYTHON, "python", "Cart.total").unwrap();
+        assert_eq!(lines(&total), (8, 10));
+        assert!(total.code.trim_start().starts_with("@functools.lru_cache"));
+
+        let cart = locate_symbol(PYTHON, "python", "Cart").unwrap();
+        assert_eq!(cart.kind, "class_definition");
+    }
+
+    #[test]
+    fn finds_go_methods_by_receiver_type() {
+        let start = locate_symbol(GO, "go", "Server.Start").unwrap();
+        assert_eq!(start.kind, "method_declaration");
+        assert_eq!(lines(&start), (7, 10));
+        assert!(start.code.starts_with("// Start listens on the address\nfunc (s *Server) Start()"));
+
+        assert_eq!(lines(&locate_symbol(GO, "go", "Worker.Start").unwrap()), (14, 16));
+    }
+
+    #[test]
+    fn returns_java_overloads_together() {
+        let add = locate_symbol(JAVA, "java", "Calculator.add").unwrap();
+        assert_eq!(lines(&add), (2, 8));
+        assert_eq!(add.code.matches("public").count(), 2);
+        assert!(add.code.contains("}\n\n    public double add"));
+    }
+
+    #[test]
+    fn reports_missing_symbols_and_languages() {
+        assert!(locate_symbol(RUST, "rust", "Tokenizer").unwrap_err().contains("Could not find"));
+        assert!(locate_symbol(RUST, "rust", "Lexer::fmt").is_err());
+        assert!(locate_symbol(RUST, "rust", " :: ").unwrap_err().contains("required"));
+        assert!(locate_symbol("def f(): pass", "ruby", "f").unwrap_err().contains("not supported"));
+    }
+
+    #[test]
+    fn cuts_file_context_at_a_line_boundary() {
+        assert_eq!(file_context("a\nb\n", 10), "a\nb\n");
+        assert_eq!(file_context("first line\nsecond line\n", 15), "first line\n... (rest of the file omitted)\n");
+    }
+}
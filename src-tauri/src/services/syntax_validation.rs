@@ -0,0 +1,209 @@
+use serde::Serialize;
+use std::fmt;
+use tree_sitter::{Language, Node, Parser};
+
+/// Most syntax errors reported for one file; later ones are usually follow-on errors
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// A syntax error found in generated code
+#[derive(Debug, Clone, Serialize)]
+pub struct SyntaxError {
+    /// Line of the error, starting at 1
+    pub line: usize,
+    /// Column of the error in characters, starting at 1
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Grammars that can parse code in the given language, in order of preference
+///
+/// TypeScript tests may contain JSX, so the TSX grammar is tried when the plain one fails.
+pub fn grammars(language: &str) -> Vec<Language> {
+    match language.to_lowercase().as_str() {
+        "rust" => vec![tree_sitter_rust::language()],
+        "typescript" => vec![
+            tree_sitter_typescript::language_typescript(),
+            tree_sitter_typescript::language_tsx(),
+        ],
+        "react" => vec![tree_sitter_typescript::language_tsx()],
+        // The JavaScript grammar already includes JSX
+        "javascript" => vec![tree_sitter_javascript::language()],
+        "python" => vec![tree_sitter_python::language()],
+        "go" => vec![tree_sitter_go::language()],
+        "java" => vec![tree_sitter_java::language()],
+        _ => Vec::new(),
+    }
+}
+
+/// Whether generated code in the given language can be syntax checked
+pub fn is_supported(language: &str) -> bool {
+    !grammars(language).is_empty()
+}
+
+/// Parses code with the grammar for its language and returns the syntax errors found
+///
+/// Returns an empty list for valid code and for languages without a grammar.
+pub fn check_syntax(code: &str, language: &str) -> Result<Vec<SyntaxError>, String> {
+    let mut best: Option<Vec<SyntaxError>> = None;
+
+    for grammar in grammars(language) {
+        let mut parser = Parser::new();
+        parser
+            .set_language(grammar)
+            .map_err(|e| format!("Failed to load the {} grammar: {}", language, e))?;
+        let tree = parser
+            .parse(code, None)
+            .ok_or_else(|| format!("Failed to parse the generated {} code", language))?;
+
+        let mut errors = Vec::new();
+        collect_errors(tree.root_node(), code, &mut errors);
+        if errors.is_empty() {
+            return Ok(errors);
+        }
+        if best.as_ref().map_or(true, |best| errors.len() < best.len()) {
+            best = Some(errors);
+        }
+    }
+
+    let mut errors = best.unwrap_or_default();
+    errors.truncate(MAX_REPORTED_ERRORS);
+    Ok(errors)
+}
+
+/// Lists syntax errors one per line, e.g. to explain why a file was rejected
+pub fn format_syntax_errors(errors: &[SyntaxError]) -> String {
+    errors.iter().map(|error| error.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+/// Collects the error and missing nodes below `node`, skipping subtrees without errors
+fn collect_errors(node: Node, code: &str, errors: &mut Vec<SyntaxError>) {
+    if !node.has_error() {
+        return;
+    }
+
+    if node.is_missing() {
+        errors.push(error_at(node, code, format!("missing `{}`", node.kind())));
+        return;
+    }
+    if node.is_error() {
+        let text = node.utf8_text(code.as_bytes()).unwrap_or_default();
+        let snippet: String = text.lines().next().unwrap_or_default().trim().chars().take(40).collect();
+        let message = if snippet.is_empty() {
+            "unexpected end of input".to_string()
+        } else {
+            format!("unexpected `{}`", snippet)
+        };
+        errors.push(error_at(node, code, message));
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_errors(child, code, errors);
+    }
+}
+
+/// Builds an error at a node's start, converting tree-sitter's byte column to characters
+fn error_at(node: Node, code: &str, message: String) -> SyntaxError {
+    let position = node.start_position();
+    let line_start = node.start_byte() - position.column;
+    let column = code
+        .get(line_start..node.start_byte())
+        .map_or(position.column, |prefix| prefix.chars().count());
+
+    SyntaxError {
+        line: position.row + 1,
+        column: column + 1,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A valid and an invalid sample for every language with a grammar
+    const SAMPLES: &[(&str, &str, &str)] = &[
+        (
+            "rust",
+            "#[test]\nfn adds() {\n    assert_eq!(add(1, 2), 3);\n}\n",
+            "#[test]\nfn adds() {\n    assert_eq!(add(1, 2), 3)\n",
+        ),
+        (
+            "typescript",
+            "import { add } from './math';\n\ntest('adds', () => {\n  const sum: number = add(1, 2);\n  expect(sum).toBe(3);\n});\n",
+            "test('adds', () => {\n  const sum: number = add(1, 2;\n});\n",
+        ),
+        (
+            "react",
+            "test('renders', () => {\n  render(<Button label=\"Save\" />);\n});\n",
+            "test('renders', () => {\n  render(<Button label=\"Save\">);\n});\n",
+        ),
+        (
+            "javascript",
+            "const { add } = require('./math');\n\ntest('adds', () => {\n  expect(add(1, 2)).toBe(3);\n});\n",
+            "test('adds', () => {\n  expect(add(1, 2).toBe(3);\n});\n",
+        ),
+        (
+            "python",
+            "from math_utils import add\n\n\ndef test_adds():\n    assert add(1, 2) == 3\n",
+            "def test_adds(:\n    assert add(1, 2) == 3\n",
+        ),
+        (
+            "go",
+            "package math\n\nimport \"testing\"\n\nfunc TestAdd(t *testing.T) {\n\tif Add(1, 2) != 3 {\n\t\tt.Fatal(\"wrong sum\")\n\t}\n}\n",
+            "package math\n\nfunc TestAdd(t *testing.T) {\n\tif Add(1, 2) != 3 {\n\t\tt.Fatal(\"wrong sum\"\n\t}\n}\n",
+        ),
+        (
+            "java",
+            "class MathTest {\n    @Test\n    void adds() {\n        assertEquals(3, Math.add(1, 2));\n    }\n}\n",
+            "class MathTest {\n    @Test\n    void adds() {\n        assertEquals(3, Math.add(1, 2))\n    }\n}\n",
+        ),
+    ];
+
+    #[test]
+    fn accepts_valid_code_in_every_supported_language() {
+        for (language, valid, _) in SAMPLES {
+            assert!(is_supported(language));
+            let errors = check_syntax(valid, language).unwrap();
+            assert!(errors.is_empty(), "{}: {}", language, format_syntax_errors(&errors));
+        }
+    }
+
+    #[test]
+    fn reports_errors_in_invalid_code_in_every_supported_language() {
+        for (language, _, invalid) in SAMPLES {
+            let errors = check_syntax(invalid, language).unwrap();
+            assert!(!errors.is_empty(), "{}", language);
+            assert!(errors.iter().all(|error| error.line >= 1 && error.column >= 1), "{}", language);
+        }
+    }
+
+    #[test]
+    fn parses_typescript_with_jsx_using_the_tsx_grammar() {
+        let code = "import { render } from '@testing-library/react';\n\ntest('renders', () => {\n  const { getByText } = render(<Button label=\"Save\" />);\n  expect(getByText('Save')).toBeTruthy();\n});\n";
+        assert!(check_syntax(code, "typescript").unwrap().is_empty());
+
+        // Type assertions in angle brackets only parse with the plain grammar
+        assert!(check_syntax("const value = <number>input;\n", "typescript").unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_the_position_of_an_error() {
+        let errors = check_syntax("def test_adds():\n    assert add(1, 2) == 3\n\ndef broken(:\n    pass\n", "python").unwrap();
+        assert_eq!(errors[0].line, 4);
+        assert!(format_syntax_errors(&errors).starts_with("line 4, column "));
+    }
+
+    #[test]
+    fn skips_languages_without_a_grammar() {
+        assert!(!is_supported("ruby"));
+        assert!(check_syntax("this is not ruby {", "ruby").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+/// A fenced code block found in an LLM response
+struct CodeBlock<'a> {
+    /// The info string after the opening fence, lowercased (e.g. "rust", "ts")
+    tag: String,
+    /// The code between the fences
+    code: &'a str,
+}
+
+/// Fence tags that identify code written in the given language
+fn language_tags(language: &str) -> &'static [&'static str] {
+    match language.to_lowercase().as_str() {
+        "rust" => &["rust", "rs"],
+        "python" => &["python", "py", "python3"],
+        "typescript" => &["typescript", "ts", "tsx"],
+        "javascript" => &["javascript", "js", "jsx", "mjs", "cjs"],
+        "react" => &["tsx", "jsx", "typescript", "javascript", "ts", "js"],
+        "go" => &["go", "golang"],
+        "java" => &["java"],
+        "c#" | "csharp" => &["csharp", "cs", "c#"],
+        "ruby" => &["ruby", "rb"],
+        "php" => &["php"],
+        "kotlin" => &["kotlin", "kt"],
+        "swift" => &["swift"],
+        "c++" | "cpp" => &["cpp", "c++", "cc"],
+        "c" => &["c"],
+        _ => &[],
+    }
+}
+
+/// Markers that show a piece of code actually contains tests for the given language
+fn test_markers(language: &str) -> &'static [&'static str] {
+    match language.to_lowercase().as_str() {
+        "rust" => &["#[test]", "#[tokio::test", "#[rstest"],
+        "python" => &["def test_", "async def test_", "class Test", "unittest.TestCase"],
+        "typescript" | "javascript" | "react" => &["describe(", "it(", "test("],
+        "go" => &["func Test"],
+        "java" | "kotlin" => &["@Test", "@ParameterizedTest"],
+        "c#" | "csharp" => &["[Fact]", "[Theory]", "[Test]", "[TestMethod]"],
+        "ruby" => &["describe ", "it '", "it \"", "def test_"],
+        "php" => &["function test", "@test"],
+        "swift" => &["func test", "XCTestCase"],
+        "c++" | "cpp" => &["TEST(", "TEST_F(", "TEST_CASE("],
+        "c" => &["TEST_ASSERT", "RUN_TEST(", "void test_"],
+        _ => &[
+            "#[test]",
+            "def test_",
+            "describe(",
+            "it(",
+            "test(",
+            "func Test",
+            "@Test",
+            "[Fact]",
+            "TEST(",
+        ],
+    }
+}
+
+/// Returns true if the code contains at least one recognizable test construct
+pub fn contains_tests(code: &str, language: &str) -> bool {
+    test_markers(language).iter().any(|marker| contains_marker(code, marker))
+}
+
+/// Whether the code contains a test marker
+///
+/// A marker that is a call, like `it(`, only matches the whole function name (so not
+/// `submit(` or `regex.test(`), and allows whitespace before the parenthesis.
+fn contains_marker(code: &str, marker: &str) -> bool {
+    let Some(name) = marker.strip_suffix('(') else {
+        return code.contains(marker);
+    };
+
+    code.match_indices(name).any(|(start, _)| {
+        let preceded_by_name = code[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | '.'));
+        !preceded_by_name && code[start + name.len()..].trim_start().starts_with('(')
+    })
+}
+
+/// Pulls the test code for the target language out of a raw LLM response
+///
+/// Picks the fenced code block tagged with the target language (or the largest
+/// untagged block), drops any prose around it, and rejects the response if the
+/// result has no recognizable test constructs.
+pub fn extract_test_code(response: &str, language: &str) -> Result<String, String> {
+    let blocks = find_code_blocks(response);
+
+    let code = if blocks.is_empty() {
+        // No fences - the model followed the "code only" instruction, or mixed prose in
+        strip_commentary(response, language)
+    } else {
+        select_block(&blocks, language).to_string()
+    };
+
+    let code = code.trim().to_string();
+    if code.is_empty() {
+        return Err("The generated response did not contain any code".to_string());
+    }
+
+    if !contains_tests(&code, language) {
+        return Err(format!(
+            "The generated response does not contain any recognizable {} test cases",
+            language
+        ));
+    }
+
+    Ok(format!("{}\n", code))
+}
+
+/// Finds all fenced code blocks in the response
+/// An unterminated final block (e.g. a truncated response) runs to the end of the text
+fn find_code_blocks(response: &str) -> Vec<CodeBlock<'_>> {
+    let mut blocks = Vec::new();
+    let mut rest = response;
+
+    while let Some(start) = rest.find("```") {
+        let after_fence = &rest[start + 3..];
+        let (info, body) = match after_fence.find('\n') {
+            Some(newline) => (&after_fence[..newline], &after_fence[newline + 1..]),
+            None => break,
+        };
+
+        let tag = info.split_whitespace().next().unwrap_or("").to_lowercase();
+
+        match body.find("\n```").map(|end| end + 1).or_else(|| body.strip_prefix("```").map(|_| 0)) {
+            Some(end) => {
+                blocks.push(CodeBlock { tag, code: &body[..end] });
+                // Skip past the closing fence and the rest of its line
+                let after_close = &body[end + 3..];
+                rest = match after_close.find('\n') {
+                    Some(newline) => &after_close[newline + 1..],
+                    None => "",
+                };
+            }
+            None => {
+                blocks.push(CodeBlock { tag, code: body });
+                break;
+            }
+        }
+    }
+
+    blocks
+}
+
+/// Chooses the block most likely to hold the tests for the target language
+fn select_block<'a>(blocks: &[CodeBlock<'a>], language: &str) -> &'a str {
+    let tags = language_tags(language);
+
+    let by_size = |candidates: Vec<&CodeBlock<'a>>| {
+        candidates
+            .into_iter()
+            .max_by_key(|block| (contains_tests(block.code, language), block.code.len()))
+            .map(|block| block.code)
+    };
+
+    let tagged: Vec<&CodeBlock<'a>> = blocks.iter().filter(|b| tags.contains(&b.tag.as_str())).collect();
+    if let Some(code) = by_size(tagged) {
+        return code;
+    }
+
+    let untagged: Vec<&CodeBlock<'a>> = blocks.iter().filter(|b| b.tag.is_empty()).collect();
+    if let Some(code) = by_size(untagged) {
+        return code;
+    }
+
+    by_size(blocks.iter().collect()).unwrap_or("")
+}
+
+/// Removes leading and trailing prose from an unfenced response
+/// Everything before the first line that looks like code and after the last one is dropped
+fn strip_commentary(response: &str, language: &str) -> String {
+    let lines: Vec<&str> = response.lines().collect();
+
+    let first = lines.iter().position(|line| looks_like_code(line, language));
+    let last = lines.iter().rposition(|line| looks_like_code(line, language));
+
+    match (first, last) {
+        (Some(first), Some(last)) => lines[first..=last].join("\n"),
+        _ => response.to_string(),
+    }
+}
+
+/// Heuristic check for whether a line is code rather than prose
+fn looks_like_code(line: &str, language: &str) -> bool {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return false;
+    }
+
+    let code_starts = [
+        "use ", "mod ", "fn ", "pub ", "#[", "impl ", "struct ", "enum ", "let ", "const ",
+        "import ", "from ", "export ", "def ", "class ", "async ", "@", "describe(", "it(", "test(",
+        "expect(", "assert", "package ", "func ", "return ", "//", "#", "/*", "}", "{", ")",
+        "]", "public ", "private ", "namespace ", "require", "module.exports", "var ", "if ", "for ",
+        "while ", "with ", "try", "except", "catch", "beforeEach(", "afterEach(", "jest.", "vi.",
+    ];
+
+    code_starts.iter().any(|start| trimmed.starts_with(start))
+        || trimmed.ends_with(';')
+        || trimmed.ends_with('{')
+        || contains_tests(trimmed, language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_the_block_in_the_target_language() {
+        let response = "Here are the tests:\n\n```python\nprint('setup')\n```\n\n```ts\nimport { add } from './math';\n\ndescribe('add', () => {\n  it ('adds', () => expect(add(1, 2)).toBe(3));\n});\n```\nThese cover addition.";
+
+        let code = extract_test_code(response, "typescript").unwrap();
+        assert!(code.starts_with("import { add }"));
+        assert!(code.ends_with("});\n"));
+    }
+
+    #[test]
+    fn strips_prose_around_unfenced_code() {
+        let response = "Sure, here you go.\n#[test]\nfn adds() {\n    assert_eq!(add(1, 2), 3);\n}\nLet me know if you need more.";
+        assert_eq!(
+            extract_test_code(response, "rust").unwrap(),
+            "#[test]\nfn adds() {\n    assert_eq!(add(1, 2), 3);\n}\n"
+        );
+    }
+
+    #[test]
+    fn rejects_code_without_tests() {
+        let response = "```rust\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n```";
+        assert!(extract_test_code(response, "rust").unwrap_err().contains("recognizable rust test cases"));
+        assert!(extract_test_code("   ", "rust").unwrap_err().contains("did not contain any code"));
+    }
+
+    #[test]
+    fn does_not_mistake_calls_that_end_in_a_marker_for_tests() {
+        let response = "```js\nconst form = init(config);\nform.submit(data);\nawait wait(100);\nif (/^\\d+$/.test(value)) {\n  latest(value);\n}\n```";
+
+        for language in ["javascript", "typescript", "unknown"] {
+            assert!(extract_test_code(response, language).is_err(), "{}", language);
+        }
+    }
+
+    #[test]
+    fn matches_test_calls_by_whole_name() {
+        assert!(contains_tests("test('adds', () => {});", "javascript"));
+        assert!(contains_tests("  it (\"adds\", () => {});", "javascript"));
+        assert!(contains_tests("describe\n('math', () => {});", "typescript"));
+        assert!(!contains_tests("this.submit(form); reinit();", "javascript"));
+        assert!(!contains_tests("const it = 1; test.value = 2;", "javascript"));
+        assert!(!contains_tests("MY_TEST(thing);", "cpp"));
+        assert!(contains_tests("TEST(MathTest, Adds) {}", "cpp"));
+    }
+}
@@ -0,0 +1,379 @@
+/// Merges newly generated test cases into an existing test file
+///
+/// Imports the additions need are added after the existing imports (skipping ones
+/// already present), and the new cases are appended - inside the existing
+/// `mod tests` block for Rust, or inside the existing test class for Java/C#.
+pub fn merge_tests(existing: &str, additions: &str, language: &str) -> String {
+    let language = language.to_lowercase();
+    let (addition_imports, addition_body) = split_imports(additions, &language);
+
+    let existing_lines: Vec<&str> = existing.lines().collect();
+    let existing_imports: Vec<&str> = existing_lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| is_import_line(line, &language))
+        .collect();
+
+    let already_imported = |import: &str| {
+        existing_imports.contains(&import.trim())
+            // Go imports usually live in an `import (...)` block; match on the quoted path
+            || (language == "go" && import.find('"').is_some_and(|start| existing.contains(&import[start..])))
+    };
+
+    let new_imports: Vec<String> = addition_imports
+        .into_iter()
+        .filter(|line| !already_imported(line))
+        .collect();
+
+    // Imports inside an unwrapped `mod tests` block may duplicate ones the existing module has
+    let body = unwrap_container(&addition_body, &language)
+        .lines()
+        .filter(|line| !(is_import_line(line.trim(), &language) && already_imported(line)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let body = body.trim_matches('\n');
+
+    // Insert the new imports right after the last existing import (or at the top)
+    let mut lines: Vec<String> = existing_lines.iter().map(|l| l.to_string()).collect();
+    if !new_imports.is_empty() {
+        let last_import = lines.iter().rposition(|line| is_import_line(line.trim(), &language));
+        let insert_at = last_import
+            .map(|index| index + 1)
+            .unwrap_or_else(|| header_end(&lines, &language));
+        // Match the indentation of the import they follow (e.g. inside `mod tests`)
+        let indent = last_import
+            .map(|index| {
+                let line = &lines[index];
+                line[..line.len() - line.trim_start().len()].to_string()
+            })
+            .unwrap_or_default();
+        for (offset, import) in new_imports.iter().enumerate() {
+            lines.insert(insert_at + offset, format!("{}{}", indent, import));
+        }
+    }
+
+    if body.is_empty() {
+        return finish(lines);
+    }
+
+    match container_close(&lines, &language) {
+        Some(close) => {
+            // Indent the new cases to match the container's members
+            let indent = member_indent(&lines, close);
+            let indented: Vec<String> = body
+                .lines()
+                .map(|line| if line.trim().is_empty() { String::new() } else { format!("{}{}", indent, line) })
+                .collect();
+            lines.insert(close, String::new());
+            for (offset, line) in indented.into_iter().enumerate() {
+                lines.insert(close + 1 + offset, line);
+            }
+        }
+        None => {
+            while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                lines.pop();
+            }
+            lines.push(String::new());
+            lines.extend(body.lines().map(|l| l.to_string()));
+        }
+    }
+
+    finish(lines)
+}
+
+/// Joins lines back into a file with a single trailing newline
+fn finish(lines: Vec<String>) -> String {
+    let mut content = lines.join("\n");
+    content.truncate(content.trim_end().len());
+    content.push('\n');
+    content
+}
+
+/// Returns true for a single-line import statement in the given language
+fn is_import_line(line: &str, language: &str) -> bool {
+    match language {
+        "rust" => line.starts_with("use ") && line.ends_with(';'),
+        "python" => {
+            (line.starts_with("import ") || (line.starts_with("from ") && line.contains(" import ")))
+                && !line.ends_with('(')
+        }
+        "javascript" | "typescript" | "react" => {
+            (line.starts_with("import ") && (line.contains(" from ") || line.starts_with("import '") || line.starts_with("import \"")))
+                || (line.contains("require(") && (line.starts_with("const ") || line.starts_with("let ") || line.starts_with("var ")))
+        }
+        "java" | "kotlin" => line.starts_with("import "),
+        "c#" | "csharp" => line.starts_with("using ") && line.ends_with(';'),
+        "go" => line.starts_with("import \"") || (line.starts_with("import ") && line.ends_with('"')),
+        "ruby" => line.starts_with("require "),
+        _ => false,
+    }
+}
+
+/// Splits generated additions into leading import lines and the rest
+/// Package declarations and Go import blocks are dropped since the existing file has them
+fn split_imports(additions: &str, language: &str) -> (Vec<String>, String) {
+    let mut imports = Vec::new();
+    let mut body = Vec::new();
+    let mut in_go_import_block = false;
+
+    for line in additions.lines() {
+        let trimmed = line.trim();
+
+        if language == "go" {
+            if trimmed.starts_with("package ") {
+                continue;
+            }
+            if trimmed == "import (" {
+                in_go_import_block = true;
+                continue;
+            }
+            if in_go_import_block {
+                if trimmed == ")" {
+                    in_go_import_block = false;
+                } else if !trimmed.is_empty() {
+                    imports.push(format!("import {}", trimmed));
+                }
+                continue;
+            }
+        }
+        if (language == "java" || language == "kotlin") && trimmed.starts_with("package ") {
+            continue;
+        }
+
+        if is_import_line(trimmed, language) {
+            imports.push(trimmed.to_string());
+        } else {
+            body.push(line);
+        }
+    }
+
+    (imports, body.join("\n"))
+}
+
+/// Strips a `mod tests { ... }` (Rust) or class wrapper (Java/C#) from the additions
+/// so that only the new cases are inserted into the existing container
+fn unwrap_container(body: &str, language: &str) -> String {
+    let lines: Vec<&str> = body.lines().collect();
+
+    let is_open = |line: &str| match language {
+        "rust" => line.trim_start().starts_with("mod tests") && line.trim_end().ends_with('{'),
+        "java" | "kotlin" | "c#" | "csharp" => line.contains("class ") && line.trim_end().ends_with('{'),
+        _ => false,
+    };
+
+    let Some(open) = lines.iter().position(|l| is_open(l)) else {
+        return body.to_string();
+    };
+    let Some(close) = lines.iter().rposition(|l| l.trim() == "}") else {
+        return body.to_string();
+    };
+    if close <= open {
+        return body.to_string();
+    }
+
+    // Anything outside the wrapper (such as its `#[cfg(test)]` attribute) is dropped
+    let inner = &lines[open + 1..close];
+    let indent = inner
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    inner
+        .iter()
+        .map(|l| if l.len() >= indent { &l[indent..] } else { l.trim_start() })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Index of the closing brace of the existing container new cases should go into
+fn container_close(lines: &[String], language: &str) -> Option<usize> {
+    let open = match language {
+        "rust" => lines.iter().position(|l| {
+            let t = l.trim_start();
+            t.starts_with("mod tests") && t.trim_end().ends_with('{')
+        })?,
+        "java" | "kotlin" | "c#" | "csharp" => lines.iter().position(|l| l.contains("class ") && l.trim_end().ends_with('{'))?,
+        _ => return None,
+    };
+
+    let mut depth: i32 = 0;
+    for (index, line) in lines.iter().enumerate().skip(open) {
+        for c in line.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth == 0 {
+            return Some(index);
+        }
+    }
+    None
+}
+
+/// The indentation used by members of the container closing at `close`
+fn member_indent(lines: &[String], close: usize) -> String {
+    let closing_indent = lines[close].len() - lines[close].trim_start().len();
+    lines[..close]
+        .iter()
+        .rev()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .find(|indent| *indent > closing_indent)
+        .map(|indent| " ".repeat(indent))
+        .unwrap_or_else(|| " ".repeat(closing_indent + 4))
+}
+
+/// Where imports go in a file that has none yet (after a package line or shebang)
+fn header_end(lines: &[String], language: &str) -> usize {
+    lines
+        .iter()
+        .position(|l| {
+            let t = l.trim();
+            !(t.is_empty()
+                || t.starts_with("#!")
+                || (language == "go" || language == "java" || language == "kotlin") && t.starts_with("package ")
+                || t.starts_with("//!"))
+        })
+        .unwrap_or(lines.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn merges_rust_cases_into_the_existing_tests_module() {
+        let existing = r#"pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds() {
+        assert_eq!(add(1, 2), 3);
+    }
+}
+"#;
+        let additions = r#"#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn adds_negative_numbers() {
+        assert_eq!(add(-1, -2), -3);
+    }
+}"#;
+
+        let expected = r#"pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn adds() {
+        assert_eq!(add(1, 2), 3);
+    }
+
+    #[test]
+    fn adds_negative_numbers() {
+        assert_eq!(add(-1, -2), -3);
+    }
+}
+"#;
+        assert_eq!(merge_tests(existing, additions, "rust"), expected);
+    }
+
+    #[test]
+    fn merges_typescript_imports_and_appends_cases() {
+        let existing = "import { describe, it, expect } from 'vitest';\nimport { add } from './math';\n\ndescribe('add', () => {\n  it('adds', () => expect(add(1, 2)).toBe(3));\n});\n\n";
+        let additions = "import { describe, it, expect } from 'vitest';\nimport { sub } from './math';\n\ndescribe('sub', () => {\n  it('subtracts', () => expect(sub(3, 2)).toBe(1));\n});";
+
+        let expected = "import { describe, it, expect } from 'vitest';\nimport { add } from './math';\nimport { sub } from './math';\n\ndescribe('add', () => {\n  it('adds', () => expect(add(1, 2)).toBe(3));\n});\n\ndescribe('sub', () => {\n  it('subtracts', () => expect(sub(3, 2)).toBe(1));\n});\n";
+        assert_eq!(merge_tests(existing, additions, "typescript"), expected);
+    }
+
+    #[test]
+    fn merges_python_imports_after_a_shebang() {
+        let existing = "#!/usr/bin/env python\n\ndef test_add():\n    assert add(1, 2) == 3\n";
+        let additions = "import pytest\nfrom calc import add, sub\n\n\ndef test_sub():\n    assert sub(3, 2) == 1\n";
+
+        let expected = "#!/usr/bin/env python\n\nimport pytest\nfrom calc import add, sub\ndef test_add():\n    assert add(1, 2) == 3\n\ndef test_sub():\n    assert sub(3, 2) == 1\n";
+        assert_eq!(merge_tests(existing, additions, "python"), expected);
+    }
+
+    #[test]
+    fn skips_go_imports_already_in_an_import_block() {
+        let existing = "package calc\n\nimport (\n\t\"testing\"\n)\n\nfunc TestAdd(t *testing.T) {}\n";
+        let additions = "package calc\n\nimport (\n\t\"strings\"\n\t\"testing\"\n)\n\nfunc TestUpper(t *testing.T) {}\n";
+
+        let expected = "package calc\n\nimport \"strings\"\nimport (\n\t\"testing\"\n)\n\nfunc TestAdd(t *testing.T) {}\n\nfunc TestUpper(t *testing.T) {}\n";
+        assert_eq!(merge_tests(existing, additions, "go"), expected);
+    }
+
+    #[test]
+    fn merges_java_cases_into_the_existing_class() {
+        let existing = "package calc;\n\nimport org.junit.jupiter.api.Test;\n\nclass CalculatorTest {\n    @Test\n    void adds() {\n        assertEquals(3, new Calculator().add(1, 2));\n    }\n}\n";
+        let additions = "package calc;\n\nimport org.junit.jupiter.api.Test;\nimport static org.junit.jupiter.api.Assertions.assertEquals;\n\nclass CalculatorTest {\n    @Test\n    void subtracts() {\n        assertEquals(1, new Calculator().sub(3, 2));\n    }\n}\n";
+
+        let expected = "package calc;\n\nimport org.junit.jupiter.api.Test;\nimport static org.junit.jupiter.api.Assertions.assertEquals;\n\nclass CalculatorTest {\n    @Test\n    void adds() {\n        assertEquals(3, new Calculator().add(1, 2));\n    }\n\n    @Test\n    void subtracts() {\n        assertEquals(1, new Calculator().sub(3, 2));\n    }\n}\n";
+        assert_eq!(merge_tests(existing, additions, "java"), expected);
+    }
+
+    #[test]
+    fn appends_rust_cases_to_a_file_without_a_tests_module() {
+        let existing = "use calc::add;\n\n#[test]\nfn adds() {\n    assert_eq!(add(1, 2), 3);\n}\n";
+        let additions = "use calc::sub;\n\n#[test]\nfn subtracts() {\n    assert_eq!(sub(3, 2), 1);\n}\n";
+
+        let expected = "use calc::add;\nuse calc::sub;\n\n#[test]\nfn adds() {\n    assert_eq!(add(1, 2), 3);\n}\n\n#[test]\nfn subtracts() {\n    assert_eq!(sub(3, 2), 1);\n}\n";
+        assert_eq!(merge_tests(existing, additions, "rust"), expected);
+    }
+
+    #[test]
+    fn leaves_the_file_alone_when_nothing_is_added() {
+        let existing = "import pytest\n\ndef test_add():\n    assert add(1, 2) == 3\n";
+        assert_eq!(merge_tests(existing, "import pytest\n", "python"), existing);
+    }
+
+    #[test]
+    fn unwraps_only_a_test_container() {
+        let wrapped = "#[cfg(test)]\nmod tests {\n    use super::*;\n\n    #[test]\n    fn adds() {}\n}";
+        assert_eq!(unwrap_container(wrapped, "rust"), "use super::*;\n\n#[test]\nfn adds() {}");
+
+        let class = "public class MathTest {\n    @Test\n    public void adds() {}\n}";
+        assert_eq!(unwrap_container(class, "csharp"), "@Test\npublic void adds() {}");
+
+        // Bodies without a wrapper, and wrappers of other languages, are returned as they are
+        let bare = "#[test]\nfn adds() {\n}";
+        assert_eq!(unwrap_container(bare, "rust"), bare);
+        assert_eq!(unwrap_container(wrapped, "python"), wrapped);
+    }
+
+    #[test]
+    fn finds_the_brace_that_closes_the_container() {
+        let rust = lines("fn helper() {}\n\nmod tests {\n    #[test]\n    fn adds() {\n        let _ = { 1 };\n    }\n}\n\nfn after() {}");
+        assert_eq!(container_close(&rust, "rust"), Some(7));
+
+        let java = lines("class MathTest {\n    void adds() {}\n}");
+        assert_eq!(container_close(&java, "java"), Some(2));
+
+        assert_eq!(container_close(&lines("mod tests {\n    fn adds() {}\n"), "rust"), None);
+        assert_eq!(container_close(&lines("#[test]\nfn adds() {}"), "rust"), None);
+        assert_eq!(container_close(&lines("class Test:\n    pass"), "python"), None);
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+use crate::services::structured_output::outermost_json_object;
+
+/// A problem found in a test file, or a gap in what it tests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewFinding {
+    /// Line the finding refers to, starting at 1; see the list it is in for which file
+    /// A missing line is read as 0 and the finding dropped, rather than failing the review
+    #[serde(default)]
+    pub line: usize,
+    /// What is wrong or missing
+    pub issue: String,
+    /// How to fix it
+    #[serde(default)]
+    pub suggestion: String,
+}
+
+/// A test case the review suggests adding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestedCase {
+    /// Name for the new test
+    pub name: String,
+    /// What the test should check
+    #[serde(default)]
+    pub description: String,
+    /// Line of the source code the case exercises, starting at 1
+    /// A missing line is read as 0 and the case dropped, rather than failing the review
+    #[serde(default)]
+    pub line: usize,
+}
+
+/// A structured review of an existing test file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestReview {
+    /// Overall assessment of the test file
+    #[serde(default)]
+    pub summary: String,
+    /// Branches of the source code no test takes; lines are in the source file
+    #[serde(default)]
+    pub untested_branches: Vec<ReviewFinding>,
+    /// Assertions that would pass for wrong behavior; lines are in the test file
+    #[serde(default)]
+    pub weak_assertions: Vec<ReviewFinding>,
+    /// Mocks tied to implementation details rather than behavior; lines are in the test file
+    #[serde(default)]
+    pub brittle_mocks: Vec<ReviewFinding>,
+    #[serde(default)]
+    pub suggested_cases: Vec<SuggestedCase>,
+}
+
+/// Prefixes each line with its number, so the model can refer to lines exactly
+pub fn number_lines(code: &str) -> String {
+    let width = code.lines().count().max(1).to_string().len();
+    code.lines()
+        .enumerate()
+        .map(|(index, line)| format!("{:>width$} | {}", index + 1, line, width = width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses and validates a test review response
+///
+/// As with structured test suites, the outermost JSON object is parsed. Findings and cases
+/// that point at a line outside their file, or at no line, are dropped, since they can't be
+/// acted on.
+pub fn parse_test_review(response: &str, source_lines: usize, test_lines: usize) -> Result<TestReview, String> {
+    let json = outermost_json_object(response)
+        .ok_or_else(|| "The review response does not contain a JSON object".to_string())?;
+
+    let mut review: TestReview =
+        serde_json::from_str(json).map_err(|e| format!("The review response is not a valid test review: {}", e))?;
+
+    let in_range = |line: usize, lines: usize, kind: &str| {
+        let valid = (1..=lines).contains(&line);
+        if !valid {
+            log::warn!("Dropping {} review finding at line {} of a {}-line file", kind, line, lines);
+        }
+        valid
+    };
+    review
+        .untested_branches
+        .retain(|finding| in_range(finding.line, source_lines, "untested branch"));
+    review
+        .weak_assertions
+        .retain(|finding| in_range(finding.line, test_lines, "weak assertion"));
+    review
+        .brittle_mocks
+        .retain(|finding| in_range(finding.line, test_lines, "brittle mock"));
+    review
+        .suggested_cases
+        .retain(|case| !case.name.trim().is_empty() && in_range(case.line, source_lines, "suggested case"));
+
+    Ok(review)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_fenced_review() {
+        let response = r#"Here is the review:
+
+```json
+{
+  "summary": "Covers the happy path only.",
+  "untested_branches": [{"line": 4, "issue": "empty cart", "suggestion": "test total() of an empty cart"}],
+  "weak_assertions": [{"line": 2, "issue": "only checks the result is defined"}],
+  "brittle_mocks": [],
+  "suggested_cases": [{"name": "totals an empty cart", "description": "returns 0", "line": 4}]
+}
+```"#;
+
+        let review = parse_test_review(response, 10, 5).unwrap();
+        assert_eq!(review.summary, "Covers the happy path only.");
+        assert_eq!(review.untested_branches[0].line, 4);
+        assert_eq!(review.weak_assertions[0].suggestion, "");
+        assert_eq!(review.suggested_cases[0].name, "totals an empty cart");
+    }
+
+    #[test]
+    fn drops_findings_without_a_usable_line() {
+        let response = r#"{
+  "untested_branches": [{"line": 11, "issue": "past the end of the source"}, {"issue": "no line"}],
+  "weak_assertions": [{"line": 5, "issue": "checks nothing"}, {"line": 0, "issue": "before the start"}],
+  "brittle_mocks": [{"line": 6, "issue": "past the end of the tests"}],
+  "suggested_cases": [
+    {"name": "no line", "description": "the model left the line out"},
+    {"name": "   ", "line": 3},
+    {"name": "rejects a negative price", "line": 10}
+  ]
+}"#;
+
+        let review = parse_test_review(response, 10, 5).unwrap();
+        assert!(review.untested_branches.is_empty());
+        assert_eq!(review.weak_assertions.len(), 1);
+        assert!(review.brittle_mocks.is_empty());
+        assert_eq!(review.suggested_cases.len(), 1);
+        assert_eq!(review.suggested_cases[0].name, "rejects a negative price");
+    }
+
+    #[test]
+    fn rejects_responses_that_are_not_a_review() {
+        assert!(parse_test_review("The tests look fine.", 10, 5)
+            .unwrap_err()
+            .contains("does not contain a JSON object"));
+        assert!(parse_test_review("{\"summary\": 3}", 10, 5)
+            .unwrap_err()
+            .contains("not a valid test review"));
+    }
+
+    #[test]
+    fn numbers_lines_to_the_same_width() {
+        let code = (1..=10).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+        let numbered = number_lines(&code);
+        assert!(numbered.starts_with(" 1 | line 1\n 2 | line 2"));
+        assert!(numbered.ends_with("10 | line 10"));
+    }
+}
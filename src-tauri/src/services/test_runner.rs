@@ -0,0 +1,174 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long a single test run may take before it is treated as a failure
+const TEST_RUN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How much of the runner output is kept (from the end, where failures are reported)
+const MAX_OUTPUT_CHARS: usize = 12_000;
+
+/// Result of running a generated test file
+#[derive(Debug, Clone, Serialize)]
+pub struct TestRunOutcome {
+    /// Whether the run exited successfully
+    pub passed: bool,
+    /// The command line that was run, for display
+    pub command: String,
+    /// Combined stdout and stderr, truncated to the last `MAX_OUTPUT_CHARS` characters
+    pub output: String,
+}
+
+/// A resolved command for running one test file
+struct TestCommand {
+    program: String,
+    args: Vec<String>,
+    working_dir: PathBuf,
+}
+
+/// Runs a single test file with the project's test framework
+///
+/// # Arguments
+/// * `project_dir` - Root directory of the project
+/// * `test_file` - Path of the test file relative to `project_dir`
+/// * `language` - Language of the source file
+/// * `test_framework` - Framework name, if known (e.g. "vitest", "pytest")
+pub async fn run_test_file(
+    project_dir: &Path,
+    test_file: &str,
+    language: &str,
+    test_framework: Option<&str>,
+) -> Result<TestRunOutcome, String> {
+    let command = resolve_command(project_dir, test_file, language, test_framework)?;
+    let command_line = format!("{} {}", command.program, command.args.join(" "));
+    log::info!("Running generated tests: {} (in {})", command_line, command.working_dir.display());
+
+    let child = Command::new(&command.program)
+        .args(&command.args)
+        .current_dir(&command.working_dir)
+        .env("CI", "true")
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Make sure the process dies with the request if it is cancelled
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run `{}`: {}", command_line, e))?;
+
+    let output = match tokio::time::timeout(TEST_RUN_TIMEOUT, child.wait_with_output()).await {
+        Ok(output) => output.map_err(|e| format!("Failed to run `{}`: {}", command_line, e))?,
+        Err(_) => {
+            return Ok(TestRunOutcome {
+                passed: false,
+                command: command_line,
+                output: format!("Test run timed out after {} seconds", TEST_RUN_TIMEOUT.as_secs()),
+            })
+        }
+    };
+
+    let mut combined = String::from_utf8_lossy(&output.stdout).into_owned();
+    combined.push_str(&String::from_utf8_lossy(&output.stderr));
+
+    Ok(TestRunOutcome {
+        passed: output.status.success(),
+        command: command_line,
+        output: truncate_output(&combined),
+    })
+}
+
+/// Works out which command runs the given test file
+fn resolve_command(
+    project_dir: &Path,
+    test_file: &str,
+    language: &str,
+    test_framework: Option<&str>,
+) -> Result<TestCommand, String> {
+    let framework = test_framework.map(|f| f.to_lowercase()).unwrap_or_default();
+    let test_path = Path::new(test_file);
+
+    let command = |program: &str, args: Vec<String>, working_dir: &Path| TestCommand {
+        program: program.to_string(),
+        args,
+        working_dir: working_dir.to_path_buf(),
+    };
+
+    match language.to_lowercase().as_str() {
+        "javascript" | "typescript" | "react" => {
+            let args = if framework.contains("vitest") {
+                vec!["vitest".to_string(), "run".to_string(), test_file.to_string()]
+            } else if framework.contains("mocha") {
+                vec!["mocha".to_string(), test_file.to_string()]
+            } else if framework.contains("ava") {
+                vec!["ava".to_string(), test_file.to_string()]
+            } else {
+                vec!["jest".to_string(), "--ci".to_string(), test_file.to_string()]
+            };
+            Ok(command("npx", args, project_dir))
+        }
+        "python" => Ok(command(
+            "python",
+            vec!["-m".to_string(), "pytest".to_string(), "-q".to_string(), test_file.to_string()],
+            project_dir,
+        )),
+        "rust" => {
+            // Integration tests in `<crate>/tests/` are the only test files Cargo compiles without
+            // a `mod` declaration; running the crate's suite for any other file would pass
+            // without ever building it
+            let tests_dir = test_path.parent().filter(|p| p.file_name().is_some_and(|n| n == "tests"));
+            let crate_dir = tests_dir
+                .map(|dir| project_dir.join(dir.parent().unwrap_or(Path::new(""))))
+                .filter(|dir| dir.join("Cargo.toml").exists());
+
+            match (crate_dir, test_path.file_stem().and_then(|s| s.to_str())) {
+                (Some(crate_dir), Some(stem)) => Ok(command(
+                    "cargo",
+                    vec!["test".to_string(), "--test".to_string(), stem.to_string()],
+                    &crate_dir,
+                )),
+                _ => Err(format!(
+                    "{} is not compiled: Rust test files must be in the tests/ directory of a crate",
+                    test_file
+                )),
+            }
+        }
+        "go" => {
+            let package = test_path
+                .parent()
+                .map(|p| p.to_string_lossy().to_string())
+                .filter(|p| !p.is_empty())
+                .unwrap_or_else(|| ".".to_string());
+            Ok(command("go", vec!["test".to_string(), format!("./{}", package)], project_dir))
+        }
+        "java" => {
+            let class_name = test_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .ok_or_else(|| format!("Invalid test file name: {}", test_file))?;
+
+            if project_dir.join("pom.xml").exists() {
+                Ok(command("mvn", vec!["-q".to_string(), "test".to_string(), format!("-Dtest={}", class_name)], project_dir))
+            } else if project_dir.join("gradlew").exists() {
+                Ok(command("./gradlew", vec!["test".to_string(), "--tests".to_string(), class_name.to_string()], project_dir))
+            } else if project_dir.join("build.gradle").exists() || project_dir.join("build.gradle.kts").exists() {
+                Ok(command("gradle", vec!["test".to_string(), "--tests".to_string(), class_name.to_string()], project_dir))
+            } else {
+                Err("No Maven or Gradle build found to run Java tests".to_string())
+            }
+        }
+        _ => Err(format!("Running tests is not supported for language: {}", language)),
+    }
+}
+
+/// Keeps the end of the output, where test runners report failures
+fn truncate_output(output: &str) -> String {
+    let char_count = output.chars().count();
+    if char_count <= MAX_OUTPUT_CHARS {
+        return output.to_string();
+    }
+
+    let tail: String = output.chars().skip(char_count - MAX_OUTPUT_CHARS).collect();
+    format!("[... output truncated ...]\n{}", tail)
+}
@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// File in the app data directory that holds one usage record per line
+pub const USAGE_LEDGER_FILE: &str = "usage.jsonl";
+
+/// Rough number of characters per token, used when a provider doesn't report usage
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Prices in USD per million input and output tokens, matched on model name prefix
+///
+/// More specific prefixes must come before shorter ones that would also match.
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("o3-mini", 1.1, 4.4),
+    ("o4-mini", 1.1, 4.4),
+];
+
+/// Token usage and timing of one agent call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CallUsage {
+    /// Model that served the call
+    pub model: String,
+    /// Tokens sent to the model
+    pub input_tokens: u64,
+    /// Tokens generated by the model
+    pub output_tokens: u64,
+    /// True when the provider didn't report usage and the counts were estimated from text length
+    pub estimated: bool,
+    /// Wall-clock time of the call in milliseconds, including retries
+    pub latency_ms: u64,
+}
+
+impl CallUsage {
+    /// Adds the usage of another call, e.g. another chunk of the same request
+    pub fn add(&mut self, other: &CallUsage) {
+        if self.model.is_empty() {
+            self.model = other.model.clone();
+        }
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.estimated |= other.estimated;
+        self.latency_ms += other.latency_ms;
+    }
+
+    /// Cost of the call in USD, if the model's price is known
+    pub fn cost_usd(&self) -> Option<f64> {
+        let model = self.model.to_lowercase();
+        MODEL_PRICES
+            .iter()
+            .find(|(prefix, _, _)| model.starts_with(prefix))
+            .map(|(_, input, output)| {
+                (self.input_tokens as f64 * input + self.output_tokens as f64 * output) / 1_000_000.0
+            })
+    }
+}
+
+/// Estimates a token count from a length in characters
+pub fn estimate_tokens(chars: usize) -> u64 {
+    chars.div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+/// What kind of agent call a usage record is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageOperation {
+    /// `agent_simple_invoke`
+    SimpleInvoke,
+    /// `agent_chain_invoke`
+    Chain,
+    /// Test generation, including each chunk of a chunked request
+    TestGeneration,
+    /// Repair of a failing generated test file
+    TestRepair,
+    /// Review of an existing test file
+    TestReview,
+    /// A turn in a chat session
+    Chat,
+}
+
+impl UsageOperation {
+    fn as_str(self) -> &'static str {
+        match self {
+            UsageOperation::SimpleInvoke => "simple_invoke",
+            UsageOperation::Chain => "chain",
+            UsageOperation::TestGeneration => "test_generation",
+            UsageOperation::TestRepair => "test_repair",
+            UsageOperation::TestReview => "test_review",
+            UsageOperation::Chat => "chat",
+        }
+    }
+}
+
+/// One line of the usage ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// When the call finished, in RFC 3339 format
+    pub timestamp: String,
+    /// Local date of the call (`YYYY-MM-DD`), used for daily totals
+    pub day: String,
+    pub operation: UsageOperation,
+    /// Project directory the call was made for, if any
+    pub project: Option<String>,
+    /// ID of the agent request the call belonged to
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub usage: CallUsage,
+    /// Cost in USD, if the model's price is known
+    pub cost_usd: Option<f64>,
+}
+
+impl UsageRecord {
+    /// Creates a record for a call that just finished
+    pub fn new(operation: UsageOperation, project: Option<String>, request_id: Option<String>, usage: CallUsage) -> Self {
+        let now = chrono::Local::now();
+        Self {
+            timestamp: now.to_rfc3339(),
+            day: now.format("%Y-%m-%d").to_string(),
+            operation,
+            project,
+            request_id,
+            cost_usd: usage.cost_usd(),
+            usage,
+        }
+    }
+}
+
+/// How usage totals are grouped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGrouping {
+    Day,
+    Project,
+    Operation,
+    Model,
+}
+
+impl UsageGrouping {
+    /// Parses a grouping name as sent by the frontend
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "day" => Ok(UsageGrouping::Day),
+            "project" => Ok(UsageGrouping::Project),
+            "operation" => Ok(UsageGrouping::Operation),
+            "model" => Ok(UsageGrouping::Model),
+            other => Err(format!(
+                "Invalid usage grouping: {} (expected day, project, operation or model)",
+                other
+            )),
+        }
+    }
+
+    fn key(self, record: &UsageRecord) -> String {
+        match self {
+            UsageGrouping::Day => record.day.clone(),
+            UsageGrouping::Project => record.project.clone().unwrap_or_default(),
+            UsageGrouping::Operation => record.operation.as_str().to_string(),
+            UsageGrouping::Model => record.usage.model.clone(),
+        }
+    }
+}
+
+/// Which records to include in a usage query
+#[derive(Debug, Clone, Default)]
+pub struct UsageFilter {
+    /// First day to include (`YYYY-MM-DD`)
+    pub since: Option<String>,
+    /// Last day to include (`YYYY-MM-DD`)
+    pub until: Option<String>,
+    /// Only include calls made for this project
+    pub project: Option<String>,
+}
+
+impl UsageFilter {
+    fn matches(&self, record: &UsageRecord) -> bool {
+        self.since.as_deref().map_or(true, |since| record.day.as_str() >= since)
+            && self.until.as_deref().map_or(true, |until| record.day.as_str() <= until)
+            && self.project.as_ref().map_or(true, |project| record.project.as_ref() == Some(project))
+    }
+}
+
+/// Summed usage of a group of calls
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotal {
+    /// The day, project, operation or model the total is for (empty for calls without a project)
+    pub key: String,
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost in USD of the calls whose model price is known
+    pub cost_usd: f64,
+    /// Calls whose model price is unknown and which are not included in `cost_usd`
+    pub unpriced_calls: u64,
+    /// Calls whose token counts were estimated
+    pub estimated_calls: u64,
+    pub latency_ms: u64,
+}
+
+/// Append-only log of the token usage of every agent call, stored in the app data directory
+#[derive(Clone, Default)]
+pub struct UsageLedger {
+    /// Path of the ledger file, set once the app data directory is known
+    path: Arc<Mutex<Option<PathBuf>>>,
+}
+
+impl UsageLedger {
+    /// Create a ledger that doesn't persist anything until a directory is set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the directory the ledger file is stored in
+    pub fn set_dir(&self, dir: PathBuf) {
+        if let Ok(mut path) = self.path.lock() {
+            log::info!("Recording agent usage in {}", dir.display());
+            *path = Some(dir.join(USAGE_LEDGER_FILE));
+        }
+    }
+
+    /// Append a record to the ledger
+    /// Failures are logged rather than returned so that accounting never fails a request
+    pub fn record(&self, record: &UsageRecord) {
+        // The lock also keeps concurrent requests from interleaving their lines
+        let Ok(path) = self.path.lock() else {
+            return;
+        };
+        let Some(path) = path.as_ref() else {
+            return;
+        };
+
+        let result = serde_json::to_string(record)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| e.to_string())?;
+                writeln!(file, "{}", line).map_err(|e| e.to_string())
+            });
+
+        if let Err(e) = result {
+            log::error!("Failed to record agent usage in {}: {}", path.display(), e);
+        }
+    }
+
+    /// Read every record matching the filter
+    pub fn records(&self, filter: &UsageFilter) -> Result<Vec<UsageRecord>, String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| e.to_string())?
+            .clone()
+            .ok_or_else(|| "The usage ledger is not available".to_string())?;
+
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read usage ledger {}: {}", path.display(), e))?;
+
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<UsageRecord>(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    log::warn!("Skipping malformed usage record: {}", e);
+                    None
+                }
+            })
+            .filter(|record| filter.matches(record))
+            .collect())
+    }
+
+    /// Sum the usage of the records matching the filter, grouped by day, project, operation or model
+    pub fn totals(&self, grouping: UsageGrouping, filter: &UsageFilter) -> Result<Vec<UsageTotal>, String> {
+        let mut totals: BTreeMap<String, UsageTotal> = BTreeMap::new();
+
+        for record in self.records(filter)? {
+            let key = grouping.key(&record);
+            let total = totals.entry(key.clone()).or_insert_with(|| UsageTotal {
+                key,
+                ..Default::default()
+            });
+
+            total.calls += 1;
+            total.input_tokens += record.usage.input_tokens;
+            total.output_tokens += record.usage.output_tokens;
+            total.latency_ms += record.usage.latency_ms;
+            match record.cost_usd {
+                Some(cost) => total.cost_usd += cost,
+                None => total.unpriced_calls += 1,
+            }
+            if record.usage.estimated {
+                total.estimated_calls += 1;
+            }
+        }
+
+        Ok(totals.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(model: &str, input_tokens: u64, output_tokens: u64) -> CallUsage {
+        CallUsage {
+            model: model.to_string(),
+            input_tokens,
+            output_tokens,
+            estimated: false,
+            latency_ms: 100,
+        }
+    }
+
+    /// A record of a call on the given day
+    fn record(day: &str, project: Option<&str>, usage: CallUsage) -> UsageRecord {
+        let mut record = UsageRecord::new(UsageOperation::TestGeneration, project.map(String::from), None, usage);
+        record.day = day.to_string();
+        record
+    }
+
+    /// A ledger in a fresh directory
+    fn ledger(name: &str) -> (UsageLedger, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("usage-ledger-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let ledger = UsageLedger::new();
+        ledger.set_dir(dir.clone());
+        (ledger, dir)
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn prices_calls_by_model() {
+        // $3 per million input tokens and $15 per million output tokens
+        assert_close(usage("claude-3-7-sonnet-20250219", 1_000, 2_000).cost_usd().unwrap(), 0.033);
+        // The mini model isn't priced as the larger one its name starts with
+        assert_close(usage("gpt-4o-mini", 1_000_000, 1_000_000).cost_usd().unwrap(), 0.75);
+        assert_close(usage("GPT-4o-2024-08-06", 1_000_000, 0).cost_usd().unwrap(), 2.5);
+        assert_eq!(usage("llama3", 1_000, 1_000).cost_usd(), None);
+
+        let record = UsageRecord::new(UsageOperation::Chat, None, None, usage("claude-3-5-haiku-latest", 10_000, 0));
+        assert_close(record.cost_usd.unwrap(), 0.008);
+    }
+
+    #[test]
+    fn adds_up_the_calls_of_a_request() {
+        let mut total = CallUsage::default();
+        total.add(&usage("gpt-4o", 100, 10));
+        total.add(&CallUsage { estimated: true, ..usage("gpt-4o", 50, 5) });
+
+        assert_eq!((total.model.as_str(), total.input_tokens, total.output_tokens), ("gpt-4o", 150, 15));
+        assert!(total.estimated);
+        assert_eq!(total.latency_ms, 200);
+        assert_eq!(estimate_tokens(9), 3);
+    }
+
+    #[test]
+    fn totals_appended_records() {
+        let (ledger, dir) = ledger("totals");
+        ledger.record(&record("2026-10-01", Some("/work/shop"), usage("claude-3-7-sonnet", 1_000, 2_000)));
+        ledger.record(&record("2026-10-01", Some("/work/shop"), usage("claude-3-7-sonnet", 3_000, 0)));
+        ledger.record(&record("2026-10-02", None, usage("llama3", 500, 500)));
+
+        // One JSON object per line; unreadable lines are skipped
+        let path = dir.join(USAGE_LEDGER_FILE);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "not a record").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 4);
+
+        let by_model = ledger.totals(UsageGrouping::Model, &UsageFilter::default()).unwrap();
+        assert_eq!(by_model.len(), 2);
+        let (sonnet, llama) = (&by_model[0], &by_model[1]);
+        assert_eq!((sonnet.key.as_str(), sonnet.calls), ("claude-3-7-sonnet", 2));
+        assert_eq!((sonnet.input_tokens, sonnet.output_tokens, sonnet.latency_ms), (4_000, 2_000, 200));
+        assert_close(sonnet.cost_usd, 0.042);
+        assert_eq!((llama.key.as_str(), llama.unpriced_calls), ("llama3", 1));
+        assert_close(llama.cost_usd, 0.0);
+
+        let by_project = ledger.totals(UsageGrouping::Project, &UsageFilter::default()).unwrap();
+        let keys: Vec<(&str, u64)> = by_project.iter().map(|total| (total.key.as_str(), total.calls)).collect();
+        assert_eq!(keys, [("", 1), ("/work/shop", 2)]);
+    }
+
+    #[test]
+    fn filters_records_by_day_and_project() {
+        let (ledger, _) = ledger("filter");
+        assert!(ledger.records(&UsageFilter::default()).unwrap().is_empty());
+        for day in ["2026-09-30", "2026-10-01", "2026-10-02"] {
+            ledger.record(&record(day, Some("/work/shop"), usage("gpt-4o", 10, 10)));
+        }
+        ledger.record(&record("2026-10-01", Some("/work/blog"), usage("gpt-4o", 10, 10)));
+
+        let filter = UsageFilter {
+            since: Some("2026-10-01".to_string()),
+            until: Some("2026-10-01".to_string()),
+            project: None,
+        };
+        assert_eq!(ledger.records(&filter).unwrap().len(), 2);
+
+        let filter = UsageFilter { project: Some("/work/shop".to_string()), ..filter };
+        let by_day = ledger.totals(UsageGrouping::Day, &filter).unwrap();
+        assert_eq!(by_day.len(), 1);
+        assert_eq!((by_day[0].key.as_str(), by_day[0].calls), ("2026-10-01", 1));
+
+        assert!(UsageLedger::new().records(&UsageFilter::default()).is_err());
+    }
+}
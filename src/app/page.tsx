@@ -27,6 +27,7 @@ import { useTestGenerationState } from "../hooks/useTestGenerationState";
 // Import our Tauri API wrapper
 import { selectDirectory } from "../lib/api/tauri";
 import { invoke } from "@tauri-apps/api/core";
+import { initializeAgentFromStorage } from "../lib/agent-utils";
 
 // Replace Progress component with a div
 const Progress: React.FC<{ value: number }> = ({ value }) => (
@@ -92,39 +93,12 @@ export default function HomePage() {
     analysisState.setFileGenerationStatus
   );
   
-  // Initialize the agent from localStorage when the page loads
+  // Initialize the agent with the stored API key when the page loads
   useEffect(() => {
     const initAgent = async () => {
-      try {
-        // First check if the agent is already initialized
-        const isInitialized = await invoke("is_agent_initialized") as boolean;
-        if (isInitialized) {
-          console.log("Agent is already initialized");
-          return;
-        }
-        
-        // If not initialized, try to initialize with the saved API key
-        const savedApiKey = localStorage.getItem("anthropic_api_key");
-        if (savedApiKey) {
-          console.log("Initializing agent with saved API key");
-          
-          // Direct invocation with camelCase parameter name
-          await invoke("initialize_agent", { 
-            apiKey: savedApiKey 
-          });
-          
-          console.log("Agent initialized successfully");
-        } else {
-          console.log("No API key found in localStorage");
-        }
-      } catch (error) {
-        console.error("Failed to initialize agent:", error);
-        // Show specific error details for debugging
-        if (error instanceof Error) {
-          console.error("Error message:", error.message);
-        } else {
-          console.error("Unknown error:", error);
-        }
+      const initialized = await initializeAgentFromStorage();
+      if (!initialized) {
+        console.log("No stored API key found");
       }
     };
     
@@ -161,9 +135,9 @@ export default function HomePage() {
       return;
     }
     
-    // Check if we have an API key set
-    const savedApiKey = localStorage.getItem("anthropic_api_key");
-    if (!savedApiKey) {
+    // Check if the agent can be initialized with a stored API key
+    const agentReady = await initializeAgentFromStorage();
+    if (!agentReady) {
       // If no API key is set, show an error and redirect to settings
       toast.error("Please set an Anthropic API key in Settings before generating tests");
       setShowApiKeyModal(true);
@@ -15,40 +15,34 @@ import Link from "next/link";
 import { invoke } from "@tauri-apps/api/core";
 import { BaseDirectorySetting } from "@/components/BaseDirectorySetting";
 import { toast } from "sonner";
+import { setApiKey, clearApiKey, hasApiKey } from "@/lib/api/tauri";
+import { migrateLegacyApiKey } from "@/lib/agent-utils";
 
 export default function SettingsPage() {
   const [isFilterExpanded, setIsFilterExpanded] = useState<boolean>(true);
   const [detectedRepoType, setDetectedRepoType] = useState<RepoType | undefined>(undefined);
   const [anthropicApiKey, setAnthropicApiKey] = useState<string>("");
   const [isApiKeyInitialized, setIsApiKeyInitialized] = useState<boolean>(false);
+  const [hasStoredApiKey, setHasStoredApiKey] = useState<boolean>(false);
   const [isSaving, setIsSaving] = useState<boolean>(false);
   const [saveSuccess, setSaveSuccess] = useState<boolean>(false);
   const [saveError, setSaveError] = useState<string | null>(null);
   
   const filterHooks = useFileFilters();
 
-  // Check if agent is already initialized when component mounts
+  // Check for a stored API key when the component mounts
   useEffect(() => {
     const checkInitialization = async () => {
       try {
-        const initialized = await invoke("is_agent_initialized") as boolean;
-        setIsApiKeyInitialized(initialized);
+        // Keys are kept by the backend; move one left in localStorage by older versions
+        await migrateLegacyApiKey();
         
-        // If initialized but no key in localStorage, try to save it
-        if (initialized) {
-          const savedApiKey = localStorage.getItem("anthropic_api_key");
-          if (savedApiKey) {
-            setAnthropicApiKey(savedApiKey);
-          } else {
-            // This is a case where the agent is initialized but we don't have the key in storage
-            try {
-              await invoke("initialize_agent", { apiKey: savedApiKey });
-              setIsApiKeyInitialized(true);
-            } catch (error) {
-              console.error("Failed to reinitialize agent:", error);
-            }
-          }
-        }
+        const [initialized, storedApiKey] = await Promise.all([
+          invoke("is_agent_initialized") as Promise<boolean>,
+          hasApiKey()
+        ]);
+        setIsApiKeyInitialized(initialized);
+        setHasStoredApiKey(storedApiKey);
       } catch (error) {
         console.error("Failed to check agent initialization:", error);
       }
@@ -57,7 +51,7 @@ export default function SettingsPage() {
     checkInitialization();
   }, []);
   
-  // Save the API key to localStorage and initialize the agent
+  // Store the API key in the backend, which also initializes the agent
   const saveAnthropicApiKey = async () => {
     if (!anthropicApiKey.trim()) {
       return;
@@ -66,47 +60,31 @@ export default function SettingsPage() {
     setIsSaving(true);
     
     try {
-      // Save API key to localStorage
-      localStorage.setItem("anthropic_api_key", anthropicApiKey);
-      
-      // Initialize the agent with the API key
-      try {
-        await invoke("initialize_agent", { apiKey: anthropicApiKey });
-        setIsApiKeyInitialized(true);
-        toast.success("API key saved and agent initialized successfully!");
-      } catch (error) {
-        console.error("Failed to initialize agent:", error);
-        toast.error("Failed to initialize agent. Please check your API key.");
-      }
+      await setApiKey(anthropicApiKey);
+      // The key is not kept in the page once the backend has it
+      setAnthropicApiKey("");
+      setHasStoredApiKey(true);
+      setIsApiKeyInitialized(true);
+      toast.success("API key saved and agent initialized successfully!");
     } catch (error) {
       console.error("Failed to save API key:", error);
-      toast.error("Failed to save API key.");
+      toast.error("Failed to save API key. Please check your API key.");
     } finally {
       setIsSaving(false);
     }
   };
-
-  // Load the API key from localStorage on component mount
-  useEffect(() => {
-    const savedApiKey = localStorage.getItem("anthropic_api_key");
-    if (savedApiKey) {
-      setAnthropicApiKey(savedApiKey);
-      
-      // If we have a saved API key but the agent is not initialized, initialize it
-      if (!isApiKeyInitialized) {
-        const initializeAgent = async () => {
-          try {
-            await invoke("initialize_agent", { apiKey: savedApiKey });
-            setIsApiKeyInitialized(true);
-          } catch (error) {
-            console.error("Failed to initialize agent with saved API key:", error);
-          }
-        };
-        
-        initializeAgent();
-      }
+  
+  // Remove the stored API key
+  const clearAnthropicApiKey = async () => {
+    try {
+      await clearApiKey();
+      setHasStoredApiKey(false);
+      toast.success("API key removed");
+    } catch (error) {
+      console.error("Failed to remove API key:", error);
+      toast.error("Failed to remove API key.");
     }
-  }, [isApiKeyInitialized]);
+  };
   
   // Handle base directory change
   const handleBaseDirectoryChange = (path: string) => {
@@ -281,7 +259,7 @@ export default function SettingsPage() {
                         type="password"
                         value={anthropicApiKey}
                         onChange={(e: React.ChangeEvent<HTMLInputElement>) => setAnthropicApiKey(e.target.value)}
-                        placeholder="Enter your Anthropic API key"
+                        placeholder={hasStoredApiKey ? "A key is stored; enter a new one to replace it" : "Enter your Anthropic API key"}
                         className="flex-1"
                       />
                       <Button 
@@ -290,6 +268,11 @@ export default function SettingsPage() {
                       >
                         {isSaving ? "Saving..." : "Save"}
                       </Button>
+                      {hasStoredApiKey && (
+                        <Button variant="outline" onClick={clearAnthropicApiKey} disabled={isSaving}>
+                          Remove
+                        </Button>
+                      )}
                     </div>
                     {saveSuccess && (
                       <p className="text-sm text-green-600 mt-2">
@@ -310,7 +293,7 @@ export default function SettingsPage() {
                   <div className="bg-blue-50 p-4 rounded-md border border-blue-200">
                     <h3 className="text-sm font-medium text-blue-800 mb-2">About API Keys</h3>
                     <p className="text-sm text-blue-700">
-                      Your API keys are stored locally on your device and are only used to authenticate with the respective services.
+                      Your API keys are stored encrypted on your device and are only used to authenticate with the respective services.
                       We never transmit your keys to our servers.
                     </p>
                     <p className="text-sm text-blue-700 mt-2">
@@ -159,17 +159,8 @@ export function useTestGenerationState(
       
       // Initialize agent if needed
       if (!isInitialized) {
-        const savedApiKey = localStorage.getItem("anthropic_api_key");
-        if (savedApiKey) {
-          try {
-            console.log("Initializing agent with saved API key");
-            // Use camelCase parameter names as expected by Tauri v2
-            await invoke("initialize_agent", { apiKey: savedApiKey });
-          } catch (initError) {
-            console.error("Error initializing agent:", initError);
-            throw new Error("Failed to initialize agent. Please check your API key in Settings.");
-          }
-        } else {
+        const initialized = await initializeAgentFromStorage();
+        if (!initialized) {
           throw new Error("Agent service has not been initialized with an API key. Please set an API key in the Settings page.");
         }
       }
@@ -3,7 +3,33 @@ import { Command } from '@tauri-apps/plugin-shell';
 import path from 'path-browserify';
 
 /**
- * Initializes the agent with the API key from localStorage if available
+ * localStorage key older versions kept the Anthropic API key in
+ */
+const LEGACY_API_KEY_STORAGE_KEY = "anthropic_api_key";
+
+/**
+ * Moves an API key left in localStorage by older versions into the backend's encrypted store
+ * @returns A promise that resolves to a boolean indicating if a key was migrated
+ */
+export async function migrateLegacyApiKey(): Promise<boolean> {
+  const legacyApiKey = localStorage.getItem(LEGACY_API_KEY_STORAGE_KEY);
+  if (!legacyApiKey) {
+    return false;
+  }
+
+  try {
+    await invoke("set_api_key", { apiKey: legacyApiKey });
+    localStorage.removeItem(LEGACY_API_KEY_STORAGE_KEY);
+    console.log("Moved the saved API key to the backend");
+    return true;
+  } catch (error) {
+    console.error("Failed to move the saved API key to the backend:", error);
+    return false;
+  }
+}
+
+/**
+ * Initializes the agent with the API key stored in the backend if there is one
  * @returns A promise that resolves to a boolean indicating if the agent was initialized
  */
 export async function initializeAgentFromStorage(): Promise<boolean> {
@@ -15,12 +41,18 @@ export async function initializeAgentFromStorage(): Promise<boolean> {
       return true;
     }
     
-    // If not initialized, try to initialize with the saved API key
-    const savedApiKey = localStorage.getItem("anthropic_api_key");
-    if (savedApiKey) {
+    // Storing a key left in localStorage initializes the agent with it
+    if (await migrateLegacyApiKey()) {
+      return true;
+    }
+    
+    // If not initialized, try to initialize with the stored API key
+    const hasStoredApiKey = await invoke("has_api_key") as boolean;
+    if (hasStoredApiKey) {
       try {
-        console.log("Initializing agent with saved API key");
-        await invoke("initialize_agent", { apiKey: savedApiKey });
+        console.log("Initializing agent with stored API key");
+        // An empty key makes the backend use the stored one
+        await invoke("initialize_agent", { apiKey: "" });
         console.log("Agent initialized successfully");
         return true;
       } catch (error) {
@@ -214,12 +246,12 @@ export async function checkPackageInstallation(language: string): Promise<boolea
 }
 
 /**
- * Initializes the test generation agent with an API key
+ * Stores an API key in the backend and initializes the test generation agent with it
  */
 export async function initializeAgent(api_key: string): Promise<boolean> {
   try {
     console.log("Initializing agent with provided API key");
-    await invoke("initialize_agent", { apiKey: api_key });
+    await invoke("set_api_key", { apiKey: api_key });
     console.log("Agent initialized successfully");
     return true;
   } catch (error) {
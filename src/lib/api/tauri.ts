@@ -60,15 +60,53 @@ export async function findTestFiles(directory: string): Promise<any> {
 
 /**
  * Generates a test for a source file
+ *
+ * Pass `stream: true` to receive `agent:token` / `agent:done` / `agent:error`
+ * events tagged with `requestId` while the test is generated. Passing a
+ * `requestId` also allows the request to be stopped with `cancelAgentRequest`.
+ * With `repairAttempts`, the written test is run and failures are sent back to
+ * the agent for repair; each run is reported as an `agent:test-run` event.
+ * Signatures from the file's local imports are sent along unless
+ * `includeDependencies` is false. With `augment`, an existing test file is kept
+ * and only missing cases are generated and merged into it. Source files longer
+ * than `maxChunkChars` are generated in chunks and the tests merged into one file.
+ * Identical requests are answered from the response cache unless `useCache` is false.
+ * Generated code that doesn't parse is sent for repair when `repairAttempts` is
+ * set; otherwise the request fails with the syntax errors and nothing is written.
+ * With `structured`, a new test file is written where the agent suggests, as
+ * long as that is a test path inside the project. Without `testFramework`, the
+ * project's framework is detected from its manifests. With `useCoverage`, the
+ * newest coverage report in the project is read and the file's uncovered lines
+ * and branches are targeted; the new tests are merged into the existing test
+ * file as with `augment`. With `useTools`, the agent can read, list and search
+ * project files and find existing tests before answering, up to `maxToolSteps`
+ * calls; each call is reported as an `agent:tool-call` event.
  */
-export async function generateTest(projectPath: string, filePath: string, language: string = 'typescript', testFramework?: string): Promise<any> {
+export async function generateTest(
+  projectPath: string,
+  filePath: string,
+  language: string = 'typescript',
+  testFramework?: string,
+  options: { stream?: boolean; requestId?: string; repairAttempts?: number; includeDependencies?: boolean; augment?: boolean; maxChunkChars?: number; useCache?: boolean; structured?: boolean; useCoverage?: boolean; useTools?: boolean; maxToolSteps?: number } = {}
+): Promise<any> {
   try {
     // Using camelCase parameter names as required by Tauri v2 convention
     return await invoke('generate_and_write_test', { 
       directory: projectPath, 
       sourceFile: filePath,
       language,
-      testFramework
+      testFramework,
+      stream: options.stream,
+      requestId: options.requestId,
+      repairAttempts: options.repairAttempts,
+      includeDependencies: options.includeDependencies,
+      augment: options.augment,
+      maxChunkChars: options.maxChunkChars,
+      useCache: options.useCache,
+      structured: options.structured,
+      useCoverage: options.useCoverage,
+      useTools: options.useTools,
+      maxToolSteps: options.maxToolSteps
     });
   } catch (error) {
     console.error('Error generating test:', error);
@@ -76,6 +114,285 @@ export async function generateTest(projectPath: string, filePath: string, langua
   }
 }
 
+/**
+ * Generates tests for one function, method or class of a source file and adds
+ * them to the file's test file, keeping existing tests
+ *
+ * `symbolName` may be qualified with its class or type (`Parser.parse`) when the
+ * name alone is ambiguous. Resolves to the path of the test file. Streaming,
+ * cancellation, repair and `useCoverage` work as in `generateTest`.
+ */
+export async function generateTestsForSymbol(
+  projectPath: string,
+  filePath: string,
+  symbolName: string,
+  options: {
+    language?: string;
+    testFramework?: string;
+    stream?: boolean;
+    requestId?: string;
+    repairAttempts?: number;
+    includeDependencies?: boolean;
+    useCache?: boolean;
+    useCoverage?: boolean;
+  } = {}
+): Promise<string> {
+  try {
+    return await invoke('generate_tests_for_symbol', {
+      directory: projectPath,
+      sourceFile: filePath,
+      symbolName,
+      language: options.language,
+      testFramework: options.testFramework,
+      stream: options.stream,
+      requestId: options.requestId,
+      repairAttempts: options.repairAttempts,
+      includeDependencies: options.includeDependencies,
+      useCache: options.useCache,
+      useCoverage: options.useCoverage
+    });
+  } catch (error) {
+    console.error('Error generating tests for symbol:', error);
+    throw new Error(`Failed to generate tests for ${symbolName}: ${error}`);
+  }
+}
+
+export interface GeneratedTestCase {
+  name: string;
+  description: string;
+}
+
+export interface GeneratedTestSuite {
+  /** Where the agent suggests writing the file, relative to the project root */
+  test_path: string | null;
+  content: string;
+  covered_functions: string[];
+  test_cases: GeneratedTestCase[];
+  dev_dependencies: string[];
+}
+
+/**
+ * Generates tests as a structured suite describing what each test covers
+ *
+ * `sourcePath` (relative to the project root) lets the agent suggest where the
+ * test file goes. With `projectDir` and no `testFramework`, the project's test
+ * framework is detected from its manifests. With `stream: true`, the raw JSON
+ * is emitted as `agent:token` events tagged with `requestId`.
+ */
+export async function generateTestSuite(
+  code: string,
+  language: string,
+  options: { testFramework?: string; sourcePath?: string; projectDir?: string; stream?: boolean; requestId?: string; useCache?: boolean } = {}
+): Promise<GeneratedTestSuite> {
+  try {
+    return await invoke('generate_test_suite', {
+      code,
+      language,
+      testFramework: options.testFramework,
+      sourcePath: options.sourcePath,
+      projectDir: options.projectDir,
+      stream: options.stream,
+      requestId: options.requestId,
+      useCache: options.useCache
+    });
+  } catch (error) {
+    console.error('Error generating test suite:', error);
+    throw new Error(`Failed to generate test suite: ${error}`);
+  }
+}
+
+export interface TestSyntaxError {
+  /** 1-based line of the error */
+  line: number;
+  /** 1-based column of the error */
+  column: number;
+  message: string;
+}
+
+/**
+ * Parses test code with the grammar for its language and returns its syntax errors
+ * Returns an empty list for valid code and for languages without a grammar
+ */
+export async function checkTestSyntax(code: string, language: string): Promise<TestSyntaxError[]> {
+  try {
+    return await invoke('check_test_syntax', { code, language });
+  } catch (error) {
+    console.error('Error checking test syntax:', error);
+    throw error;
+  }
+}
+
+export interface DetectedFramework {
+  /** Framework name, e.g. `vitest` or `pytest` */
+  name: string;
+  /** Version required by the project, without range operators */
+  version: string | null;
+  /** Manifest the framework was found in, relative to the project root */
+  manifest: string;
+}
+
+/**
+ * Detects the test framework a project uses for a language from its manifests
+ * Manifests nearest to `sourceFile` win; resolves to null if none names a framework
+ */
+export async function detectTestFramework(
+  projectPath: string,
+  language: string,
+  sourceFile?: string
+): Promise<DetectedFramework | null> {
+  try {
+    return await invoke('detect_project_test_framework', { directory: projectPath, language, sourceFile });
+  } catch (error) {
+    console.error('Error detecting test framework:', error);
+    throw new Error(`Failed to detect test framework: ${error}`);
+  }
+}
+
+export interface ReviewFinding {
+  /** 1-based line the finding refers to */
+  line: number;
+  issue: string;
+  suggestion: string;
+}
+
+export interface SuggestedCase {
+  name: string;
+  description: string;
+  /** 1-based line of the source file the case exercises */
+  line: number;
+}
+
+export interface TestReview {
+  summary: string;
+  /** Lines refer to the source file */
+  untested_branches: ReviewFinding[];
+  /** Lines refer to the test file */
+  weak_assertions: ReviewFinding[];
+  /** Lines refer to the test file */
+  brittle_mocks: ReviewFinding[];
+  suggested_cases: SuggestedCase[];
+}
+
+/**
+ * Reviews a source file's existing tests for untested branches, weak
+ * assertions, brittle mocks and missing cases
+ *
+ * `testFile` is the match from `findTestFiles`; it is looked up when omitted.
+ * The review can be cancelled with `cancelAgentRequest(requestId)`.
+ */
+export async function reviewTestFile(
+  projectPath: string,
+  sourceFile: string,
+  options: { testFile?: string; language?: string; testFramework?: string; requestId?: string } = {}
+): Promise<TestReview> {
+  try {
+    return await invoke('review_test_file', {
+      directory: projectPath,
+      sourceFile,
+      testFile: options.testFile,
+      language: options.language,
+      testFramework: options.testFramework,
+      requestId: options.requestId
+    });
+  } catch (error) {
+    console.error('Error reviewing test file:', error);
+    throw new Error(`Failed to review tests for ${sourceFile}: ${error}`);
+  }
+}
+
+export interface LineRange {
+  start: number;
+  end: number;
+}
+
+export interface UncoveredBranch {
+  line: number;
+  /** e.g. `1 of 2 branches not taken` */
+  description: string;
+}
+
+export interface FileCoverage {
+  /** Path as reported, relative to the project root when inside it */
+  path: string;
+  lines_found: number;
+  lines_hit: number;
+  uncovered_lines: LineRange[];
+  uncovered_branches: UncoveredBranch[];
+}
+
+export interface ProjectCoverage {
+  format: 'lcov' | 'cobertura' | 'coverage_py';
+  report_path: string;
+  /** Coverage of each source file of the analysis, keyed like `source_files` */
+  files: Record<string, FileCoverage>;
+}
+
+/**
+ * Reads an lcov, Cobertura or coverage.py JSON report and maps it onto the
+ * source files found by `findTestFiles`
+ *
+ * `reportPath` is relative to the project; the newest report found in the
+ * project is used when omitted. Resolves to null if there is no report.
+ */
+export async function loadCoverage(
+  projectPath: string,
+  analysis: any,
+  reportPath?: string
+): Promise<ProjectCoverage | null> {
+  try {
+    return await invoke('load_coverage', { directory: projectPath, analysis, reportPath });
+  } catch (error) {
+    console.error('Error loading coverage:', error);
+    throw new Error(`Failed to load coverage: ${error}`);
+  }
+}
+
+/**
+ * Generates and writes tests for many files of a project at once
+ *
+ * `analysis` is the result of `findTestFiles`; every source file without a
+ * test is generated unless `files` selects a subset. Up to `concurrency` files
+ * are generated at a time, each reported as an `agent:batch-progress` event
+ * when it starts and finishes, followed by an `agent:batch-done` summary, all
+ * tagged with `batchId`. Cancelling `batchId` stops the whole batch; cancelling
+ * a file's own request ID only stops that file. With `useCoverage`, each file's
+ * uncovered lines and branches from the project's coverage report are targeted.
+ */
+export async function generateTestsForProject(
+  projectPath: string,
+  analysis: any,
+  options: {
+    files?: string[];
+    language?: string;
+    testFramework?: string;
+    concurrency?: number;
+    batchId?: string;
+    repairAttempts?: number;
+    includeDependencies?: boolean;
+    useCache?: boolean;
+    useCoverage?: boolean;
+  } = {}
+): Promise<BatchSummary> {
+  try {
+    return await invoke('generate_tests_for_project', {
+      directory: projectPath,
+      analysis,
+      files: options.files,
+      language: options.language,
+      testFramework: options.testFramework,
+      concurrency: options.concurrency,
+      batchId: options.batchId,
+      repairAttempts: options.repairAttempts,
+      includeDependencies: options.includeDependencies,
+      useCache: options.useCache,
+      useCoverage: options.useCoverage
+    });
+  } catch (error) {
+    console.error('Error generating tests for project:', error);
+    throw new Error(`Failed to generate tests for project: ${error}`);
+  }
+}
+
 /**
  * Starts watching a directory for file changes
  */
@@ -113,6 +430,147 @@ export async function listenForFileChanges(callback: (event: any) => void): Prom
   }
 }
 
+/**
+ * Payloads of the events emitted while an agent request streams its output
+ */
+export interface AgentTokenEvent {
+  request_id: string;
+  token: string;
+}
+
+export interface AgentDoneEvent {
+  request_id: string;
+  content: string;
+  test_file_path: string | null;
+}
+
+export interface AgentErrorEvent {
+  request_id: string;
+  error: string;
+}
+
+export interface AgentRetryEvent {
+  request_id: string;
+  attempt: number;
+  max_retries: number;
+  kind: 'rate_limited' | 'overloaded' | 'network' | 'auth' | 'context_length' | 'other';
+  delay_ms: number;
+  error: string;
+}
+
+export interface BatchFileResult {
+  source_file: string;
+  request_id: string;
+  status: 'started' | 'succeeded' | 'failed';
+  test_file_path: string | null;
+  error: string | null;
+}
+
+export interface BatchSummary {
+  batch_id: string;
+  total: number;
+  succeeded: number;
+  failed: number;
+  results: BatchFileResult[];
+}
+
+export interface AgentBatchProgressEvent {
+  request_id: string;
+  file: BatchFileResult;
+  completed: number;
+  total: number;
+}
+
+export interface AgentBatchDoneEvent {
+  request_id: string;
+  summary: BatchSummary;
+}
+
+/**
+ * A likely secret that was replaced before code was sent to the LLM; the secret itself is never included
+ */
+export interface Redaction {
+  kind: 'api_key' | 'private_key' | 'high_entropy' | 'assignment' | 'custom';
+  /** Built-in rule or user pattern that matched */
+  rule: string;
+  /** File (or part of the prompt) the secret was in */
+  location: string;
+  line: number;
+  placeholder: string;
+}
+
+export interface RedactionReport {
+  redactions: Redaction[];
+}
+
+export interface AgentRedactionEvent {
+  request_id: string;
+  report: RedactionReport;
+}
+
+/**
+ * A project tool the agent called while generating tests; the tool's output is not included
+ */
+export interface ToolStep {
+  /** Which call this is, starting at 1 */
+  step: number;
+  tool: 'read_file' | 'list_dir' | 'grep' | 'find_test_for' | string;
+  input: Record<string, unknown>;
+  /** Length of the output sent back to the model */
+  output_chars: number;
+  error?: string | null;
+}
+
+export interface AgentToolCallEvent {
+  request_id: string;
+  call: ToolStep;
+}
+
+/**
+ * Listens for streamed agent output, retries of failed LLM calls, batch job progress, redacted secrets and tool calls
+ * Returns a function that removes all the listeners
+ */
+export async function listenForAgentEvents(handlers: {
+  onToken?: (event: AgentTokenEvent) => void;
+  onDone?: (event: AgentDoneEvent) => void;
+  onError?: (event: AgentErrorEvent) => void;
+  onRetry?: (event: AgentRetryEvent) => void;
+  onBatchProgress?: (event: AgentBatchProgressEvent) => void;
+  onBatchDone?: (event: AgentBatchDoneEvent) => void;
+  onRedaction?: (event: AgentRedactionEvent) => void;
+  onToolCall?: (event: AgentToolCallEvent) => void;
+}): Promise<() => void> {
+  try {
+    const unlisteners = await Promise.all([
+      listen<AgentTokenEvent>('agent:token', (event) => handlers.onToken?.(event.payload)),
+      listen<AgentDoneEvent>('agent:done', (event) => handlers.onDone?.(event.payload)),
+      listen<AgentErrorEvent>('agent:error', (event) => handlers.onError?.(event.payload)),
+      listen<AgentRetryEvent>('agent:retry', (event) => handlers.onRetry?.(event.payload)),
+      listen<AgentBatchProgressEvent>('agent:batch-progress', (event) => handlers.onBatchProgress?.(event.payload)),
+      listen<AgentBatchDoneEvent>('agent:batch-done', (event) => handlers.onBatchDone?.(event.payload)),
+      listen<AgentRedactionEvent>('agent:redaction', (event) => handlers.onRedaction?.(event.payload)),
+      listen<AgentToolCallEvent>('agent:tool-call', (event) => handlers.onToolCall?.(event.payload)),
+    ]);
+    return () => unlisteners.forEach((unlisten) => unlisten());
+  } catch (error) {
+    console.error('Error setting up agent event listeners:', error);
+    throw new Error(`Failed to set up agent event listeners: ${error}`);
+  }
+}
+
+/**
+ * Cancels an in-flight agent request
+ * Resolves to false if no request with that ID is running
+ */
+export async function cancelAgentRequest(requestId: string): Promise<boolean> {
+  try {
+    return await invoke('cancel_agent_request', { requestId }) as boolean;
+  } catch (error) {
+    console.error('Error cancelling agent request:', error);
+    throw new Error(`Failed to cancel agent request: ${error}`);
+  }
+}
+
 /**
  * Checks if a package is installed for a specific language
  */
@@ -126,16 +584,471 @@ export async function checkPackageInstallation(language: string): Promise<boolea
   }
 }
 
+/**
+ * LLM provider settings accepted by `initialize_agent`
+ */
+export interface AgentProviderOptions {
+  provider?: 'anthropic' | 'openai' | 'openai_compatible' | 'mock';
+  model?: string;
+  baseUrl?: string;
+  maxConcurrentRequests?: number;
+  maxRetries?: number;
+  /** Directory of recorded responses; required for the mock provider and for recording */
+  fixturesDir?: string;
+  /** Save every response of a real provider as a fixture in `fixturesDir` */
+  recordFixtures?: boolean;
+}
+
 /**
  * Initializes the test generation agent
  */
-export async function initializeAgent(api_key: string): Promise<boolean> {
+export async function initializeAgent(api_key: string, options: AgentProviderOptions = {}): Promise<boolean> {
   try {
     // Use camelCase parameter name as expected by Tauri v2
-    const result = await invoke('initialize_agent', { apiKey: api_key });
+    const result = await invoke('initialize_agent', {
+      apiKey: api_key,
+      provider: options.provider,
+      model: options.model,
+      baseUrl: options.baseUrl,
+      maxConcurrentRequests: options.maxConcurrentRequests,
+      maxRetries: options.maxRetries,
+      fixturesDir: options.fixturesDir,
+      recordFixtures: options.recordFixtures
+    });
     return !!result;
   } catch (error) {
     console.error(`Error initializing agent:`, error);
     return false;
   }
-} 
\ No newline at end of file
+} 
+
+/**
+ * Stores a provider's API key in the OS keychain (or an encrypted file). The agent
+ * switches to the new key, keeping its model and base URL, if it uses that provider,
+ * and is initialized with the provider's defaults if it isn't initialized yet
+ */
+export async function setApiKey(apiKey: string, provider?: 'anthropic' | 'openai'): Promise<void> {
+  try {
+    await invoke('set_api_key', { apiKey, provider });
+  } catch (error) {
+    console.error('Error storing API key:', error);
+    throw new Error(`Failed to store API key: ${error}`);
+  }
+}
+
+/**
+ * Removes a provider's stored API key; resolves to false if none was stored
+ */
+export async function clearApiKey(provider?: 'anthropic' | 'openai'): Promise<boolean> {
+  try {
+    return await invoke('clear_api_key', { provider }) as boolean;
+  } catch (error) {
+    console.error('Error clearing API key:', error);
+    throw new Error(`Failed to clear API key: ${error}`);
+  }
+}
+
+/**
+ * Removes the stored API keys of every provider
+ *
+ * The way out when the key store can no longer be read; storing a key fails until then.
+ */
+export async function resetApiKeyStore(): Promise<void> {
+  try {
+    await invoke('reset_api_key_store');
+  } catch (error) {
+    console.error('Error resetting the API key store:', error);
+    throw new Error(`Failed to reset the API key store: ${error}`);
+  }
+}
+
+/**
+ * Checks whether an API key is stored for a provider, without reading it
+ */
+export async function hasApiKey(provider?: 'anthropic' | 'openai'): Promise<boolean> {
+  try {
+    return await invoke('has_api_key', { provider }) as boolean;
+  } catch (error) {
+    console.error('Error checking for a stored API key:', error);
+    throw new Error(`Failed to check for a stored API key: ${error}`);
+  }
+}
+/**
+ * A prompt template and where its effective version was loaded from
+ */
+export interface PromptTemplate {
+  key: string;
+  name: string;
+  language: string | null;
+  framework: string | null;
+  source: 'builtin' | 'app' | 'project';
+  path: string | null;
+  content: string;
+}
+
+/**
+ * Lists the effective prompt templates, including a project's overrides
+ */
+export async function listPromptTemplates(projectDir?: string): Promise<PromptTemplate[]> {
+  try {
+    return await invoke('list_prompt_templates', { projectDir }) as PromptTemplate[];
+  } catch (error) {
+    console.error('Error listing prompt templates:', error);
+    throw new Error(`Failed to list prompt templates: ${error}`);
+  }
+}
+
+/**
+ * Reads the effective version of a prompt template
+ */
+export async function readPromptTemplate(key: string, projectDir?: string): Promise<PromptTemplate> {
+  try {
+    return await invoke('read_prompt_template', { key, projectDir }) as PromptTemplate;
+  } catch (error) {
+    console.error('Error reading prompt template:', error);
+    throw new Error(`Failed to read prompt template: ${error}`);
+  }
+}
+
+/**
+ * Saves a prompt template for every project (`app`) or only for one project (`project`)
+ */
+export async function savePromptTemplate(
+  key: string,
+  content: string,
+  scope: 'app' | 'project',
+  projectDir?: string
+): Promise<PromptTemplate> {
+  try {
+    return await invoke('save_prompt_template', { key, content, scope, projectDir }) as PromptTemplate;
+  } catch (error) {
+    console.error('Error saving prompt template:', error);
+    throw new Error(`Failed to save prompt template: ${error}`);
+  }
+}
+
+/**
+ * Token usage and cost of a group of agent calls
+ */
+export interface UsageTotal {
+  key: string;
+  calls: number;
+  input_tokens: number;
+  output_tokens: number;
+  cost_usd: number;
+  unpriced_calls: number;
+  estimated_calls: number;
+  latency_ms: number;
+}
+
+/**
+ * One recorded agent call
+ */
+export interface UsageRecord {
+  timestamp: string;
+  day: string;
+  operation: 'simple_invoke' | 'chain' | 'test_generation' | 'test_repair' | 'chat';
+  project: string | null;
+  request_id: string | null;
+  model: string;
+  input_tokens: number;
+  output_tokens: number;
+  estimated: boolean;
+  latency_ms: number;
+  cost_usd: number | null;
+}
+
+/**
+ * Days (`YYYY-MM-DD`) and project to restrict a usage query to
+ */
+export interface UsageQuery {
+  since?: string;
+  until?: string;
+  projectDir?: string;
+}
+
+/**
+ * Sums agent token usage and cost by day, project, operation or model
+ */
+export async function getUsageTotals(
+  groupBy: 'day' | 'project' | 'operation' | 'model',
+  query: UsageQuery = {}
+): Promise<UsageTotal[]> {
+  try {
+    return await invoke('get_usage_totals', { groupBy, ...query }) as UsageTotal[];
+  } catch (error) {
+    console.error('Error getting usage totals:', error);
+    throw new Error(`Failed to get usage totals: ${error}`);
+  }
+}
+
+/**
+ * Lists recorded agent calls, oldest first
+ */
+export async function listUsageRecords(query: UsageQuery = {}): Promise<UsageRecord[]> {
+  try {
+    return await invoke('list_usage_records', { ...query }) as UsageRecord[];
+  } catch (error) {
+    console.error('Error listing usage records:', error);
+    throw new Error(`Failed to list usage records: ${error}`);
+  }
+}
+
+/**
+ * Number and size of the cached test generation responses, and the cache limits
+ */
+export interface ResponseCacheStats {
+  entries: number;
+  bytes: number;
+  ttl_secs: number;
+  max_bytes: number;
+}
+
+/**
+ * Gets the size and limits of the response cache
+ */
+export async function getResponseCacheStats(): Promise<ResponseCacheStats> {
+  try {
+    return await invoke('get_response_cache_stats') as ResponseCacheStats;
+  } catch (error) {
+    console.error('Error getting response cache stats:', error);
+    throw new Error(`Failed to get response cache stats: ${error}`);
+  }
+}
+
+/**
+ * Removes every cached response, resolving to how many were removed
+ */
+export async function clearResponseCache(): Promise<number> {
+  try {
+    return await invoke('clear_response_cache') as number;
+  } catch (error) {
+    console.error('Error clearing response cache:', error);
+    throw new Error(`Failed to clear response cache: ${error}`);
+  }
+}
+
+/**
+ * Changes how long cached responses are used and how large the cache may grow
+ */
+export async function configureResponseCache(options: { ttlHours?: number; maxMegabytes?: number }): Promise<ResponseCacheStats> {
+  try {
+    return await invoke('configure_response_cache', {
+      ttlHours: options.ttlHours,
+      maxMegabytes: options.maxMegabytes
+    }) as ResponseCacheStats;
+  } catch (error) {
+    console.error('Error configuring response cache:', error);
+    throw new Error(`Failed to configure response cache: ${error}`);
+  }
+}
+
+/**
+ * A user pattern redacted from code in addition to the built-in secret rules
+ */
+export interface RedactionPattern {
+  name: string;
+  /** Regular expression; only the first capture group is replaced when it has one */
+  pattern: string;
+}
+
+export interface RedactionSettings {
+  enabled: boolean;
+  detect_high_entropy: boolean;
+  patterns: RedactionPattern[];
+}
+
+export interface RedactionPreview {
+  code: string;
+  report: RedactionReport;
+}
+
+/**
+ * Gets what is redacted from code before it is sent to the LLM
+ */
+export async function getRedactionSettings(): Promise<RedactionSettings> {
+  try {
+    return await invoke('get_redaction_settings') as RedactionSettings;
+  } catch (error) {
+    console.error('Error getting redaction settings:', error);
+    throw new Error(`Failed to get redaction settings: ${error}`);
+  }
+}
+
+/**
+ * Changes what is redacted from code before it is sent to the LLM
+ * Omitted options are unchanged; `patterns` replaces the current list
+ */
+export async function configureRedaction(options: {
+  enabled?: boolean;
+  detectHighEntropy?: boolean;
+  patterns?: RedactionPattern[];
+}): Promise<RedactionSettings> {
+  try {
+    return await invoke('configure_redaction', {
+      enabled: options.enabled,
+      detectHighEntropy: options.detectHighEntropy,
+      patterns: options.patterns
+    }) as RedactionSettings;
+  } catch (error) {
+    console.error('Error configuring redaction:', error);
+    throw new Error(`Failed to configure redaction: ${error}`);
+  }
+}
+
+/**
+ * Shows what would be redacted from code, without sending it anywhere
+ */
+export async function previewRedaction(code: string, location?: string): Promise<RedactionPreview> {
+  try {
+    return await invoke('preview_redaction', { code, location }) as RedactionPreview;
+  } catch (error) {
+    console.error('Error previewing redaction:', error);
+    throw new Error(`Failed to preview redaction: ${error}`);
+  }
+}
+
+/**
+ * A message in a chat session
+ */
+export interface ChatMessage {
+  role: 'user' | 'assistant';
+  content: string;
+}
+
+/**
+ * A persisted conversation with the agent
+ */
+export interface ChatSession {
+  id: string;
+  title: string;
+  created_at: string;
+  updated_at: string;
+  project_dir: string | null;
+  system_prompt: string | null;
+  attachments: string[];
+  messages: ChatMessage[];
+}
+
+export interface ChatSessionSummary {
+  id: string;
+  title: string;
+  created_at: string;
+  updated_at: string;
+  project_dir: string | null;
+  message_count: number;
+}
+
+/**
+ * Creates a chat session, optionally tied to a project so files can be attached
+ */
+export async function createChatSession(
+  options: { title?: string; projectDir?: string; systemPrompt?: string } = {}
+): Promise<ChatSession> {
+  try {
+    return await invoke('create_chat_session', {
+      title: options.title,
+      projectDir: options.projectDir,
+      systemPrompt: options.systemPrompt
+    }) as ChatSession;
+  } catch (error) {
+    console.error('Error creating chat session:', error);
+    throw new Error(`Failed to create chat session: ${error}`);
+  }
+}
+
+/**
+ * Lists chat sessions, most recently updated first
+ */
+export async function listChatSessions(): Promise<ChatSessionSummary[]> {
+  try {
+    return await invoke('list_chat_sessions') as ChatSessionSummary[];
+  } catch (error) {
+    console.error('Error listing chat sessions:', error);
+    throw new Error(`Failed to list chat sessions: ${error}`);
+  }
+}
+
+/**
+ * Loads a chat session with its full history
+ */
+export async function getChatSession(sessionId: string): Promise<ChatSession> {
+  try {
+    return await invoke('get_chat_session', { sessionId }) as ChatSession;
+  } catch (error) {
+    console.error('Error loading chat session:', error);
+    throw new Error(`Failed to load chat session: ${error}`);
+  }
+}
+
+/**
+ * Deletes a chat session, resolving to false if it didn't exist
+ */
+export async function deleteChatSession(sessionId: string): Promise<boolean> {
+  try {
+    return await invoke('delete_chat_session', { sessionId }) as boolean;
+  } catch (error) {
+    console.error('Error deleting chat session:', error);
+    throw new Error(`Failed to delete chat session: ${error}`);
+  }
+}
+
+/**
+ * Appends a message to a chat session without invoking the agent
+ */
+export async function appendChatMessage(sessionId: string, message: ChatMessage): Promise<ChatSession> {
+  try {
+    return await invoke('append_chat_message', { sessionId, role: message.role, content: message.content }) as ChatSession;
+  } catch (error) {
+    console.error('Error appending chat message:', error);
+    throw new Error(`Failed to append chat message: ${error}`);
+  }
+}
+
+/**
+ * Attaches a project file (relative to the session's project) to a chat session
+ */
+export async function attachFileToChatSession(sessionId: string, file: string): Promise<ChatSession> {
+  try {
+    return await invoke('attach_file_to_chat_session', { sessionId, file }) as ChatSession;
+  } catch (error) {
+    console.error('Error attaching file to chat session:', error);
+    throw new Error(`Failed to attach file to chat session: ${error}`);
+  }
+}
+
+/**
+ * Removes an attached file from a chat session
+ */
+export async function detachFileFromChatSession(sessionId: string, file: string): Promise<ChatSession> {
+  try {
+    return await invoke('detach_file_from_chat_session', { sessionId, file }) as ChatSession;
+  } catch (error) {
+    console.error('Error detaching file from chat session:', error);
+    throw new Error(`Failed to detach file from chat session: ${error}`);
+  }
+}
+
+/**
+ * Invokes the agent with a session's full history and resolves to its reply
+ */
+export async function invokeChatSession(sessionId: string, requestId?: string): Promise<ChatMessage> {
+  try {
+    return await invoke('invoke_chat_session', { sessionId, requestId }) as ChatMessage;
+  } catch (error) {
+    console.error('Error invoking chat session:', error);
+    throw new Error(`Failed to invoke chat session: ${error}`);
+  }
+}
+
+/**
+ * Sends a user message in a chat session and resolves to the agent's reply
+ */
+export async function sendChatMessage(sessionId: string, content: string, requestId?: string): Promise<ChatMessage> {
+  try {
+    return await invoke('send_chat_message', { sessionId, content, requestId }) as ChatMessage;
+  } catch (error) {
+    console.error('Error sending chat message:', error);
+    throw new Error(`Failed to send chat message: ${error}`);
+  }
+}